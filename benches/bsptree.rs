@@ -0,0 +1,96 @@
+//! Measures the common operations of a `BSPTree` on trees of different sizes.
+//!
+//! Run with `cargo bench --bench bsptree`.
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use dss::data_structures::bsptree::{BSPTree, Grid, MoveDirection, Rectangle, SplitPolicy};
+
+use std::hint::black_box;
+
+const SIZES: [u32; 3] = [10, 100, 1000];
+
+fn size() -> Rectangle {
+    Rectangle::new(0, 0, 3840, 2160)
+}
+
+/// A balanced grid of `leaves` leaves, the first of which is focused.
+fn grid(leaves: u32) -> BSPTree {
+    let items: Vec<u32> = (0..leaves).collect();
+    BSPTree::from_leaves(size(), &items, SplitPolicy::AutoSplit)
+}
+
+fn insert(c: &mut Criterion) {
+    let mut group = c.benchmark_group("insert");
+    for leaves in SIZES {
+        group.bench_with_input(BenchmarkId::from_parameter(leaves), &leaves, |b, &n| {
+            b.iter(|| {
+                let mut tree = BSPTree::new(size());
+                tree.set_policy(Box::new(Grid));
+                for i in 0..n {
+                    tree.insert(i).unwrap();
+                }
+                black_box(tree)
+            })
+        });
+    }
+    group.finish();
+}
+
+fn delete(c: &mut Criterion) {
+    let mut group = c.benchmark_group("delete");
+    for leaves in SIZES {
+        group.bench_with_input(BenchmarkId::from_parameter(leaves), &leaves, |b, &n| {
+            b.iter_batched(
+                || grid(n),
+                |mut tree| {
+                    while tree.delete_focused().is_ok() {}
+                    tree
+                },
+                BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+fn move_focus(c: &mut Criterion) {
+    let directions = [
+        MoveDirection::Right,
+        MoveDirection::Down,
+        MoveDirection::Left,
+        MoveDirection::Up,
+    ];
+
+    let mut group = c.benchmark_group("move_focus");
+    for leaves in SIZES {
+        let mut tree = grid(leaves);
+        let mut i = 0;
+        group.bench_function(BenchmarkId::from_parameter(leaves), |b| {
+            b.iter(|| {
+                i += 1;
+                let _ = tree.move_focus(directions[i % directions.len()]);
+            })
+        });
+    }
+    group.finish();
+}
+
+fn relayout(c: &mut Criterion) {
+    let mut group = c.benchmark_group("relayout");
+    for leaves in SIZES {
+        let mut tree = grid(leaves);
+        let mut i = 0;
+        group.bench_function(BenchmarkId::from_parameter(leaves), |b| {
+            b.iter(|| {
+                i += 1;
+                let size = size();
+                tree.resize(Rectangle::new(0, 0, size.w - i % 2, size.h));
+                black_box(&tree);
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, insert, delete, move_focus, relayout);
+criterion_main!(benches);
@@ -0,0 +1,49 @@
+//! Compares laying out a whole tree with laying out only the part changed by `set_ratio`.
+//!
+//! Run with `cargo bench --bench relayout`.
+
+use dss::data_structures::bsptree::{BSPTree, NodeId, Rectangle, SplitPolicy};
+
+use std::hint::black_box;
+use std::time::Instant;
+
+const LEAVES: u32 = 1024;
+const ITERATIONS: u32 = 10_000;
+
+fn measure(name: &str, mut f: impl FnMut(u32)) {
+    let start = Instant::now();
+    for i in 0..ITERATIONS {
+        f(i);
+    }
+    let per_iteration = start.elapsed() / ITERATIONS;
+    println!("{name:>26}: {per_iteration:>10?} per iteration");
+}
+
+fn main() {
+    let size = Rectangle::new(0, 0, 3840, 2160);
+    let items: Vec<u32> = (0..LEAVES).collect();
+    let mut tree = BSPTree::from_leaves(size, &items, SplitPolicy::AutoSplit);
+    println!("{} leaves", tree.leaf_count());
+
+    // the parent of the last leaf, deep down in the tree
+    let container: NodeId = tree
+        .walk()
+        .into_iter()
+        .rev()
+        .find(|&id| tree[id].get_data().is_none())
+        .expect("the tree has internal nodes");
+
+    measure("full relayout", |i| {
+        let w = size.w - i % 2;
+        tree.resize(Rectangle::new(0, 0, w, size.h));
+        black_box(&tree);
+    });
+    tree.resize(size);
+
+    measure("set_ratio + relayout_dirty", |i| {
+        let ratio = if i % 2 == 0 { 0.25 } else { 0.75 };
+        tree.set_ratio(container, ratio).unwrap();
+        tree.relayout_dirty();
+        black_box(&tree);
+    });
+}
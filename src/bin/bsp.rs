@@ -1,9 +1,21 @@
 extern crate dss;
 
-use dss::data_structures::bsptree::{BSPTree, MoveDirection, Rectangle};
+use dss::data_structures::bsptree::{
+    BSPTree, Gaps, MoveDirection, Rectangle, TraversalOrder, TreeError,
+};
+use dss::render::{self, Fill};
+use dss::workspaces::Workspaces;
 
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::env;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{LineWriter, Write};
+use std::process;
 use std::rc::Rc;
+use std::str::FromStr;
+use std::sync::OnceLock;
 
 use std::os::raw::c_double;
 use tcl::*;
@@ -16,126 +28,531 @@ fn convert(rect: Rectangle) -> ((f64, f64), (f64, f64)) {
     ((x as f64, y as f64), (nx as f64, ny as f64))
 }
 
-fn draw_tree(tree: Rc<RefCell<BSPTree>>, c: TkCanvas<impl Fn() + Copy + 'static>) -> TkResult<()> {
-    let nodes = tree.borrow_mut().walk();
-    c.create_rectangle(0., 0., 640., 480., -fill("white"))?;
-    for node in nodes {
-        let n = node.borrow();
-        let rect = n.get_rect();
-        if n.get_data().is_some() {
-            let (fst, snd) = convert(rect);
-            if n.is_focused() {
-                c.create_rectangle(fst.0, fst.1, snd.0, snd.1, -fill("green"))?;
-            } else {
-                c.create_rectangle(fst.0, fst.1, snd.0, snd.1, -fill("red"))?;
+const USAGE: &str = "\
+usage: bsp [options]
+
+options:
+    --width <px>          canvas width (default 640)
+    --height <px>         canvas height (default 480)
+    --background <color>  background color (default white)
+    --focused <color>     color of the focused leaf (default green)
+    --unfocused <color>   color of the other leaves (default red)
+    --split <color>       color of split nodes, visible through gaps (default blue)
+    --gap <px>            space left between neighboring leaves (default 0)
+    --leaves <n>          number of leaves to insert on startup (default 0)
+    --record <file>       write every operation performed to <file>
+    --replay <file>       perform the operations from <file> on startup, recorded resizes
+                          change the layout but not the window
+    --step                with --replay, perform one operation per press of space
+    --help                print this message";
+
+/// Command line options of the demo.
+struct Options {
+    width: u32,
+    height: u32,
+    background: String,
+    focused: String,
+    unfocused: String,
+    split: String,
+    gap: u32,
+    leaves: u32,
+    record: Option<String>,
+    replay: Option<String>,
+    step: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            width: 640,
+            height: 480,
+            background: "white".to_owned(),
+            focused: "green".to_owned(),
+            unfocused: "red".to_owned(),
+            split: "blue".to_owned(),
+            gap: 0,
+            leaves: 0,
+            record: None,
+            replay: None,
+            step: false,
+        }
+    }
+}
+
+impl Options {
+    /// Parse the options from `args`, not including the program name.
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut opts = Self::default();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--help" => {
+                    println!("{USAGE}");
+                    process::exit(0);
+                }
+                "--step" => {
+                    opts.step = true;
+                    continue;
+                }
+                _ => {}
+            }
+
+            let value = args
+                .next()
+                .ok_or_else(|| format!("missing value for `{arg}`"))?;
+            let number = || {
+                value
+                    .parse::<u32>()
+                    .map_err(|_| format!("invalid value for `{arg}`: `{value}`"))
+            };
+
+            match arg.as_str() {
+                "--width" => opts.width = number()?,
+                "--height" => opts.height = number()?,
+                "--gap" => opts.gap = number()?,
+                "--leaves" => opts.leaves = number()?,
+                "--background" => opts.background = value,
+                "--focused" => opts.focused = value,
+                "--unfocused" => opts.unfocused = value,
+                "--split" => opts.split = value,
+                "--record" => opts.record = Some(value),
+                "--replay" => opts.replay = Some(value),
+                _ => return Err(format!("unknown option `{arg}`\n\n{USAGE}")),
             }
-        } else {
-            let (fst, snd) = convert(rect);
-            c.create_rectangle(fst.0, fst.1, snd.0, snd.1, -fill("blue"))?;
+        }
+
+        if opts.step && opts.replay.is_none() {
+            return Err(format!("`--step` requires `--replay`\n\n{USAGE}"));
+        }
+
+        Ok(opts)
+    }
+
+    fn color(&self, kind: Fill) -> &str {
+        match kind {
+            Fill::Background => &self.background,
+            Fill::Split => &self.split,
+            Fill::Leaf => &self.unfocused,
+            Fill::Focused => &self.focused,
         }
     }
+}
+
+static OPTIONS: OnceLock<Options> = OnceLock::new();
+
+fn options() -> &'static Options {
+    OPTIONS.get_or_init(Options::default)
+}
+
+/// Create an empty tree with the gaps from the command line.
+fn new_tree(size: Rectangle) -> BSPTree {
+    let gap = options().gap;
+    let mut tree = BSPTree::new(size);
+    tree.set_gaps(Gaps::new(gap, gap / 2));
+    tree
+}
+
+fn draw_tree<Inst: TkInstance>(tree: &BSPTree, c: TkCanvas<Inst>) -> TkResult<()> {
+    let opts = options();
+    for (rect, kind) in render::draw_list(tree) {
+        let (fst, snd) = convert(rect);
+        c.create_rectangle(fst.0, fst.1, snd.0, snd.1, -fill(opts.color(kind)))?;
+    }
+
+    // label the leaves with the number which focuses them together with control
+    let leaves: Vec<_> = tree.iter().filter(|node| node.leaf).collect();
+    for (i, node) in leaves.into_iter().enumerate() {
+        if node.hidden {
+            continue;
+        }
+        let ((x, y), (nx, ny)) = convert(node.rect);
+        c.create_text((x + nx) / 2., (y + ny) / 2., -text((i + 1).to_string()))?;
+    }
     println!("===");
-    tree.borrow().print(1);
+    tree.print_order(TraversalOrder::InOrder);
     println!("===");
     Ok(())
 }
 
+const WORKSPACES: usize = 9;
+
+fn title(workspaces: &Workspaces) -> String {
+    format!("bsp - workspace {}", workspaces.active_index() + 1)
+}
+
+/// A single operation performed in the demo. A session is recorded as a script with one
+/// operation per line, e.g. `insert 3` or `focus 120 40`; empty lines and lines starting with `#`
+/// are ignored. Resizing the window is recorded as well, so that a replay goes through the same
+/// layouts.
+#[derive(Clone, Copy)]
+enum Op {
+    Insert(u32),
+    Delete,
+    ToggleSplit,
+    Fullscreen,
+    Undo,
+    Redo,
+    Focus(i32, i32),
+    Move(MoveDirection),
+    Swap(MoveDirection),
+    FocusNth(usize),
+    FocusNext,
+    FocusPrev,
+    Workspace(usize),
+    Send(usize),
+    Resize(u32, u32),
+}
+
+fn direction_name(direction: MoveDirection) -> &'static str {
+    match direction {
+        MoveDirection::Left => "left",
+        MoveDirection::Right => "right",
+        MoveDirection::Up => "up",
+        MoveDirection::Down => "down",
+    }
+}
+
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Insert(data) => write!(f, "insert {data}"),
+            Self::Delete => write!(f, "delete"),
+            Self::ToggleSplit => write!(f, "toggle-split"),
+            Self::Fullscreen => write!(f, "fullscreen"),
+            Self::Undo => write!(f, "undo"),
+            Self::Redo => write!(f, "redo"),
+            Self::Focus(x, y) => write!(f, "focus {x} {y}"),
+            Self::Move(d) => write!(f, "move {}", direction_name(*d)),
+            Self::Swap(d) => write!(f, "swap {}", direction_name(*d)),
+            Self::FocusNth(n) => write!(f, "focus-nth {n}"),
+            Self::FocusNext => write!(f, "focus-next"),
+            Self::FocusPrev => write!(f, "focus-prev"),
+            Self::Workspace(n) => write!(f, "workspace {n}"),
+            Self::Send(n) => write!(f, "send {n}"),
+            Self::Resize(w, h) => write!(f, "resize {w} {h}"),
+        }
+    }
+}
+
+impl FromStr for Op {
+    type Err = String;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let mut words = line.split_whitespace();
+        let name = words.next().unwrap_or_default();
+        let mut arg = || {
+            words
+                .next()
+                .ok_or_else(|| format!("`{name}` is missing an argument"))
+        };
+        fn number<T: FromStr>(name: &str, word: &str) -> Result<T, String> {
+            word.parse()
+                .map_err(|_| format!("invalid number `{word}` for `{name}`"))
+        }
+        let direction = |word: &str| match word {
+            "left" => Ok(MoveDirection::Left),
+            "right" => Ok(MoveDirection::Right),
+            "up" => Ok(MoveDirection::Up),
+            "down" => Ok(MoveDirection::Down),
+            _ => Err(format!("invalid direction `{word}` for `{name}`")),
+        };
+
+        let op = match name {
+            "insert" => Self::Insert(number(name, arg()?)?),
+            "delete" => Self::Delete,
+            "toggle-split" => Self::ToggleSplit,
+            "fullscreen" => Self::Fullscreen,
+            "undo" => Self::Undo,
+            "redo" => Self::Redo,
+            "focus" => Self::Focus(number(name, arg()?)?, number(name, arg()?)?),
+            "move" => Self::Move(direction(arg()?)?),
+            "swap" => Self::Swap(direction(arg()?)?),
+            "focus-nth" => Self::FocusNth(number(name, arg()?)?),
+            "focus-next" => Self::FocusNext,
+            "focus-prev" => Self::FocusPrev,
+            "workspace" => Self::Workspace(number(name, arg()?)?),
+            "send" => Self::Send(number(name, arg()?)?),
+            "resize" => Self::Resize(number(name, arg()?)?, number(name, arg()?)?),
+            _ => return Err(format!("unknown operation `{name}`")),
+        };
+
+        Ok(op)
+    }
+}
+
+/// Parse a recorded session, reporting errors with their line number.
+fn parse_script(script: &str) -> Result<Vec<Op>, String> {
+    script
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(i, line)| line.parse().map_err(|e| format!("line {}: {e}", i + 1)))
+        .collect()
+}
+
+/// Print why an operation failed. Running into the edge of the layout or an empty tree is not
+/// worth mentioning.
+fn report(op: Op, result: Result<(), TreeError>) {
+    match result {
+        Ok(()) | Err(TreeError::Empty | TreeError::NoNeighbor) => {}
+        Err(e) => eprintln!("unable to {op}: {e}"),
+    }
+}
+
+/// Everything the key bindings of the demo operate on.
+struct Session {
+    workspaces: RefCell<Workspaces>,
+    /// The largest value inserted so far, new leaves get the next one.
+    count: Cell<u32>,
+    log: RefCell<Option<LineWriter<File>>>,
+    /// Operations of a step-by-step replay which have not been performed yet.
+    pending: RefCell<VecDeque<Op>>,
+}
+
+impl Session {
+    /// Record the operation, if recording, and perform it.
+    fn perform(&self, op: Op) {
+        let mut log = self.log.borrow_mut();
+        if let Some(file) = log.as_mut() {
+            if let Err(e) = writeln!(file, "{op}") {
+                eprintln!("unable to record the session, recording stopped: {e}");
+                *log = None;
+            }
+        }
+        drop(log);
+
+        self.apply(op);
+    }
+
+    /// Perform the operation without recording it.
+    fn apply(&self, op: Op) {
+        let mut workspaces = self.workspaces.borrow_mut();
+        let tree = workspaces.active_mut();
+        match op {
+            Op::Insert(data) => match tree.insert(data) {
+                Ok(()) => self.count.set(self.count.get().max(data)),
+                Err(e) => eprintln!("unable to insert {data}: {e}"),
+            },
+            Op::Delete => report(op, tree.delete_focused()),
+            Op::ToggleSplit => report(op, tree.toggle_split()),
+            Op::Fullscreen => report(op, tree.toggle_fullscreen()),
+            Op::Undo => {
+                tree.undo();
+            }
+            Op::Redo => {
+                tree.redo();
+            }
+            Op::Focus(x, y) => tree.focus_coords(x, y),
+            Op::Move(direction) => report(op, tree.move_focus(direction)),
+            Op::Swap(direction) => report(op, tree.swap_in_direction(direction)),
+            Op::FocusNth(n) => tree.focus_nth(n),
+            Op::FocusNext => tree.focus_next(),
+            Op::FocusPrev => tree.focus_prev(),
+            Op::Workspace(n) => {
+                workspaces.switch_to(n);
+            }
+            Op::Send(n) => {
+                if let Err(e) = workspaces.move_focused_to(n) {
+                    eprintln!(
+                        "unable to send the focused leaf to workspace {}: {e}",
+                        n + 1
+                    );
+                }
+            }
+            Op::Resize(w, h) => workspaces.resize(Rectangle::new(0, 0, w, h)),
+        }
+    }
+
+    /// Perform the next operation of a step-by-step replay.
+    fn step(&self) {
+        let op = self.pending.borrow_mut().pop_front();
+        if let Some(op) = op {
+            println!("replay: {op}");
+            self.perform(op);
+        }
+    }
+}
+
+fn draw<Inst: TkInstance>(
+    session: &Session,
+    root: TkRoot<Inst>,
+    c: TkCanvas<Inst>,
+) -> TkResult<()> {
+    let workspaces = session.workspaces.borrow();
+    root.set_wm_title(title(&workspaces))?;
+    draw_tree(workspaces.active(), c)
+}
+
 fn main() -> TkResult<()> {
-    let tree = Rc::new(RefCell::new(BSPTree::new(Rectangle::new(0, 0, 640, 480))));
+    let opts = match Options::parse(env::args().skip(1)) {
+        Ok(opts) => OPTIONS.get_or_init(|| opts),
+        Err(msg) => {
+            eprintln!("{msg}");
+            process::exit(2);
+        }
+    };
 
-    let tk = make_tk!()?;
-    let root = tk.root();
+    let replay = match opts.replay.as_ref() {
+        Some(path) => {
+            let script = fs::read_to_string(path).unwrap_or_else(|e| {
+                eprintln!("unable to read `{path}`: {e}");
+                process::exit(1);
+            });
+            parse_script(&script).unwrap_or_else(|e| {
+                eprintln!("{path}: {e}");
+                process::exit(1);
+            })
+        }
+        None => vec![],
+    };
 
-    let c = root.add_canvas("canvas" - width(640) - height(480) - background("white"))?;
-    c.pack(())?;
+    let log = opts.record.as_ref().map(|path| {
+        let file = File::create(path).unwrap_or_else(|e| {
+            eprintln!("unable to create `{path}`: {e}");
+            process::exit(1);
+        });
+        LineWriter::new(file)
+    });
 
-    let t = tree.clone();
+    let size = Rectangle::new(0, 0, opts.width, opts.height);
+    let session = Rc::new(Session {
+        workspaces: RefCell::new(Workspaces::from_template(WORKSPACES, new_tree(size))),
+        count: Cell::new(0),
+        log: RefCell::new(log),
+        pending: RefCell::new(VecDeque::new()),
+    });
 
-    let count = Rc::new(RefCell::new(0));
+    let tk = make_tk!()?;
+    let root = tk.root();
 
-    root.bind(
-        event::key_press(TkKey::n),
-        tclosure!(tk, args: "%x %y", move |_x: c_double, _y: c_double| -> TkResult<()> {
-            let co = *count.borrow();
-            t.borrow_mut().insert(co + 1);
-            draw_tree(t.clone(), c)?;
-            count.replace(co + 1);
-            Ok(())
-        }),
+    let c = root.add_canvas(
+        "canvas" - width(opts.width) - height(opts.height) - background(opts.background.as_str()),
     )?;
-    let t = tree.clone();
+    c.pack(-fill("both") - expand(true))?;
+
+    // the layout follows the size of the window
+    let s = session.clone();
     root.bind(
-        event::key_press(TkKey::d),
-        tclosure!(tk, args: "%x %y", move |_x: c_double, _y: c_double| -> TkResult<()> {
-            t.borrow_mut().delete_focused();
-            draw_tree(t.clone(), c)?;
+        event::configure(),
+        tclosure!(tk, args: "%w %h", move |w: c_double, h: c_double| -> TkResult<()> {
+            let (w, h) = (w as u32, h as u32);
+            if s.workspaces.borrow().get_size() != Rectangle::new(0, 0, w, h) {
+                s.perform(Op::Resize(w, h));
+                draw(&s, root, c)?;
+            }
             Ok(())
         }),
     )?;
 
-    let t = tree.clone();
+    let s = session.clone();
     root.bind(
-        event::key_press(TkKey::s),
+        event::key_press(TkKey::n),
         tclosure!(tk, args: "%x %y", move |_x: c_double, _y: c_double| -> TkResult<()> {
-            t.borrow_mut().toggle_split();
-            draw_tree(t.clone(), c)?;
-            Ok(())
+            s.perform(Op::Insert(s.count.get() + 1));
+            draw(&s, root, c)
         }),
     )?;
 
-    let t = tree.clone();
+    let s = session.clone();
     root.bind(
         event::button_press_1(),
         tclosure!(tk, args: "%x %y", move |x: c_double, y: c_double| -> TkResult<()> {
-            t.borrow_mut().focus_coords(x as i32, y as i32);
-            draw_tree(t.clone(), c)?;
-            Ok(())
-        }),
-    )?;
-    let t = tree.clone();
-    root.bind(
-        event::key_press(TkKey::h),
-        tclosure!(tk, args: "%x %y", move |_x: c_double, _y: c_double| -> TkResult<()> {
-            t.borrow_mut().move_focus(MoveDirection::Left);
-            draw_tree(t.clone(), c)?;
-            Ok(())
+            s.perform(Op::Focus(x as i32, y as i32));
+            draw(&s, root, c)
         }),
     )?;
-    let t = tree.clone();
-    root.bind(
-        event::key_press(TkKey::l),
-        tclosure!(tk, args: "%x %y", move |_x: c_double, _y: c_double| -> TkResult<()> {
-            t.borrow_mut().move_focus(MoveDirection::Right);
-            draw_tree(t.clone(), c)?;
-            Ok(())
-        }),
-    )?;
-    let t = tree.clone();
-    root.bind(
-        event::key_press(TkKey::k),
-        tclosure!(tk, args: "%x %y", move |_x: c_double, _y: c_double| -> TkResult<()> {
-            t.borrow_mut().move_focus(MoveDirection::Up);
-            draw_tree(t.clone(), c)?;
-            Ok(())
-        }),
-    )?;
-    let t = tree.clone();
+
+    let s = session.clone();
     root.bind(
-        event::key_press(TkKey::j),
+        event::key_press(TkKey::space),
         tclosure!(tk, args: "%x %y", move |_x: c_double, _y: c_double| -> TkResult<()> {
-            t.borrow_mut().move_focus(MoveDirection::Down);
-            draw_tree(t.clone(), c)?;
-            Ok(())
+            s.step();
+            draw(&s, root, c)
         }),
     )?;
 
-    tree.borrow_mut().delete_focused();
+    let bindings = [
+        (event::key_press(TkKey::d), Op::Delete),
+        (event::key_press(TkKey::u), Op::Undo),
+        (event::control().key_press(TkKey::r), Op::Redo),
+        (event::key_press(TkKey::s), Op::ToggleSplit),
+        (event::key_press(TkKey::f), Op::Fullscreen),
+        (event::key_press(TkKey::Tab), Op::FocusNext),
+        (event::key_press(TkKey::ISO_Left_Tab), Op::FocusPrev),
+        (event::key_press(TkKey::h), Op::Move(MoveDirection::Left)),
+        (event::key_press(TkKey::l), Op::Move(MoveDirection::Right)),
+        (event::key_press(TkKey::k), Op::Move(MoveDirection::Up)),
+        (event::key_press(TkKey::j), Op::Move(MoveDirection::Down)),
+        (event::key_press(TkKey::H), Op::Swap(MoveDirection::Left)),
+        (event::key_press(TkKey::L), Op::Swap(MoveDirection::Right)),
+        (event::key_press(TkKey::K), Op::Swap(MoveDirection::Up)),
+        (event::key_press(TkKey::J), Op::Swap(MoveDirection::Down)),
+    ];
+
+    let number_keys = [
+        TkKey::_1,
+        TkKey::_2,
+        TkKey::_3,
+        TkKey::_4,
+        TkKey::_5,
+        TkKey::_6,
+        TkKey::_7,
+        TkKey::_8,
+        TkKey::_9,
+    ];
+    // what the number keys produce while holding shift, on a US layout
+    let shifted_number_keys = [
+        TkKey::exclam,
+        TkKey::at,
+        TkKey::numbersign,
+        TkKey::dollar,
+        TkKey::percent,
+        TkKey::asciicircum,
+        TkKey::ampersand,
+        TkKey::asterisk,
+        TkKey::parenleft,
+    ];
+
+    let mut numbered = vec![];
+    for (n, (key, shifted)) in number_keys.into_iter().zip(shifted_number_keys).enumerate() {
+        numbered.push((event::key_press(key), Op::Workspace(n)));
+        numbered.push((event::control().key_press(key), Op::FocusNth(n)));
+        numbered.push((event::key_press(shifted), Op::Send(n)));
+    }
+
+    for (ev, op) in bindings.into_iter().chain(numbered) {
+        let s = session.clone();
+        root.bind(
+            ev,
+            tclosure!(tk, args: "%x %y", move |_x: c_double, _y: c_double| -> TkResult<()> {
+                s.perform(op);
+                draw(&s, root, c)
+            }),
+        )?;
+    }
+
+    for i in 0..opts.leaves {
+        session.apply(Op::Insert(i + 1));
+    }
+
+    if opts.step {
+        session.pending.replace(replay.into());
+    } else {
+        for op in replay {
+            session.perform(op);
+        }
+    }
 
-    draw_tree(tree.clone(), c)?;
+    draw(&session, root, c)?;
 
     main_loop();
 
-    tree.borrow().print(1);
+    session
+        .workspaces
+        .borrow()
+        .active()
+        .print_order(TraversalOrder::InOrder);
 
     Ok(())
 }
@@ -0,0 +1,120 @@
+extern crate dss;
+
+use dss::data_structures::gap_buffer::GapBuffer;
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use tcl::*;
+use tk::{cmd::*, *};
+
+const WIDTH: f64 = 660.;
+const HEIGHT: f64 = 480.;
+const CELL: f64 = 20.;
+const PER_ROW: usize = 32;
+
+fn printable(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes).replace('\n', "\\n")
+}
+
+fn draw(gb: &GapBuffer, c: TkCanvas<impl Fn() + Copy + 'static>) -> TkResult<()> {
+    c.create_rectangle(0., 0., WIDTH, HEIGHT, -fill("white"))?;
+
+    let (l, r) = gb.gap();
+    let top = 30.;
+
+    c.create_text(10., 10., -anchor("nw") - text("raw buffer"))?;
+
+    // the backing array, one cell per byte, with the gap greyed out
+    for (i, byte) in gb.buffer().iter().enumerate() {
+        let x = 10. + (i % PER_ROW) as f64 * CELL;
+        let y = top + (i / PER_ROW) as f64 * CELL;
+        let in_gap = i >= l && i <= r;
+
+        c.create_rectangle(
+            x,
+            y,
+            x + CELL,
+            y + CELL,
+            -fill(if in_gap { "lightgrey" } else { "white" }),
+        )?;
+        if !in_gap {
+            let ch = printable(&[*byte]);
+            c.create_text(
+                x + CELL / 2.,
+                y + CELL / 2.,
+                -text(ch) - font("TkFixedFont"),
+            )?;
+        }
+        if i == l {
+            c.create_line(&[(x, y - 2.), (x, y + CELL + 2.)], -fill("red") - width(3))?;
+        }
+    }
+
+    let rows = gb.buffer().len().div_ceil(PER_ROW);
+    let mut y = top + rows as f64 * CELL + 20.;
+
+    let (before, after) = gb.as_slices();
+    let length = before.len() + after.len();
+    let lines = [
+        format!("gap: [{l}, {r}] ({} bytes)", r + 1 - l),
+        format!("cursor: {l}"),
+        format!("capacity: {}", gb.capacity),
+        format!("content length: {length}"),
+        format!("before gap: \"{}\"", printable(before)),
+        format!("after gap: \"{}\"", printable(after)),
+        format!("content: \"{}{}\"", printable(before), printable(after)),
+    ];
+
+    for line in lines {
+        c.create_text(10., y, -anchor("nw") - text(line) - font("TkFixedFont"))?;
+        y += CELL;
+    }
+
+    Ok(())
+}
+
+fn main() -> TkResult<()> {
+    let gb = Rc::new(RefCell::new(GapBuffer::default()));
+
+    let tk = make_tk!()?;
+    let root = tk.root();
+    root.set_wm_title("GapBuffer inspector")?;
+
+    let c = root.add_canvas("canvas" - width(WIDTH) - height(HEIGHT) - background("white"))?;
+    c.pack(())?;
+
+    let g = gb.clone();
+    root.bind(
+        event::any_key_press(),
+        tclosure!(tk, args: "%K %A", move |keysym: String, ch: String| -> TkResult<()> {
+            let mut gb = g.borrow_mut();
+            match keysym.as_str() {
+                "Left" => gb.left_by(1),
+                "Right" => gb.right_by(1),
+                "Home" => gb.seek(0),
+                "End" => gb.seek(usize::MAX),
+                "BackSpace" => {
+                    gb.delete_left(1);
+                }
+                "Delete" => {
+                    gb.delete_right(1);
+                }
+                "Return" => gb.insert_byte(b'\n'),
+                _ => {
+                    if !ch.is_empty() && !ch.chars().any(char::is_control) {
+                        gb.insert_str(&ch);
+                    }
+                }
+            }
+            draw(&gb, c)?;
+            Ok(())
+        }),
+    )?;
+
+    draw(&gb.borrow(), c)?;
+
+    main_loop();
+
+    Ok(())
+}
@@ -0,0 +1,316 @@
+use crate::data_structures::bsptree::{
+    BSPTree, CommandLog, Layout, MoveDirection, NodeData, Rectangle, SplitDirection, TreeCommand,
+    MAX_RATIO, MIN_RATIO,
+};
+
+use std::io;
+
+/// The first bytes of every encoded tree, followed by the version of the format.
+const MAGIC: &[u8; 4] = b"BSPT";
+const VERSION: u8 = 1;
+
+/// The first bytes of every encoded command log, followed by the version of the format.
+const LOG_MAGIC: &[u8; 4] = b"BSPL";
+const LOG_VERSION: u8 = 1;
+
+/// The bits of the tag byte written in front of every node.
+const TAG_LEAF: u8 = 0b001;
+const TAG_VERTICAL: u8 = 0b010;
+const TAG_FOCUSED: u8 = 0b100;
+
+/// A node which is still missing it's children, used while decoding.
+struct Partial {
+    split: SplitDirection,
+    ratio: f32,
+    left: Option<Layout<NodeData>>,
+}
+
+impl BSPTree<NodeData> {
+    /// Write the size and layout of the tree in a compact binary format, which can be read back
+    /// with `decode`, e.g. to pass the tree to another process. The nodes are written in
+    /// pre-order, each as a tag byte telling it's kind, split and focus, followed by the split
+    /// ratio of an internal node or the data of a leaf. Numbers are written as varints.
+    ///
+    /// Only the shape of the tree is kept, like with `to_layout`. Settings, metadata, hidden
+    /// leaves and the undo history are not written.
+    pub fn encode(&self, out: &mut impl io::Write) -> io::Result<()> {
+        let size = self.get_size();
+        out.write_all(MAGIC)?;
+        out.write_all(&[VERSION])?;
+        write_varint(out, zigzag(size.x))?;
+        write_varint(out, zigzag(size.y))?;
+        write_varint(out, size.w as u64)?;
+        write_varint(out, size.h as u64)?;
+        write_varint(out, self.node_count() as u64)?;
+
+        for node in self.iter() {
+            let mut tag = 0;
+            if node.leaf {
+                tag |= TAG_LEAF;
+            }
+            if node.split == SplitDirection::Vertical {
+                tag |= TAG_VERTICAL;
+            }
+            if node.focused {
+                tag |= TAG_FOCUSED;
+            }
+            out.write_all(&[tag])?;
+
+            match node.data {
+                Some(data) if node.leaf => write_varint(out, data as u64)?,
+                _ => out.write_all(&node.ratio.to_le_bytes())?,
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Read a tree written by `encode`. Fails with `io::ErrorKind::InvalidData` if the input is
+    /// not an encoded tree, or with `io::ErrorKind::UnexpectedEof` if it ends too early. Split
+    /// ratios are clamped like with `BSPTree::set_ratio`, so that no child ends up empty.
+    pub fn decode(input: &mut impl io::Read) -> io::Result<Self> {
+        let mut magic = [0; 5];
+        input.read_exact(&mut magic)?;
+        if &magic[..4] != MAGIC || magic[4] != VERSION {
+            return Err(invalid("not an encoded tree"));
+        }
+
+        let x = unzigzag(read_varint(input)?)?;
+        let y = unzigzag(read_varint(input)?)?;
+        let w = read_u32(input)?;
+        let h = read_u32(input)?;
+        let size = Rectangle::new(x, y, w, h);
+
+        let count = read_varint(input)?;
+        if count == 0 {
+            return Ok(Self::new(size));
+        }
+
+        // the internal nodes whose right child is being read
+        let mut stack: Vec<Partial> = vec![];
+        let mut root = None;
+        let mut focused = 0;
+
+        for _ in 0..count {
+            let mut tag = [0];
+            input.read_exact(&mut tag)?;
+            let tag = tag[0];
+            if tag & !(TAG_LEAF | TAG_VERTICAL | TAG_FOCUSED) != 0 {
+                return Err(invalid("unknown node tag"));
+            }
+            let split = if tag & TAG_VERTICAL != 0 {
+                SplitDirection::Vertical
+            } else {
+                SplitDirection::Horizontal
+            };
+
+            if tag & TAG_LEAF == 0 {
+                let mut ratio = [0; 4];
+                input.read_exact(&mut ratio)?;
+                let ratio = f32::from_le_bytes(ratio);
+                if !(ratio > 0.0 && ratio < 1.0) {
+                    return Err(invalid("split ratio out of range"));
+                }
+                let ratio = ratio.clamp(MIN_RATIO, MAX_RATIO);
+
+                stack.push(Partial {
+                    split,
+                    ratio,
+                    left: None,
+                });
+                continue;
+            }
+
+            if tag & TAG_FOCUSED != 0 {
+                focused += 1;
+            }
+            let mut layout = Layout::Leaf {
+                data: read_u32(input)?,
+                split,
+                focused: tag & TAG_FOCUSED != 0,
+            };
+
+            // a finished node completes all of the nodes it is the right child of
+            loop {
+                match stack.pop() {
+                    Some(Partial {
+                        split,
+                        ratio,
+                        left: None,
+                    }) => {
+                        stack.push(Partial {
+                            split,
+                            ratio,
+                            left: Some(layout),
+                        });
+                        break;
+                    }
+                    Some(Partial {
+                        split,
+                        ratio,
+                        left: Some(left),
+                    }) => {
+                        layout = Layout::Split {
+                            split,
+                            ratio,
+                            left: Box::new(left),
+                            right: Box::new(layout),
+                        };
+                    }
+                    None if root.is_none() => {
+                        root = Some(layout);
+                        break;
+                    }
+                    None => return Err(invalid("more nodes than the tree has")),
+                }
+            }
+        }
+
+        match root {
+            Some(_) if focused > 1 => Err(invalid("more than one leaf is focused")),
+            Some(layout) if stack.is_empty() => Ok(Self::from_layout(layout, size)),
+            _ => Err(invalid("the tree is missing nodes")),
+        }
+    }
+}
+
+impl CommandLog<NodeData> {
+    /// Write the log in a compact binary format, which can be read back with `decode`, e.g. to
+    /// save it to a file attached to a bug report. The starting tree is written with
+    /// `BSPTree::encode`, so it's settings are not kept, followed by the commands. Every command
+    /// is a tag byte followed by it's arguments.
+    pub fn encode(&self, out: &mut impl io::Write) -> io::Result<()> {
+        out.write_all(LOG_MAGIC)?;
+        out.write_all(&[LOG_VERSION])?;
+        self.start().encode(out)?;
+        write_varint(out, self.commands().len() as u64)?;
+
+        for command in self.commands() {
+            match *command {
+                TreeCommand::Insert(data) => {
+                    out.write_all(&[0])?;
+                    write_varint(out, data as u64)?;
+                }
+                TreeCommand::Delete => out.write_all(&[1])?,
+                TreeCommand::Focus(direction) => out.write_all(&[2, direction_tag(direction)])?,
+                TreeCommand::Move(direction) => out.write_all(&[3, direction_tag(direction)])?,
+                TreeCommand::Resize { direction, amount } => {
+                    out.write_all(&[4, direction_tag(direction)])?;
+                    write_varint(out, zigzag(amount))?;
+                }
+                TreeCommand::ToggleSplit => out.write_all(&[5])?,
+                TreeCommand::ToggleFullscreen => out.write_all(&[6])?,
+                TreeCommand::Rotate => out.write_all(&[7])?,
+                TreeCommand::Promote => out.write_all(&[8])?,
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Read a log written by `encode`. Fails like `BSPTree::decode`.
+    pub fn decode(input: &mut impl io::Read) -> io::Result<Self> {
+        let mut magic = [0; 5];
+        input.read_exact(&mut magic)?;
+        if &magic[..4] != LOG_MAGIC || magic[4] != LOG_VERSION {
+            return Err(invalid("not an encoded command log"));
+        }
+
+        let start = BSPTree::decode(input)?;
+        let count = read_varint(input)?;
+        let mut commands = vec![];
+
+        for _ in 0..count {
+            let command = match read_byte(input)? {
+                0 => TreeCommand::Insert(read_u32(input)?),
+                1 => TreeCommand::Delete,
+                2 => TreeCommand::Focus(read_direction(input)?),
+                3 => TreeCommand::Move(read_direction(input)?),
+                4 => TreeCommand::Resize {
+                    direction: read_direction(input)?,
+                    amount: unzigzag(read_varint(input)?)?,
+                },
+                5 => TreeCommand::ToggleSplit,
+                6 => TreeCommand::ToggleFullscreen,
+                7 => TreeCommand::Rotate,
+                8 => TreeCommand::Promote,
+                _ => return Err(invalid("unknown command tag")),
+            };
+            commands.push(command);
+        }
+
+        Ok(Self::new(start, commands))
+    }
+}
+
+const DIRECTIONS: [MoveDirection; 4] = [
+    MoveDirection::Left,
+    MoveDirection::Right,
+    MoveDirection::Up,
+    MoveDirection::Down,
+];
+
+fn direction_tag(direction: MoveDirection) -> u8 {
+    DIRECTIONS
+        .iter()
+        .position(|&d| d == direction)
+        .expect("all directions are listed") as u8
+}
+
+fn read_direction(input: &mut impl io::Read) -> io::Result<MoveDirection> {
+    DIRECTIONS
+        .get(read_byte(input)? as usize)
+        .copied()
+        .ok_or_else(|| invalid("unknown direction"))
+}
+
+fn read_byte(input: &mut impl io::Read) -> io::Result<u8> {
+    let mut byte = [0];
+    input.read_exact(&mut byte)?;
+    Ok(byte[0])
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn zigzag(value: i32) -> u64 {
+    ((value << 1) ^ (value >> 31)) as u32 as u64
+}
+
+fn unzigzag(value: u64) -> io::Result<i32> {
+    let value = u32::try_from(value).map_err(|_| invalid("coordinate out of range"))?;
+    Ok(((value >> 1) as i32) ^ -((value & 1) as i32))
+}
+
+/// Write `value` seven bits at a time, the lowest first, setting the top bit of every byte but
+/// the last.
+fn write_varint(out: &mut impl io::Write, mut value: u64) -> io::Result<()> {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            return out.write_all(&[byte]);
+        }
+        out.write_all(&[byte | 0x80])?;
+    }
+}
+
+fn read_varint(input: &mut impl io::Read) -> io::Result<u64> {
+    let mut value = 0;
+
+    for shift in (0..64).step_by(7) {
+        let mut byte = [0];
+        input.read_exact(&mut byte)?;
+        value |= ((byte[0] & 0x7f) as u64) << shift;
+        if byte[0] & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+
+    Err(invalid("varint is too long"))
+}
+
+fn read_u32(input: &mut impl io::Read) -> io::Result<u32> {
+    u32::try_from(read_varint(input)?).map_err(|_| invalid("number out of range"))
+}
@@ -1,323 +1,3390 @@
 use core::fmt;
-use std::cell::RefCell;
-use std::rc::Rc;
+use std::collections::{BTreeMap, VecDeque};
+use std::io;
+use std::iter;
+use std::mem;
+use std::ops;
+use std::sync::{Arc, Mutex};
+
+/// The index of a Node inside of a BSPTree. An id stays valid until it's node is removed from the
+/// tree, after which it may be given to a new node.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash, PartialOrd, Ord)]
+pub struct NodeId(usize);
+
+impl NodeId {
+    /// The position of the node in the tree's arena.
+    pub fn index(&self) -> usize {
+        self.0
+    }
+}
+
+/// Identifies a region reserved with `BSPTree::reserve`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash, PartialOrd, Ord)]
+pub struct ReservationId(usize);
+
+/// The default type of data stored in the leaves of a BSPTree.
+pub type NodeData = u32;
+
+/// Defines the four ways in which you can move focus around in the BSPTree.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MoveDirection {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+impl MoveDirection {
+    pub fn apply_move(&self, rect: Rectangle) -> (i32, i32) {
+        match self {
+            Self::Left => (rect.x - 1, rect.y),
+            Self::Right => (rect.right(), rect.y),
+            Self::Up => (rect.x, rect.y - 1),
+            Self::Down => (rect.x, rect.bottom()),
+        }
+    }
+}
+/// The way in which a Node in the BSP Tree will be split.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SplitDirection {
+    Horizontal,
+    Vertical,
+}
+
+/// The default ratio in which a Node is split between it's children.
+pub const DEFAULT_RATIO: f32 = 0.5;
+
+/// Split ratios are kept in this range, so that no child can be resized out of existence.
+pub(crate) const MIN_RATIO: f32 = 0.05;
+pub(crate) const MAX_RATIO: f32 = 0.95;
+
+/// Errors returned by the operations of a BSPTree.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+pub enum TreeError {
+    /// Splitting the focused leaf would make one of the halves smaller than the minimum size of
+    /// the tree.
+    TooSmall,
+    /// The operation needs a focused leaf, but the tree is empty.
+    Empty,
+    /// There is no leaf in the direction the focus was supposed to move.
+    NoNeighbor,
+    /// The focused leaf doesn't have the parent or grandparent the operation works on.
+    NoParent,
+    /// The node `id` has been removed from the tree, or never was part of it.
+    InvalidNode { id: NodeId },
+    /// None of the hidden leaves holds the data which was supposed to be shown.
+    NotHidden,
+    /// The node `id` is not a floating leaf.
+    NotFloating { id: NodeId },
+}
+
+impl fmt::Display for TreeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TooSmall => write!(f, "the focused leaf is too small to be split"),
+            Self::Empty => write!(f, "the tree is empty"),
+            Self::NoNeighbor => write!(f, "there is no leaf in that direction"),
+            Self::NoParent => write!(f, "the focused leaf has no parent to operate on"),
+            Self::InvalidNode { id } => write!(f, "node {} is not part of the tree", id.0),
+            Self::NotHidden => write!(f, "no hidden leaf holds that data"),
+            Self::NotFloating { id } => write!(f, "node {} is not floating", id.0),
+        }
+    }
+}
+
+impl std::error::Error for TreeError {}
+
+/// A broken invariant of a BSPTree, found by `BSPTree::validate`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+pub enum InvariantError {
+    /// The node `id` is referred to, but is not stored in the tree.
+    Dangling { id: NodeId },
+    /// The node `id` is stored in the tree, but can't be reached from the root.
+    Unreachable { id: NodeId },
+    /// The internal node `id` doesn't have exactly two children, or the leaf `id` has children or
+    /// no data.
+    BadChildren { id: NodeId },
+    /// The children of `id` don't exactly cover it's rectangle, or the root doesn't cover the
+    /// whole tree.
+    NotTiled { id: NodeId },
+    /// The node `id` doesn't point back to it's parent, or is on the wrong side of it.
+    BadParent { id: NodeId },
+    /// The tree doesn't have exactly one focused leaf, or it's not the one tracked by the tree.
+    BadFocus,
+}
+
+impl fmt::Display for InvariantError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Dangling { id } => write!(f, "node {} is not in the tree", id.0),
+            Self::Unreachable { id } => write!(f, "node {} can't be reached from the root", id.0),
+            Self::BadChildren { id } => write!(f, "node {} has the wrong children", id.0),
+            Self::NotTiled { id } => write!(f, "node {} is not covered by it's children", id.0),
+            Self::BadParent { id } => write!(f, "node {} has the wrong parent", id.0),
+            Self::BadFocus => write!(f, "the tree doesn't have exactly one focused leaf"),
+        }
+    }
+}
+
+impl std::error::Error for InvariantError {}
+
+/// A change made to a BSPTree, passed to the callbacks registered with `BSPTree::on_change`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+pub enum TreeEvent {
+    /// A new leaf was added to the tree.
+    Inserted { id: NodeId, rect: Rectangle },
+    /// A leaf was removed from the tree, hidden or floated, `rect` is the space it used to fill.
+    /// Removing a hidden or floating leaf is not reported again.
+    Deleted { id: NodeId, rect: Rectangle },
+    /// The leaf `id` got the focus.
+    FocusChanged { id: NodeId },
+    /// The leaf `id` got a new size.
+    Resized { id: NodeId, rect: Rectangle },
+    /// The data of the leaves `a` and `b` was exchanged.
+    Swapped { a: NodeId, b: NodeId },
+}
+
+/// An operation on a BSPTree, run with `BSPTree::apply`. Commands make it possible to drive a
+/// tree through a single entry point, e.g. from another process. With the `serde` feature
+/// enabled they can be serialized.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TreeCommand<T = NodeData> {
+    /// Insert a new leaf with `BSPTree::insert`.
+    Insert(T),
+    /// Delete the focused leaf with `BSPTree::delete_focused`.
+    Delete,
+    /// Move the focus with `BSPTree::move_focus`.
+    Focus(MoveDirection),
+    /// Swap the focused leaf with it's neighbor with `BSPTree::swap_in_direction`.
+    Move(MoveDirection),
+    /// Move an edge of the focused leaf with `BSPTree::resize_focused`.
+    Resize {
+        direction: MoveDirection,
+        amount: i32,
+    },
+    /// Change the split of the focused leaf with `BSPTree::toggle_split`.
+    ToggleSplit,
+    /// Enter or leave fullscreen with `BSPTree::toggle_fullscreen`.
+    ToggleFullscreen,
+    /// Swap the focused leaf and it's sibling with `BSPTree::rotate_focused`.
+    Rotate,
+    /// Move the focused leaf up with `BSPTree::promote_focused`.
+    Promote,
+}
+
+/// The commands applied to a BSPTree since `BSPTree::record` was called, together with the tree
+/// as it was at that point, so that the result can be reproduced with `BSPTree::replay`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CommandLog<T = NodeData> {
+    start: Box<BSPTree<T>>,
+    commands: Vec<TreeCommand<T>>,
+}
+
+impl<T> CommandLog<T> {
+    /// Make a log of `commands` which are applied to `start`.
+    pub fn new(start: BSPTree<T>, commands: Vec<TreeCommand<T>>) -> Self {
+        Self {
+            start: Box::new(start),
+            commands,
+        }
+    }
+
+    /// Get the tree the commands are applied to.
+    pub fn start(&self) -> &BSPTree<T> {
+        &self.start
+    }
+
+    /// Get the commands in the order in which they were applied.
+    pub fn commands(&self) -> &[TreeCommand<T>] {
+        &self.commands
+    }
+}
+
+/// A difference between the leaves of two BSPTrees, reported by `BSPTree::diff`. Leaves are
+/// matched by their data.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TreeChange<T = NodeData> {
+    /// A leaf only exists in the other tree.
+    Inserted { data: T, rect: Rectangle },
+    /// A leaf only exists in this tree.
+    Removed { data: T, rect: Rectangle },
+    /// A leaf exists in both trees, but with a different size.
+    Resized {
+        data: T,
+        from: Rectangle,
+        to: Rectangle,
+    },
+}
+
+/// A callback registered with `BSPTree::on_change`.
+type Observer = Box<dyn FnMut(&TreeEvent) + Send>;
+
+/// The callbacks registered with `BSPTree::on_change`. They are not copied when a tree is cloned
+/// and are ignored when trees are compared.
+#[derive(Default)]
+struct Observers(Vec<Observer>);
+
+impl Clone for Observers {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
+impl fmt::Debug for Observers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Observers({})", self.0.len())
+    }
+}
+
+impl PartialEq for Observers {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+/// The order in which the nodes of a BSPTree are visited.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+pub enum TraversalOrder {
+    /// Each node comes before it's children.
+    PreOrder,
+    /// Each node comes between it's left and right child.
+    InOrder,
+    /// Each node comes after it's children.
+    PostOrder,
+}
+
+/// The number of previously focused leaves remembered by a BSPTree.
+pub const FOCUS_HISTORY_LEN: usize = 16;
+
+/// How a length which can't be divided evenly is split between the children of a node. The
+/// second child always gets what is left, so that the children cover their parent exactly.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash, Default)]
+pub enum Rounding {
+    /// The first child gets it's share rounded down.
+    #[default]
+    Floor,
+    /// The first child gets it's share rounded to the nearest pixel.
+    Nearest,
+    /// The first child gets it's share rounded up.
+    Ceil,
+}
+
+impl SplitDirection {
+    /// Split `rect` in two. The first rectangle gets `ratio` of the space, rounded down, the
+    /// second one gets the rest.
+    pub fn split(&self, rect: Rectangle, ratio: f32) -> (Rectangle, Rectangle) {
+        self.split_rounded(rect, ratio, Rounding::Floor)
+    }
+
+    /// Split `rect` in two like `split`, rounding the share of the first rectangle as given by
+    /// `rounding`.
+    pub fn split_rounded(
+        &self,
+        rect: Rectangle,
+        ratio: f32,
+        rounding: Rounding,
+    ) -> (Rectangle, Rectangle) {
+        match self {
+            Self::Horizontal => {
+                let (fst, snd) = Self::divide(rect.h, ratio, rounding);
+                (
+                    Rectangle::new(rect.x, rect.y, rect.w, fst),
+                    Rectangle::new(rect.x, rect.y + fst as i32, rect.w, snd),
+                )
+            }
+            Self::Vertical => {
+                let (fst, snd) = Self::divide(rect.w, ratio, rounding);
+                (
+                    Rectangle::new(rect.x, rect.y, fst, rect.h),
+                    Rectangle::new(rect.x + fst as i32, rect.y, snd, rect.h),
+                )
+            }
+        }
+    }
+
+    /// Split `rect` in two, so that the first rectangle is `offset` pixels long and the second one
+    /// gets the rest. The offset is limited to the length of `rect`.
+    pub fn split_at(&self, rect: Rectangle, offset: u32) -> (Rectangle, Rectangle) {
+        match self {
+            Self::Horizontal => {
+                let fst = offset.min(rect.h);
+                (
+                    Rectangle::new(rect.x, rect.y, rect.w, fst),
+                    Rectangle::new(rect.x, rect.y + fst as i32, rect.w, rect.h - fst),
+                )
+            }
+            Self::Vertical => {
+                let fst = offset.min(rect.w);
+                (
+                    Rectangle::new(rect.x, rect.y, fst, rect.h),
+                    Rectangle::new(rect.x + fst as i32, rect.y, rect.w - fst, rect.h),
+                )
+            }
+        }
+    }
+
+    /// The split ratio which divides `rect` at `offset` pixels, kept in the range of ratios a
+    /// node can have.
+    fn ratio_at(&self, rect: Rectangle, offset: u32) -> f32 {
+        let length = match self {
+            Self::Horizontal => rect.h,
+            Self::Vertical => rect.w,
+        };
+        if length == 0 {
+            return DEFAULT_RATIO;
+        }
+
+        (offset as f32 / length as f32).clamp(MIN_RATIO, MAX_RATIO)
+    }
+
+    fn divide(length: u32, ratio: f32, rounding: Rounding) -> (u32, u32) {
+        let share = length as f64 * ratio as f64;
+        let fst = match rounding {
+            Rounding::Floor => share.floor(),
+            Rounding::Nearest => share.round(),
+            Rounding::Ceil => share.ceil(),
+        };
+        let fst = (fst as u32).min(length);
+        (fst, length - fst)
+    }
+
+    /// Get a ratio for which `divide` gives the first part exactly `share` of `length`, or `None`
+    /// if that would leave one of the parts empty.
+    fn ratio_of(length: u32, share: u32, rounding: Rounding) -> Option<f32> {
+        if share == 0 || share >= length {
+            return None;
+        }
+
+        // aim for the middle of the ratios which round to `share`
+        let bias = match rounding {
+            Rounding::Floor => 0.5,
+            Rounding::Nearest => 0.0,
+            Rounding::Ceil => -0.5,
+        };
+        Some(((share as f64 + bias) / length as f64) as f32)
+    }
+
+    /// The split which divides space along the axis of the given `direction`.
+    fn along(direction: MoveDirection) -> Self {
+        match direction {
+            MoveDirection::Left | MoveDirection::Right => Self::Vertical,
+            MoveDirection::Up | MoveDirection::Down => Self::Horizontal,
+        }
+    }
+}
+
+/// How `BSPTree::insert` chooses the split of the focused leaf.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash, Default)]
+pub enum SplitPolicy {
+    /// Use the split stored in the focused leaf, which can be changed with `toggle_split`.
+    #[default]
+    Manual,
+    /// Split along the longer side of the focused leaf, i.e. wide leaves are split `Vertical`
+    /// and tall ones `Horizontal`, which produces a spiral layout.
+    AutoSplit,
+}
+
+impl SplitPolicy {
+    /// The split of a leaf covering `rect` whose own split is `manual`.
+    fn choose(&self, rect: Rectangle, manual: SplitDirection) -> SplitDirection {
+        match self {
+            Self::Manual => manual,
+            Self::AutoSplit if rect.h > rect.w => SplitDirection::Horizontal,
+            Self::AutoSplit => SplitDirection::Vertical,
+        }
+    }
+}
+
+/// What a `LayoutPolicy` gets to know about a tree when a new leaf is inserted.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct InsertContext<'a> {
+    /// The ids and sizes of all leaves, from left to right. There always is at least one.
+    pub leaves: &'a [(NodeId, Rectangle)],
+    /// The focused leaf.
+    pub focused: NodeId,
+    /// The split stored in the focused leaf, which is changed by `BSPTree::toggle_split`.
+    pub split: SplitDirection,
+    /// The area of the tree left for the leaves, see `BSPTree::get_area`.
+    pub size: Rectangle,
+}
+
+/// Where and how a `LayoutPolicy` puts a new leaf into a tree.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Placement {
+    /// The leaf which is split in two to make room for the new leaf.
+    pub target: NodeId,
+    pub split: SplitDirection,
+    /// The share of the space which goes to the first child.
+    pub ratio: f32,
+    /// The side of the split on which the new leaf is placed.
+    pub side: Side,
+}
+
+/// Decides where `BSPTree::insert` puts new leaves and how the space is divided between them,
+/// which makes the tree follow different tiling schemes. `SplitPolicy` is the default one,
+/// `MasterStack` and `Grid` are other examples.
+pub trait LayoutPolicy: fmt::Debug + Send + Sync {
+    /// Choose the placement of a new leaf. The target has to be one of the leaves in `context`.
+    fn place(&self, context: &InsertContext<'_>) -> Placement;
+}
+
+/// Binary splits of the focused leaf in half. `Manual` splits always follow the split of the
+/// focused leaf, `AutoSplit` gives a spiral layout.
+impl LayoutPolicy for SplitPolicy {
+    fn place(&self, context: &InsertContext<'_>) -> Placement {
+        let focused = context.focused;
+        let rect = context
+            .leaves
+            .iter()
+            .find(|&&(id, _)| id == focused)
+            .map_or(context.size, |&(_, rect)| rect);
+        let split = self.choose(rect, context.split);
+
+        Placement {
+            target: focused,
+            split,
+            ratio: DEFAULT_RATIO,
+            side: Side::Second,
+        }
+    }
+}
+
+/// The first leaf is the master on the left, taking `ratio` of the width, all the other leaves
+/// are stacked on the right. Each new leaf splits the last leaf of the stack in half.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct MasterStack {
+    pub ratio: f32,
+}
+
+impl Default for MasterStack {
+    fn default() -> Self {
+        Self {
+            ratio: DEFAULT_RATIO,
+        }
+    }
+}
+
+impl LayoutPolicy for MasterStack {
+    fn place(&self, context: &InsertContext<'_>) -> Placement {
+        let (split, ratio) = match context.leaves.len() {
+            1 => (SplitDirection::Vertical, self.ratio),
+            _ => (SplitDirection::Horizontal, DEFAULT_RATIO),
+        };
+        let &(target, _) = context
+            .leaves
+            .last()
+            .expect("a tree with a focused leaf has leaves");
+
+        Placement {
+            target,
+            split,
+            ratio,
+            side: Side::Second,
+        }
+    }
+}
+
+/// Every new leaf splits the largest leaf along it's longer side, so that the leaves form an
+/// even grid.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct Grid;
+
+impl LayoutPolicy for Grid {
+    fn place(&self, context: &InsertContext<'_>) -> Placement {
+        let mut largest = context.leaves[0];
+        for &(id, rect) in context.leaves.iter() {
+            if rect.area() > largest.1.area() {
+                largest = (id, rect);
+            }
+        }
+        let (target, rect) = largest;
+
+        Placement {
+            target,
+            split: if rect.h > rect.w {
+                SplitDirection::Horizontal
+            } else {
+                SplitDirection::Vertical
+            },
+            ratio: DEFAULT_RATIO,
+            side: Side::Second,
+        }
+    }
+}
+
+/// The layout policy of a tree set with `BSPTree::set_policy`. Trees are equal if they share
+/// the same policy or have none.
+#[derive(Clone, Debug, Default)]
+struct Policy(Option<Arc<dyn LayoutPolicy>>);
+
+impl PartialEq for Policy {
+    fn eq(&self, other: &Self) -> bool {
+        match (&self.0, &other.0) {
+            (Some(a), Some(b)) => Arc::ptr_eq(a, b),
+            (a, b) => a.is_none() && b.is_none(),
+        }
+    }
+}
+
+/// The side of a split on which a new node is placed. With a `Vertical` split the `First` side
+/// is on the left, with a `Horizontal` split it's on the top.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Side {
+    First,
+    Second,
+}
+
+/// Structure representing a simple rectangle.
+/// The `x` and `y` fields represent the **top-left** corner of the rectangle.
+/// The `w` and `h` fields represent the width and height of the Rectangle starting from the top
+/// left point. The corner can lie at negative coordinates, e.g. for a screen left of the primary
+/// one.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash, PartialOrd, Ord)]
+pub struct Rectangle {
+    pub x: i32,
+    pub y: i32,
+    pub w: u32,
+    pub h: u32,
+}
+
+impl Rectangle {
+    pub fn new(x: i32, y: i32, w: u32, h: u32) -> Self {
+        Self { x, y, w, h }
+    }
+
+    /// Whether the point lies in the rectangle. The right and bottom edges are just past it, so
+    /// that a point on the edge shared by two rectangles is only inside one of them.
+    pub fn is_inside(&self, x_: i32, y_: i32) -> bool {
+        (x_ >= self.x && x_ < self.right()) && (y_ >= self.y && y_ < self.bottom())
+    }
+
+    /// The x coordinate just past the right edge of the rectangle.
+    pub fn right(&self) -> i32 {
+        self.x + self.w as i32
+    }
+
+    /// The y coordinate just past the bottom edge of the rectangle.
+    pub fn bottom(&self) -> i32 {
+        self.y + self.h as i32
+    }
+
+    /// The number of pixels covered by the rectangle.
+    pub fn area(&self) -> u64 {
+        self.w as u64 * self.h as u64
+    }
+
+    /// The center point of the rectangle, rounded towards the top-left corner.
+    pub fn center(&self) -> (i32, i32) {
+        (self.x + (self.w / 2) as i32, self.y + (self.h / 2) as i32)
+    }
+
+    /// Return true if `other` lies completely inside of this rectangle.
+    pub fn contains_rect(&self, other: &Rectangle) -> bool {
+        other.x >= self.x
+            && other.y >= self.y
+            && other.right() <= self.right()
+            && other.bottom() <= self.bottom()
+    }
+
+    /// Return true if the two rectangles share at least one pixel. Rectangles which only touch
+    /// along an edge don't intersect.
+    pub fn intersects(&self, other: &Rectangle) -> bool {
+        self.intersection(other).is_some()
+    }
+
+    /// Get the area shared by both rectangles, or `None` if they don't intersect.
+    pub fn intersection(&self, other: &Rectangle) -> Option<Rectangle> {
+        let x = self.x.max(other.x);
+        let y = self.y.max(other.y);
+        let right = self.right().min(other.right());
+        let bottom = self.bottom().min(other.bottom());
+
+        (x < right && y < bottom)
+            .then(|| Rectangle::new(x, y, (right - x) as u32, (bottom - y) as u32))
+    }
+
+    /// Get the smallest rectangle containing both rectangles.
+    pub fn union(&self, other: &Rectangle) -> Rectangle {
+        let x = self.x.min(other.x);
+        let y = self.y.min(other.y);
+        let right = self.right().max(other.right());
+        let bottom = self.bottom().max(other.bottom());
+
+        Rectangle::new(x, y, (right - x) as u32, (bottom - y) as u32)
+    }
+
+    /// Shrink the rectangle by `amount` on every side. A rectangle which is too small for that
+    /// is shrunk as far as possible, keeping it's center.
+    pub fn inset(&self, amount: u32) -> Rectangle {
+        let dx = amount.min(self.w / 2);
+        let dy = amount.min(self.h / 2);
+
+        Rectangle::new(
+            self.x + dx as i32,
+            self.y + dy as i32,
+            self.w - 2 * dx,
+            self.h - 2 * dy,
+        )
+    }
+}
+
+impl fmt::Display for Rectangle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "({}, {}); ({}, {})", self.x, self.y, self.w, self.h)
+    }
+}
+
+/// Space left around the leaves of a BSPTree. `inner` is the space between two neighboring
+/// leaves, `outer` is the space between a leaf and the edge of the tree.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash, Default)]
+pub struct Gaps {
+    pub inner: u32,
+    pub outer: u32,
+}
+
+impl Gaps {
+    pub fn new(inner: u32, outer: u32) -> Self {
+        Self { inner, outer }
+    }
+
+    /// Shrink the `rect` of a leaf inside of a tree of size `bounds` by the gaps. Sides on the
+    /// edge of `bounds` are moved by `outer`, the others by half of `inner`.
+    pub fn apply(&self, rect: Rectangle, bounds: Rectangle) -> Rectangle {
+        let inner_start = self.inner - self.inner / 2;
+        let inner_end = self.inner / 2;
+
+        let left = if rect.x <= bounds.x {
+            self.outer
+        } else {
+            inner_start
+        };
+        let top = if rect.y <= bounds.y {
+            self.outer
+        } else {
+            inner_start
+        };
+        let right = if rect.right() >= bounds.right() {
+            self.outer
+        } else {
+            inner_end
+        };
+        let bottom = if rect.bottom() >= bounds.bottom() {
+            self.outer
+        } else {
+            inner_end
+        };
+
+        Rectangle::new(
+            rect.x + left.min(rect.w) as i32,
+            rect.y + top.min(rect.h) as i32,
+            rect.w.saturating_sub(left + right),
+            rect.h.saturating_sub(top + bottom),
+        )
+    }
+}
+
+/// A description of the shape of a BSPTree, without any of the computed sizes. It can be turned
+/// back into a tree of any size with `BSPTree::from_layout`. With the `serde` feature enabled it
+/// can be serialized, so that layouts can be saved and restored later.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Layout<T = NodeData> {
+    Leaf {
+        data: T,
+        /// The split used when this leaf is split further.
+        split: SplitDirection,
+        focused: bool,
+    },
+    Split {
+        split: SplitDirection,
+        ratio: f32,
+        left: Box<Layout<T>>,
+        right: Box<Layout<T>>,
+    },
+}
+
+/// A plain copy of the information stored in a Node, which can be used without borrowing the
+/// tree.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NodeView<T = NodeData> {
+    /// The data of a leaf, `None` for internal nodes.
+    pub data: Option<T>,
+    pub rect: Rectangle,
+    pub split: SplitDirection,
+    pub ratio: f32,
+    pub leaf: bool,
+    pub focused: bool,
+    /// Whether the node is covered by a fullscreen leaf.
+    pub hidden: bool,
+    /// The distance from the root, which has depth `0`.
+    pub depth: usize,
+}
+
+impl<T: Clone> NodeView<T> {
+    fn new(node: &Node<T>, depth: usize, hidden: bool) -> Self {
+        Self {
+            data: node.data.clone(),
+            rect: node.rect,
+            split: node.split,
+            ratio: node.ratio,
+            leaf: node.leaf,
+            focused: node.focused,
+            hidden,
+            depth,
+        }
+    }
+}
+
+/// The position of a visible leaf, as returned by `BSPTree::layout`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+pub struct LeafGeometry<T = NodeData> {
+    pub data: T,
+    pub rect: Rectangle,
+    pub focused: bool,
+}
+
+/// The space of a leaf as returned by `BSPTree::frame`. The `outer` rectangle is the leaf with the
+/// gaps applied, the `inner` one is what is left inside of the border.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+pub struct Frame {
+    pub outer: Rectangle,
+    pub inner: Rectangle,
+}
+
+/// An iterator over the nodes of a BSPTree in a `TraversalOrder`, created by `BSPTree::iter` or
+/// `BSPTree::iter_order`.
+pub struct Iter<'a, T> {
+    tree: &'a BSPTree<T>,
+    order: TraversalOrder,
+    /// The nodes still to visit together with their depth and whether their children have
+    /// already been pushed.
+    stack: Vec<(NodeId, usize, bool)>,
+}
+
+impl<T> Iter<'_, T> {
+    fn next_id(&mut self) -> Option<(NodeId, usize)> {
+        loop {
+            let (id, depth, expanded) = self.stack.pop()?;
+            if expanded {
+                return Some((id, depth));
+            }
+            let n = &self.tree[id];
+
+            // push in the reverse order of visiting
+            let (left, right) = (n.left, n.right);
+            let push = |stack: &mut Vec<_>, child: Option<NodeId>| {
+                stack.extend(child.map(|c| (c, depth + 1, false)))
+            };
+            match self.order {
+                TraversalOrder::PreOrder => {
+                    push(&mut self.stack, right);
+                    push(&mut self.stack, left);
+                    return Some((id, depth));
+                }
+                TraversalOrder::InOrder => {
+                    push(&mut self.stack, right);
+                    self.stack.push((id, depth, true));
+                    push(&mut self.stack, left);
+                }
+                TraversalOrder::PostOrder => {
+                    self.stack.push((id, depth, true));
+                    push(&mut self.stack, right);
+                    push(&mut self.stack, left);
+                }
+            }
+        }
+    }
+}
+
+impl<T: Clone> Iterator for Iter<'_, T> {
+    type Item = NodeView<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (id, depth) = self.next_id()?;
+        Some(NodeView::new(
+            &self.tree[id],
+            depth,
+            self.tree.is_hidden(id),
+        ))
+    }
+}
+
+/// An iterator over the nodes of a BSPTree in level-order, created by `BSPTree::iter_bfs`.
+pub struct BfsIter<'a, T> {
+    tree: &'a BSPTree<T>,
+    queue: VecDeque<(NodeId, usize)>,
+}
+
+impl<T: Clone> Iterator for BfsIter<'_, T> {
+    type Item = NodeView<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (id, depth) = self.queue.pop_front()?;
+        let n = &self.tree[id];
+
+        if let Some(l) = n.left {
+            self.queue.push_back((l, depth + 1));
+        }
+        if let Some(r) = n.right {
+            self.queue.push_back((r, depth + 1));
+        }
+
+        Some(NodeView::new(n, depth, self.tree.is_hidden(id)))
+    }
+}
+
+/// A part of a BSPTree taken out by `BSPTree::detach_focused_subtree`, which can be put back into
+/// the same or another tree with `BSPTree::attach`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DetachedSubtree<T = NodeData> {
+    layout: Layout<T>,
+    /// The metadata of the nodes, in pre-order.
+    meta: Vec<BTreeMap<String, String>>,
+}
+
+impl<T> DetachedSubtree<T> {
+    /// Get the shape of the subtree.
+    pub fn layout(&self) -> &Layout<T> {
+        &self.layout
+    }
+
+    /// Get the shape of the subtree, dropping the metadata of it's nodes.
+    pub fn into_layout(self) -> Layout<T> {
+        self.layout
+    }
+}
+
+/// A subtree without any metadata, so that any layout can be attached to a tree.
+impl<T> From<Layout<T>> for DetachedSubtree<T> {
+    fn from(layout: Layout<T>) -> Self {
+        Self {
+            layout,
+            meta: vec![],
+        }
+    }
+}
+
+/// Callbacks for `BSPTree::visit`, which walks the tree depth-first from left to right. Every
+/// callback gets the id of the node, the node itself and it's depth, with the root at depth `0`.
+/// All the callbacks do nothing by default.
+pub trait TreeVisitor<T> {
+    /// Called for an internal node before any of it's children.
+    fn enter_internal(&mut self, _id: NodeId, _node: &Node<T>, _depth: usize) {}
+
+    /// Called for every leaf.
+    fn visit_leaf(&mut self, _id: NodeId, _node: &Node<T>, _depth: usize) {}
+
+    /// Called for an internal node after all of it's children.
+    fn leave_internal(&mut self, _id: NodeId, _node: &Node<T>, _depth: usize) {}
+}
+
+/// The parts of a BSPTree saved by `undo` and `redo`.
+#[derive(Clone, Debug, PartialEq)]
+struct Snapshot<T> {
+    nodes: Vec<Option<Node<T>>>,
+    free: Vec<NodeId>,
+    root: Option<NodeId>,
+    focused: Option<NodeId>,
+    fullscreen: Option<(NodeId, Rectangle)>,
+    hidden: Vec<NodeId>,
+    floating: Vec<NodeId>,
+}
+
+/// A Binary Space Partitioning Tree is a type of binary tree with with all nodes having either two
+/// or zero descendants. The BSP Tree can be used to divide a rectangle into chunks, each the
+/// half the size of the previous chunk.
+///
+/// The nodes are stored in an arena and refer to each other by their `NodeId`. Slots of removed
+/// nodes are kept in a free list and reused by later insertions. As nodes don't point at each
+/// other, a clone of the tree is a completely independent copy. For the same reason dropping a
+/// tree never recurses, no matter how deep it is.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BSPTree<T = NodeData> {
+    nodes: Vec<Option<Node<T>>>,
+    free: Vec<NodeId>,
+    root: Option<NodeId>,
+    focused: Option<NodeId>,
+    /// The fullscreen leaf together with the rectangle it has in the layout.
+    fullscreen: Option<(NodeId, Rectangle)>,
+    /// Leaves taken out of the layout by `hide_focused`, in the order in which they were hidden.
+    /// They keep their slot in the arena, but are not linked to any other node.
+    hidden: Vec<NodeId>,
+    /// Leaves taken out of the layout by `float_focused`, from the bottom to the top of the
+    /// stack. Like hidden leaves they are not linked to any other node.
+    floating: Vec<NodeId>,
+    size: Rectangle,
+    /// Regions along the edges of the tree which are left out of the layout, e.g. for panels.
+    reserved: Vec<(ReservationId, MoveDirection, u32)>,
+    next_reservation: usize,
+    gaps: Gaps,
+    /// The width of the border drawn inside of every leaf, unless the leaf has it's own.
+    border: u32,
+    /// The smallest width and height a leaf can be split or resized to.
+    min_size: (u32, u32),
+    rounding: Rounding,
+    split_policy: SplitPolicy,
+    /// Overrides the `split_policy` if set.
+    policy: Policy,
+    /// Whether moving the focus past an edge of the tree continues at the opposite edge.
+    wrap_focus: bool,
+    undo_stack: Vec<Snapshot<T>>,
+    redo_stack: Vec<Snapshot<T>>,
+    /// Previously focused leaves, the most recent first.
+    history: VecDeque<NodeId>,
+    observers: Observers,
+    /// The log started by `record`.
+    recording: Option<CommandLog<T>>,
+}
+
+impl<T> ops::Index<NodeId> for BSPTree<T> {
+    type Output = Node<T>;
+
+    /// Get the node with the given `id`. Panics if the node has been removed from the tree, see
+    /// `BSPTree::node` for a non-panicking version.
+    fn index(&self, id: NodeId) -> &Node<T> {
+        self.nodes[id.0]
+            .as_ref()
+            .expect("the node has been removed from the tree")
+    }
+}
+
+impl<T: Clone> BSPTree<T> {
+    /// Create a new BSPTree with a given `size`.
+    pub fn new(size: Rectangle) -> Self {
+        Self {
+            nodes: vec![],
+            free: vec![],
+            root: None,
+            focused: None,
+            fullscreen: None,
+            hidden: vec![],
+            floating: vec![],
+            size,
+            reserved: vec![],
+            next_reservation: 0,
+            gaps: Gaps::default(),
+            border: 0,
+            min_size: (0, 0),
+            rounding: Rounding::default(),
+            split_policy: SplitPolicy::Manual,
+            policy: Policy::default(),
+            wrap_focus: false,
+            undo_stack: vec![],
+            redo_stack: vec![],
+            history: VecDeque::new(),
+            observers: Observers::default(),
+            recording: None,
+        }
+    }
+
+    /// Rebuild a tree of the given `size` from a `layout`. If no leaf of the layout is focused,
+    /// the first one gets the focus.
+    pub fn from_layout(layout: Layout<T>, size: Rectangle) -> Self {
+        let mut tree = Self::new(size);
+        let root = tree.build(layout, size);
+
+        tree.root = Some(root);
+        tree.focused = tree.find_focused();
+        if tree.focused.is_none() {
+            tree.focus_nth(0);
+        }
+
+        tree
+    }
+
+    /// Build a balanced tree of the given `size` with a leaf for each of the `items`, in
+    /// left-to-right order, all of which get the same share of the space. The tree is built in one
+    /// go, instead of inserting the leaves one by one. With `SplitPolicy::Manual` every node is
+    /// split `Vertical`, giving columns, `SplitPolicy::AutoSplit` splits every node along it's
+    /// longer side, giving a grid. The policy is kept for later insertions and the first leaf
+    /// gets the focus.
+    pub fn from_leaves(size: Rectangle, items: &[T], policy: SplitPolicy) -> Self {
+        let mut tree = match items {
+            [] => Self::new(size),
+            _ => Self::from_layout(Self::balanced(items, size, policy), size),
+        };
+
+        tree.split_policy = policy;
+        tree
+    }
+
+    /// Describe a balanced tree holding `items`, dividing `rect` in proportion to the number of
+    /// items on each side. `items` must not be empty.
+    fn balanced(items: &[T], rect: Rectangle, policy: SplitPolicy) -> Layout<T> {
+        let split = policy.choose(rect, SplitDirection::Vertical);
+
+        if let [data] = items {
+            return Layout::Leaf {
+                data: data.clone(),
+                split,
+                focused: false,
+            };
+        }
+
+        let (first, second) = items.split_at(items.len() / 2);
+        let ratio = first.len() as f32 / items.len() as f32;
+        let (lrect, rrect) = split.split(rect, ratio);
+        Layout::Split {
+            split,
+            ratio,
+            left: Box::new(Self::balanced(first, lrect, policy)),
+            right: Box::new(Self::balanced(second, rrect, policy)),
+        }
+    }
+
+    /// Describe the shape of the tree, so that it can be rebuilt later with `from_layout`.
+    /// Returns `None` if the tree is empty.
+    pub fn to_layout(&self) -> Option<Layout<T>> {
+        self.root.map(|r| self.layout_of(r))
+    }
+
+    /// Get the size of the whole tree.
+    pub fn get_size(&self) -> Rectangle {
+        self.size
+    }
+
+    /// Change the size of the whole tree and lay out all nodes again to fill it.
+    pub fn resize(&mut self, size: Rectangle) {
+        self.size = size;
+        self.relayout_dirty();
+    }
+
+    /// Get the part of the tree which is left for the leaves, i.e. the size of the tree without
+    /// the reserved regions.
+    pub fn get_area(&self) -> Rectangle {
+        let mut area = self.size;
+
+        for &(_, edge, pixels) in self.reserved.iter() {
+            match edge {
+                MoveDirection::Left => {
+                    let pixels = pixels.min(area.w);
+                    area.x += pixels as i32;
+                    area.w -= pixels;
+                }
+                MoveDirection::Right => area.w -= pixels.min(area.w),
+                MoveDirection::Up => {
+                    let pixels = pixels.min(area.h);
+                    area.y += pixels as i32;
+                    area.h -= pixels;
+                }
+                MoveDirection::Down => area.h -= pixels.min(area.h),
+            }
+        }
+        area
+    }
+
+    /// Keep a strip `pixels` wide along the given `edge` of the tree free of leaves, e.g. for a
+    /// panel or a status bar, and lay out the tree again. Reservations on the same edge are
+    /// stacked. The returned id can be passed to `unreserve` to give the space back. Fullscreen
+    /// leaves still cover the whole tree.
+    pub fn reserve(&mut self, edge: MoveDirection, pixels: u32) -> ReservationId {
+        let id = ReservationId(self.next_reservation);
+        self.next_reservation += 1;
+        self.reserved.push((id, edge, pixels));
+        self.relayout_dirty();
+        id
+    }
+
+    /// Give the space reserved with `reserve` back to the leaves. Returns false if there is no
+    /// such reservation.
+    pub fn unreserve(&mut self, id: ReservationId) -> bool {
+        let count = self.reserved.len();
+        self.reserved.retain(|&(r, _, _)| r != id);
+        if self.reserved.len() == count {
+            return false;
+        }
+
+        self.relayout_dirty();
+        true
+    }
+
+    /// Set the split ratio of the node `id` without laying out the tree again, so that many
+    /// ratios can be changed before calling `relayout_dirty` once. The ratio of a leaf is used
+    /// when it's split. Unlike `resize_focused`, this doesn't look at the minimum size and isn't
+    /// recorded in the undo history.
+    ///
+    /// Returns `TreeError::InvalidNode` if there is no node `id`.
+    pub fn set_ratio(&mut self, id: NodeId, ratio: f32) -> Result<(), TreeError> {
+        let n = self.try_node_mut(id)?;
+        n.ratio = ratio.clamp(MIN_RATIO, MAX_RATIO);
+        if !n.leaf {
+            self.mark_dirty(id);
+        }
+        Ok(())
+    }
+
+    /// Return true if some nodes have changed since the tree was last laid out, see `set_ratio`.
+    pub fn needs_relayout(&self) -> bool {
+        self.root.is_some_and(|r| self[r].dirty)
+    }
+
+    /// Lay out the tree again to fill it's area. Only the nodes which have changed and the
+    /// subtrees whose size has changed are visited, the rest of the tree is skipped.
+    pub fn relayout_dirty(&mut self) {
+        if let Some(r) = self.root {
+            self.lay_out(r, self.get_area(), false);
+        }
+    }
+
+    /// Return true if the tree has no nodes.
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+
+    /// Get the number of nodes in the tree, both leaves and internal nodes. Hidden and floating
+    /// leaves are not counted.
+    pub fn node_count(&self) -> usize {
+        self.nodes.len() - self.free.len() - self.hidden.len() - self.floating.len()
+    }
+
+    /// Get the number of leaves in the tree.
+    pub fn leaf_count(&self) -> usize {
+        // every internal node has two children, so there is one more leaf than internal nodes
+        self.node_count().div_ceil(2)
+    }
+
+    /// Get the length of the longest path from the root to a leaf. Both an empty tree and a tree
+    /// with a single leaf have depth `0`.
+    pub fn depth(&self) -> usize {
+        let mut depth = 0;
+        let mut stack: Vec<(NodeId, usize)> = self.root.iter().map(|&r| (r, 0)).collect();
+
+        while let Some((id, d)) = stack.pop() {
+            depth = depth.max(d);
+            let n = &self[id];
+            stack.extend([n.left, n.right].into_iter().flatten().map(|c| (c, d + 1)));
+        }
+        depth
+    }
+
+    /// Check that the tree is well formed: every internal node has exactly two children whose
+    /// rectangles tile it's own, the root covers the whole tree, every node points back to it's
+    /// parent, hidden and floating leaves are not linked to any node and there is exactly one
+    /// focused leaf. Returns the first broken invariant found.
+    pub fn validate(&self) -> Result<(), InvariantError> {
+        let get = |id: NodeId| self.node(id).ok_or(InvariantError::Dangling { id });
+        let mut visited = vec![false; self.nodes.len()];
+        let mut focused = vec![];
+
+        for id in self.detached() {
+            let n = get(id)?;
+            if !n.leaf || n.data.is_none() {
+                return Err(InvariantError::BadChildren { id });
+            }
+            if n.parent.is_some() {
+                return Err(InvariantError::BadParent { id });
+            }
+            if n.focused {
+                return Err(InvariantError::BadFocus);
+            }
+            visited[id.0] = true;
+        }
+
+        let root = match self.root {
+            Some(r) => r,
+            None if self.focused.is_none() && self.node_count() == 0 => return Ok(()),
+            None => return Err(InvariantError::BadFocus),
+        };
+        if self.layout_rect(get(root)?, root) != self.get_area() {
+            return Err(InvariantError::NotTiled { id: root });
+        }
+
+        // (node, parent, right_child)
+        let mut stack = vec![(root, None, false)];
+        while let Some((id, parent, right_child)) = stack.pop() {
+            let n = get(id)?;
+            visited[id.0] = true;
+
+            if n.parent != parent || (parent.is_some() && n.right_child != right_child) {
+                return Err(InvariantError::BadParent { id });
+            }
+            if n.focused {
+                focused.push(id);
+            }
+
+            match (n.left, n.right) {
+                (Some(l), Some(r)) if !n.leaf && n.data.is_none() => {
+                    let rect = self.layout_rect(n, id);
+                    let lrect = self.layout_rect(get(l)?, l);
+                    let rrect = self.layout_rect(get(r)?, r);
+                    let tiled = match n.split {
+                        SplitDirection::Vertical => {
+                            lrect.x == rect.x
+                                && rrect.x == lrect.right()
+                                && rrect.right() == rect.right()
+                                && [lrect, rrect]
+                                    .iter()
+                                    .all(|c| c.y == rect.y && c.h == rect.h)
+                        }
+                        SplitDirection::Horizontal => {
+                            lrect.y == rect.y
+                                && rrect.y == lrect.bottom()
+                                && rrect.bottom() == rect.bottom()
+                                && [lrect, rrect]
+                                    .iter()
+                                    .all(|c| c.x == rect.x && c.w == rect.w)
+                        }
+                    };
+                    if !tiled {
+                        return Err(InvariantError::NotTiled { id });
+                    }
+
+                    stack.push((r, Some(id), true));
+                    stack.push((l, Some(id), false));
+                }
+                (None, None) if n.leaf && n.data.is_some() => {}
+                _ => return Err(InvariantError::BadChildren { id }),
+            }
+        }
+
+        if let Some(id) = (0..self.nodes.len())
+            .map(NodeId)
+            .find(|id| self.nodes[id.0].is_some() && !visited[id.0])
+        {
+            return Err(InvariantError::Unreachable { id });
+        }
+        if focused.len() != 1 || self.focused != focused.first().copied() {
+            return Err(InvariantError::BadFocus);
+        }
+
+        Ok(())
+    }
+
+    /// The rectangle of `node` in the layout, which differs from it's own for a fullscreen leaf.
+    fn layout_rect(&self, node: &Node<T>, id: NodeId) -> Rectangle {
+        match self.fullscreen {
+            Some((f, rect)) if f == id => rect,
+            _ => node.rect,
+        }
+    }
+
+    /// Get the id of the root node, `None` if the tree is empty.
+    pub fn get_root(&self) -> Option<NodeId> {
+        self.root
+    }
+
+    /// Get the id of the focused leaf, `None` if the tree is empty.
+    pub fn get_focused(&self) -> Option<NodeId> {
+        self.focused
+    }
+
+    /// Get the node with the given `id`, or `None` if it has been removed from the tree.
+    pub fn node(&self, id: NodeId) -> Option<&Node<T>> {
+        self.nodes.get(id.0)?.as_ref()
+    }
+
+    /// Register a callback which is called with every change made to the leaves of the tree.
+    /// Undoing or redoing a change reports the resulting differences. Callbacks are not copied
+    /// when the tree is cloned.
+    pub fn on_change(&mut self, callback: impl FnMut(&TreeEvent) + Send + 'static) {
+        self.observers.0.push(Box::new(callback));
+    }
+
+    /// Run `command` on the tree, returning the changes it made in the order in which they were
+    /// reported to the callbacks registered with `on_change`. Commands on the focused leaf fail
+    /// with `TreeError::Empty` if the tree is empty, otherwise the errors are the ones of the
+    /// methods the commands stand for.
+    pub fn apply(&mut self, command: TreeCommand<T>) -> Result<Vec<TreeEvent>, TreeError> {
+        let recorded = self.recording.is_some().then(|| command.clone());
+        let events = Arc::new(Mutex::new(vec![]));
+        let sink = events.clone();
+        self.on_change(move |event| sink.lock().unwrap().push(*event));
+        let result = self.run(command);
+        self.observers.0.pop();
+
+        result?;
+        if let (Some(log), Some(command)) = (self.recording.as_mut(), recorded) {
+            log.commands.push(command);
+        }
+        let events = mem::take(&mut *events.lock().unwrap());
+        Ok(events)
+    }
+
+    /// Start recording the commands run with `apply` in a `CommandLog`, beginning at the current
+    /// state of the tree. A log which is already being recorded is discarded. Changes made by
+    /// calling the other methods directly are not recorded, so they break the replay.
+    pub fn record(&mut self) {
+        self.recording = None;
+        self.recording = Some(CommandLog::new(self.clone(), vec![]));
+    }
+
+    /// Stop recording and return the log, or `None` if the tree isn't being recorded.
+    pub fn stop_recording(&mut self) -> Option<CommandLog<T>> {
+        self.recording.take()
+    }
+
+    /// Get the log being recorded, if any.
+    pub fn recording(&self) -> Option<&CommandLog<T>> {
+        self.recording.as_ref()
+    }
+
+    /// Rebuild the tree recorded in `log`, by applying all of it's commands to the starting tree
+    /// again.
+    ///
+    /// Returns the error of the first command which fails, which means the log doesn't match the
+    /// tree it starts with.
+    pub fn replay(log: &CommandLog<T>) -> Result<Self, TreeError> {
+        let mut tree = (*log.start).clone();
+
+        for command in log.commands.iter() {
+            tree.apply(command.clone())?;
+        }
+        Ok(tree)
+    }
+
+    /// Run `command` like `apply`, without collecting the changes.
+    fn run(&mut self, command: TreeCommand<T>) -> Result<(), TreeError> {
+        if !matches!(command, TreeCommand::Insert(_)) && self.focused.is_none() {
+            return Err(TreeError::Empty);
+        }
+
+        match command {
+            TreeCommand::Insert(data) => self.insert(data),
+            TreeCommand::Delete => self.delete_focused(),
+            TreeCommand::Focus(direction) => self.move_focus(direction),
+            TreeCommand::Move(direction) => self.swap_in_direction(direction),
+            TreeCommand::Resize { direction, amount } => self.resize_focused(direction, amount),
+            TreeCommand::ToggleSplit => self.toggle_split(),
+            TreeCommand::ToggleFullscreen => self.toggle_fullscreen(),
+            TreeCommand::Rotate => self.rotate_focused(),
+            TreeCommand::Promote => self.promote_focused(),
+        }
+    }
+
+    /// Get the gaps left around the leaves.
+    pub fn gaps(&self) -> Gaps {
+        self.gaps
+    }
+
+    /// Set the gaps left around the leaves. They are not applied to the node rectangles
+    /// themselves, only to the rectangles returned by `leaf_rects_with_gaps`.
+    pub fn set_gaps(&mut self, gaps: Gaps) {
+        self.gaps = gaps;
+    }
+
+    /// Get the width of the border of leaves which don't have their own.
+    pub fn get_border_width(&self) -> u32 {
+        self.border
+    }
+
+    /// Set the width of the border of leaves which don't have their own. Like the gaps, the
+    /// border doesn't change the node rectangles, only the ones returned by `frame`.
+    pub fn set_border_width(&mut self, width: u32) {
+        self.border = width;
+    }
+
+    /// Give the node `id` a border `width` of it's own, or make it use the border width of the
+    /// tree again with `None`. The border moves together with the data of a leaf when it is
+    /// swapped with another one.
+    ///
+    /// Returns `TreeError::InvalidNode` if the node has been removed from the tree.
+    pub fn set_node_border(&mut self, id: NodeId, width: Option<u32>) -> Result<(), TreeError> {
+        self.try_node_mut(id)?.border = width;
+        Ok(())
+    }
+
+    /// Get the frame of the leaf `id`, i.e. it's rectangle with the gaps applied and the part of
+    /// it inside of the border, so that the border can be drawn between the two. Floating leaves
+    /// are not part of the layout, so the gaps are not applied to them. Returns `None` if `id` is
+    /// not a leaf of the tree or is hidden.
+    pub fn frame(&self, id: NodeId) -> Option<Frame> {
+        let n = self.node(id).filter(|n| n.leaf)?;
+        if self.hidden.contains(&id) {
+            return None;
+        }
+        let outer = if self.floating.contains(&id) {
+            n.rect
+        } else {
+            self.gaps.apply(n.rect, self.get_area())
+        };
+
+        Some(Frame {
+            outer,
+            inner: outer.inset(n.border.unwrap_or(self.border)),
+        })
+    }
+
+    /// Get the smallest width and height of a leaf.
+    pub fn get_min_size(&self) -> (u32, u32) {
+        self.min_size
+    }
+
+    /// Set the smallest `width` and `height` of a leaf. Leaves are not split and nodes are not
+    /// resized if that would make a leaf smaller, leaves which are already smaller are left as
+    /// they are.
+    pub fn set_min_size(&mut self, width: u32, height: u32) {
+        self.min_size = (width, height);
+    }
+
+    /// Get the way in which lengths which can't be divided evenly are split.
+    pub fn get_rounding(&self) -> Rounding {
+        self.rounding
+    }
+
+    /// Set the way in which lengths which can't be divided evenly are split and lay out the tree
+    /// again. The default is `Rounding::Floor`.
+    pub fn set_rounding(&mut self, rounding: Rounding) {
+        self.rounding = rounding;
+        if let Some(r) = self.root {
+            self.update(r, self.get_area());
+        }
+    }
+
+    /// Get the way in which `insert` chooses the split of the focused leaf.
+    pub fn get_split_policy(&self) -> SplitPolicy {
+        self.split_policy
+    }
+
+    /// Set the way in which `insert` chooses the split of the focused leaf. This has no effect
+    /// while a `LayoutPolicy` is set with `set_policy`.
+    pub fn set_split_policy(&mut self, policy: SplitPolicy) {
+        self.split_policy = policy;
+    }
+
+    /// Let `policy` decide where `insert` puts new leaves, instead of the `SplitPolicy`.
+    pub fn set_policy(&mut self, policy: Box<dyn LayoutPolicy>) {
+        self.policy = Policy(Some(Arc::from(policy)));
+    }
+
+    /// Go back to placing new leaves according to the `SplitPolicy`.
+    pub fn clear_policy(&mut self) {
+        self.policy = Policy(None);
+    }
+
+    /// Return true if moving the focus past an edge of the tree wraps around.
+    pub fn get_wrap_focus(&self) -> bool {
+        self.wrap_focus
+    }
+
+    /// Make `move_focus`, `neighbor` and `swap_in_direction` continue at the opposite edge of the
+    /// tree when there is no leaf in the given direction, instead of doing nothing.
+    pub fn set_wrap_focus(&mut self, wrap: bool) {
+        self.wrap_focus = wrap;
+    }
+
+    /// Get the data and size of all leaves from left to right, with the gaps applied.
+    pub fn leaf_rects_with_gaps(&self) -> Vec<(T, Rectangle)> {
+        self.leaves()
+            .map(|(data, rect)| (data, self.gaps.apply(rect, self.get_area())))
+            .collect()
+    }
+
+    /// Insert a new node as into the tree at the currently focused node, or wherever the
+    /// `LayoutPolicy` of the tree decides. Without a layout policy, the split is chosen according
+    /// to the `SplitPolicy` of the tree.
+    ///
+    /// Returns `TreeError::TooSmall` if the leaf is too small to be split.
+    pub fn insert(&mut self, data: T) -> Result<(), TreeError> {
+        self.insert_placed(Self::new_leaf(data), true).map(|_| ())
+    }
+
+    /// Insert a new node like `insert`, but keep the focus on the currently focused leaf, e.g. for
+    /// windows opened in the background. The new node is only focused if the tree is empty.
+    ///
+    /// Returns the id of the new node, or `TreeError::TooSmall` if the leaf is too small to be
+    /// split.
+    pub fn insert_unfocused(&mut self, data: T) -> Result<NodeId, TreeError> {
+        self.insert_placed(Self::new_leaf(data), false)
+    }
+
+    /// Put the leaf created by `make` wherever `insert` would put a new leaf, focusing it if
+    /// `focus` is set.
+    fn insert_placed(
+        &mut self,
+        make: impl FnOnce(&mut Self, Rectangle, SplitDirection) -> NodeId,
+        focus: bool,
+    ) -> Result<NodeId, TreeError> {
+        let focused = match self.focused {
+            Some(f) => f,
+            None => {
+                return self.insert_leaf(
+                    make,
+                    None,
+                    SplitDirection::Vertical,
+                    None,
+                    Side::Second,
+                    focus,
+                )
+            }
+        };
+
+        let leaves: Vec<_> = self
+            .leaf_ids()
+            .map(|id| (id, self.layout_rect(&self[id], id)))
+            .collect();
+        let context = InsertContext {
+            leaves: &leaves,
+            focused,
+            split: self[focused].split,
+            size: self.get_area(),
+        };
+        let placement = match &self.policy.0 {
+            Some(policy) => policy.place(&context),
+            None => self.split_policy.place(&context),
+        };
+        assert!(
+            leaves.iter().any(|&(id, _)| id == placement.target),
+            "the target of a placement has to be a leaf of the tree"
+        );
+
+        let Placement {
+            target,
+            split,
+            ratio,
+            side,
+        } = placement;
+        let ratio = ratio.clamp(MIN_RATIO, MAX_RATIO);
+        self.insert_leaf(make, Some(target), split, Some(ratio), side, focus)
+    }
+
+    /// Insert a new node into the tree by splitting the currently focused node with `split`,
+    /// placing the new node on the given `side`. The new node gets the focus and is split with
+    /// `split` as well, once it is split further.
+    ///
+    /// Returns `TreeError::TooSmall` if the focused leaf is too small to be split.
+    pub fn insert_with(
+        &mut self,
+        data: T,
+        split: SplitDirection,
+        side: Side,
+    ) -> Result<(), TreeError> {
+        self.insert_leaf(Self::new_leaf(data), self.focused, split, None, side, true)
+            .map(|_| ())
+    }
+
+    /// Insert a new node like `insert_with`, but split the focused node so that the first part,
+    /// the left or top one, is `offset` pixels long, e.g. a 600 pixel wide pane split at `400`
+    /// is divided into 400 and 200 pixels. The proportion is kept as the split ratio, so that it's
+    /// preserved when the tree is resized. The offset is ignored when the tree is empty.
+    ///
+    /// Returns `TreeError::TooSmall` if the focused leaf is too small to be split.
+    pub fn insert_at(
+        &mut self,
+        data: T,
+        split: SplitDirection,
+        offset: u32,
+        side: Side,
+    ) -> Result<(), TreeError> {
+        let ratio = self
+            .focused
+            .map(|f| split.ratio_at(self.layout_rect(&self[f], f), offset));
+        self.insert_leaf(Self::new_leaf(data), self.focused, split, ratio, side, true)
+            .map(|_| ())
+    }
+
+    /// Make a function allocating a leaf with `data`, as used by `insert_leaf`.
+    fn new_leaf(data: T) -> impl FnOnce(&mut Self, Rectangle, SplitDirection) -> NodeId {
+        move |tree, rect, split| tree.alloc(Node::new(rect, split, data))
+    }
+
+    /// Split the leaf `target` in `ratio`, or in it's own ratio, and put the leaf created by
+    /// `make` from it's rectangle and split on the given `side`. Without a target, the new leaf
+    /// becomes the root and is always focused, otherwise only if `focus` is set. Returns the id
+    /// of the new leaf.
+    fn insert_leaf(
+        &mut self,
+        make: impl FnOnce(&mut Self, Rectangle, SplitDirection) -> NodeId,
+        target: Option<NodeId>,
+        split: SplitDirection,
+        ratio: Option<f32>,
+        side: Side,
+        focus: bool,
+    ) -> Result<NodeId, TreeError> {
+        let target = match target {
+            Some(t) => t,
+            None => {
+                self.save_state();
+
+                let rect = self.get_area();
+                let id = make(self, rect, split);
+                self.root = Some(id);
+                self.emit(TreeEvent::Inserted { id, rect });
+                self.set_focus(id);
+                return Ok(id);
+            }
+        };
+
+        let rect = self.layout_rect(&self[target], target);
+        let ratio = ratio.unwrap_or(self[target].ratio);
+        let (lsize, rsize) = split.split_rounded(rect, ratio, self.rounding);
+        let (min_w, min_h) = self.min_size;
+        if [lsize, rsize].iter().any(|r| r.w < min_w || r.h < min_h) {
+            return Err(TreeError::TooSmall);
+        }
+
+        self.save_state();
+        self.leave_fullscreen();
+
+        let new = self.graft(target, rect, split, ratio, side, |tree, rect| {
+            make(tree, rect, split)
+        });
+        let rect = self[new].rect;
+        self.emit(TreeEvent::Inserted { id: new, rect });
+        if focus {
+            self.set_focus(new);
+        }
+        Ok(new)
+    }
+
+    /// Take the container of the focused leaf, i.e. the focused leaf together with it's sibling,
+    /// out of the tree, keeping it's arrangement and metadata. If the focused leaf is the only
+    /// leaf, it's taken on it's own. The space is filled the same way as with `delete_focused`.
+    /// The detached part can be put back into this or another tree with `attach`.
+    ///
+    /// Returns `None` if the tree is empty.
+    pub fn detach_focused_subtree(&mut self) -> Option<DetachedSubtree<T>> {
+        let focused = self.focused?;
+        let id = self[focused].parent.unwrap_or(focused);
+        let subtree = DetachedSubtree {
+            layout: self.layout_of(id),
+            meta: self
+                .walk_from(id)
+                .into_iter()
+                .map(|n| self[n].meta.clone())
+                .collect(),
+        };
+
+        self.save_state();
+        self.remove(id);
+        Some(subtree)
+    }
+
+    /// Put the `subtree` next to the focused leaf, on the side given by `at`, or make it the whole
+    /// tree if the tree is empty. The leaf which was focused in the subtree, or else it's first
+    /// leaf, becomes focused.
+    ///
+    /// Fails without changing anything if any leaf would become smaller than the minimum size.
+    pub fn attach(
+        &mut self,
+        subtree: DetachedSubtree<T>,
+        at: MoveDirection,
+    ) -> Result<(), TreeError> {
+        let DetachedSubtree { layout, meta } = subtree;
+        let split = SplitDirection::along(at);
+        let side = match at {
+            MoveDirection::Left | MoveDirection::Up => Side::First,
+            MoveDirection::Right | MoveDirection::Down => Side::Second,
+        };
+
+        let attached = match self.focused {
+            Some(focused) => {
+                let rect = match self.fullscreen {
+                    Some((_, rect)) => rect,
+                    None => self[focused].rect,
+                };
+                let ratio = self[focused].ratio;
+                let (lsize, rsize) = split.split_rounded(rect, ratio, self.rounding);
+                let (old_size, new_size) = match side {
+                    Side::First => (rsize, lsize),
+                    Side::Second => (lsize, rsize),
+                };
+                let (min_w, min_h) = self.min_size;
+                if old_size.w < min_w || old_size.h < min_h || !self.fits(&layout, new_size) {
+                    return Err(TreeError::TooSmall);
+                }
+
+                self.save_state();
+                self.leave_fullscreen();
+                self.graft(focused, rect, split, ratio, side, |tree, rect| {
+                    tree.build(layout, rect)
+                })
+            }
+            None => {
+                let area = self.get_area();
+                if !self.fits(&layout, area) {
+                    return Err(TreeError::TooSmall);
+                }
+
+                self.save_state();
+                let root = self.build(layout, area);
+                self.root = Some(root);
+                root
+            }
+        };
+
+        // the focus flags of the subtree are stale, the focus is moved with `set_focus` instead
+        let ids = self.walk_from(attached);
+        let mut meta = meta.into_iter();
+        let (mut first, mut marked) = (None, None);
+        for &id in ids.iter() {
+            let n = self.node_mut(id);
+            n.meta = meta.next().unwrap_or_default();
+            if mem::take(&mut n.focused) {
+                marked = Some(id);
+            }
+            if n.leaf {
+                first = first.or(Some(id));
+                let rect = n.rect;
+                self.emit(TreeEvent::Inserted { id, rect });
+            }
+        }
+        if let Some(f) = marked.or(first) {
+            self.set_focus(f);
+        }
+        Ok(())
+    }
+
+    /// Delete the currently focused node.
+    /// Focus is set to the node which fills the space of the deleted node.
+    ///
+    /// Returns `TreeError::Empty` if the tree is empty.
+    pub fn delete_focused(&mut self) -> Result<(), TreeError> {
+        let f = self.focused.ok_or(TreeError::Empty)?;
+        self.save_state();
+        self.remove(f);
+        Ok(())
+    }
+
+    /// Remove the leaf holding `data`, the same way as `delete_focused` would. The focus only
+    /// moves if the removed leaf was focused. Hidden and floating leaves are looked at after the
+    /// ones in the layout, e.g. to remove a minimised window which was closed. Returns the removed
+    /// data, or `None` if no leaf holds `data`.
+    pub fn remove_data(&mut self, data: &T) -> Option<T>
+    where
+        T: PartialEq,
+    {
+        if let Some(leaf) = self.find_leaf(data) {
+            self.save_state();
+            return self.remove(leaf);
+        }
+
+        let leaf = self
+            .detached()
+            .find(|&id| self[id].data.as_ref() == Some(data))?;
+        self.save_state();
+        self.remove_detached(leaf)
+    }
+
+    /// Replace the data of the focused leaf with `data`, keeping the leaf where it is. Returns the
+    /// old data, or `None` if the tree is empty, in which case nothing changes.
+    pub fn set_focused_data(&mut self, data: T) -> Option<T> {
+        let f = self.focused?;
+
+        self.save_state();
+        self.node_mut(f).data.replace(data)
+    }
+
+    /// Replace the data of the first leaf holding `old`, from left to right, with `new`, e.g.
+    /// when the ids of the windows in the leaves change. Hidden and floating leaves are looked at
+    /// after the ones in the layout. Returns the replaced data, or `None` if no leaf holds `old`.
+    pub fn replace_data(&mut self, old: &T, new: T) -> Option<T>
+    where
+        T: PartialEq,
+    {
+        let leaf = self.find_leaf(old).or_else(|| {
+            self.detached()
+                .find(|&id| self[id].data.as_ref() == Some(old))
+        })?;
+
+        self.save_state();
+        self.node_mut(leaf).data.replace(new)
+    }
+
+    /// Find the leaf corresponding to the given coordinates.
+    #[deprecated(note = "use `leaf_at` or `deepest_container_at` instead")]
+    pub fn get_node(&self, x: i32, y: i32) -> Option<NodeId> {
+        self.leaf_at(x, y)
+    }
+
+    /// Find the leaf covering the given coordinates, which is the fullscreen leaf if there is
+    /// one. Returns `None` if the point is outside of the tree.
+    pub fn leaf_at(&self, x: i32, y: i32) -> Option<NodeId> {
+        // nothing else is visible next to a fullscreen leaf
+        if let Some((f, _)) = self.fullscreen {
+            return self.size.is_inside(x, y).then_some(f);
+        }
+
+        self.descend(x, y).filter(|&id| self[id].leaf)
+    }
+
+    /// Find the deepest internal node covering the given coordinates in the layout, i.e. the
+    /// parent of the leaf there, even if another leaf is fullscreen. Returns `None` if the point
+    /// is outside of the tree or the tree has only one leaf.
+    pub fn deepest_container_at(&self, x: i32, y: i32) -> Option<NodeId> {
+        let id = self.descend(x, y)?;
+
+        if self[id].leaf {
+            self[id].parent
+        } else {
+            Some(id)
+        }
+    }
+
+    /// Follow the nodes covering the given coordinates in the layout from the root down as far
+    /// as possible. Returns `None` if the point is outside of the root.
+    fn descend(&self, x: i32, y: i32) -> Option<NodeId> {
+        let mut id = self.root?;
+        if !self.layout_rect(&self[id], id).is_inside(x, y) {
+            return None;
+        }
+
+        while let (Some(l), Some(r)) = (self[id].left, self[id].right) {
+            id = match [l, r]
+                .into_iter()
+                .find(|&c| self.layout_rect(&self[c], c).is_inside(x, y))
+            {
+                Some(c) => c,
+                None => break,
+            };
+        }
+
+        Some(id)
+    }
+
+    /// Try to move focus in the given `direction`. If there is nowhere to move, the focus stays
+    /// the same and `TreeError::NoNeighbor` is returned, or `TreeError::Empty` for an empty tree.
+    pub fn move_focus(&mut self, direction: MoveDirection) -> Result<(), TreeError> {
+        let node = self.neighbor_result(direction)?;
+        self.set_focus(node);
+        Ok(())
+    }
+
+    /// Get the data and size of the leaf which `move_focus` would focus in the given `direction`,
+    /// without moving the focus. Returns `None` if there is nowhere to move.
+    pub fn neighbor(&self, direction: MoveDirection) -> Option<(T, Rectangle)> {
+        let n = &self[self.neighbor_id(direction)?];
+        Some((n.data.clone()?, n.rect))
+    }
+
+    /// Try to swap the currently focused leaf with the leaf found in the given `direction`. The
+    /// data of the two leaves is exchanged and the focus follows the moved data. If there is
+    /// nowhere to move, nothing happens and the same error as from `move_focus` is returned.
+    pub fn swap_in_direction(&mut self, direction: MoveDirection) -> Result<(), TreeError> {
+        let node = self.neighbor_result(direction)?;
+        let focused = self.focused.ok_or(TreeError::Empty)?;
+
+        self.save_state();
+
+        // the metadata and the border belong to the data, so they move along
+        let f = self.node_mut(focused);
+        let (data, meta, border) = (f.data.take(), mem::take(&mut f.meta), f.border);
+        let n = self.node_mut(node);
+        let data = mem::replace(&mut n.data, data);
+        let meta = mem::replace(&mut n.meta, meta);
+        let border = mem::replace(&mut n.border, border);
+        let f = self.node_mut(focused);
+        f.data = data;
+        f.meta = meta;
+        f.border = border;
+        self.emit(TreeEvent::Swapped {
+            a: focused,
+            b: node,
+        });
+        self.set_focus(node);
+        Ok(())
+    }
+
+    /// Swap the two children of the focused node's parent, so that the focused node and it's
+    /// sibling exchange places. Both children keep their size.
+    ///
+    /// Returns `TreeError::NoParent` if the focused leaf is the only one.
+    pub fn rotate_focused(&mut self) -> Result<(), TreeError> {
+        let focused = self.focused.ok_or(TreeError::Empty)?;
+        let parent = self[focused].parent.ok_or(TreeError::NoParent)?;
+
+        self.save_state();
+
+        self.swap_children(parent);
+        let rect = self[parent].rect;
+        self.update(parent, rect);
+        Ok(())
+    }
+
+    /// Make the focused leaf bigger by swapping it with the sibling of it's parent, i.e. moving it
+    /// one level up towards the root. The sibling of the parent takes the old place of the
+    /// focused leaf.
+    ///
+    /// Returns `TreeError::NoParent` if the focused leaf has no grandparent.
+    pub fn promote_focused(&mut self) -> Result<(), TreeError> {
+        let focused = self.focused.ok_or(TreeError::Empty)?;
+        let f = &self[focused];
+        let (parent, right_child) = (f.parent.ok_or(TreeError::NoParent)?, f.right_child);
+        let p = &self[parent];
+        let grandparent = p.parent.ok_or(TreeError::NoParent)?;
+        let g = &self[grandparent];
+        let (uncle, uncle_right) = if p.right_child {
+            (g.left, false)
+        } else {
+            (g.right, true)
+        };
+        let uncle = uncle.expect("an internal node has two children");
+
+        self.save_state();
+
+        self.link(focused, Some(grandparent), uncle_right);
+        self.link(uncle, Some(parent), right_child);
+        let rect = self[grandparent].rect;
+        self.update(grandparent, rect);
+        Ok(())
+    }
+
+    /// Rebuild the container of the focused leaf, i.e. it's parent, into a grid of the leaves it
+    /// contains, with `cols` leaves in every row, or less in the last one. The rows and the leaves
+    /// in every row get the same share of the space and the leaves keep their order from left to
+    /// right, as well as their ids and metadata. A `cols` of zero counts as one.
+    ///
+    /// Returns `TreeError::NoParent` if the focused leaf is the only one, or `TreeError::TooSmall`
+    /// if any leaf would become smaller than the minimum size.
+    pub fn gridify_focused_parent(&mut self, cols: usize) -> Result<(), TreeError> {
+        let focused = self.focused.ok_or(TreeError::Empty)?;
+        let parent = self[focused].parent.ok_or(TreeError::NoParent)?;
+        let nodes = self.walk_from(parent);
+        let leaves: Vec<_> = nodes.iter().copied().filter(|&id| self[id].leaf).collect();
+        let rows: Vec<_> = leaves.chunks(cols.clamp(1, leaves.len())).collect();
+
+        let rect = self.layout_rect(&self[parent], parent);
+        let (min_w, min_h) = self.min_size;
+        let fits = self
+            .spread(rect, rows.len(), SplitDirection::Horizontal)
+            .into_iter()
+            .zip(&rows)
+            .flat_map(|(row, leaves)| self.spread(row, leaves.len(), SplitDirection::Vertical))
+            .all(|r| r.w >= min_w && r.h >= min_h);
+        if !fits {
+            return Err(TreeError::TooSmall);
+        }
+
+        self.save_state();
+
+        let (grandparent, right_child) = (self[parent].parent, self[parent].right_child);
+        for id in nodes {
+            if !self[id].leaf {
+                self.release(id);
+            }
+        }
+        let rects = self.spread(rect, rows.len(), SplitDirection::Horizontal);
+        let rows: Vec<_> = rows
+            .into_iter()
+            .zip(rects)
+            .map(|(row, rect)| self.join(row, rect, SplitDirection::Vertical))
+            .collect();
+        let top = self.join(&rows, rect, SplitDirection::Horizontal);
+        self.link(top, grandparent, right_child);
+        self.update(top, rect);
+        self.remember_focus(focused);
+        Ok(())
+    }
+
+    /// Flip the layout along the given axis, by swapping the children of every node split with
+    /// `split`. A `Vertical` split flips the layout left to right, a `Horizontal` one top to
+    /// bottom. All nodes keep their size.
+    pub fn mirror(&mut self, split: SplitDirection) {
+        let root = match self.root {
+            Some(r) => r,
+            None => return,
+        };
+
+        self.save_state();
+
+        for id in self.walk() {
+            if !self[id].leaf && self[id].split == split {
+                self.swap_children(id);
+                self.mark_dirty(id);
+            }
+        }
+        self.lay_out(root, self.get_area(), false);
+    }
+
+    /// Move the edge of the focused node in the given `direction` by `amount` pixels, growing the
+    /// node if `amount` is positive and shrinking it if negative. The split ratio of the closest
+    /// ancestor whose split runs along that edge is adjusted and it's subtree is laid out again.
+    /// The ratio is clamped so that no leaf becomes smaller than the minimum size.
+    ///
+    /// Returns `TreeError::Empty` if the tree is empty, `TreeError::NoParent` if there is no such
+    /// ancestor, e.g. when the node touches the edge of the tree, or `TreeError::TooSmall` if the
+    /// leaves on both sides of the edge are already at the minimum size.
+    pub fn resize_focused(
+        &mut self,
+        direction: MoveDirection,
+        amount: i32,
+    ) -> Result<(), TreeError> {
+        let mut node = self.focused.ok_or(TreeError::Empty)?;
+
+        let split = SplitDirection::along(direction);
+        // the focused node has to be the first child to move it's right or bottom edge
+        let second = matches!(direction, MoveDirection::Left | MoveDirection::Up);
+
+        let ancestor = loop {
+            let parent = self[node].parent.ok_or(TreeError::NoParent)?;
+
+            if self[parent].split == split && self[node].right_child == second {
+                break parent;
+            }
+            node = parent;
+        };
+
+        let a = &self[ancestor];
+        let length = match split {
+            SplitDirection::Horizontal => a.rect.h,
+            SplitDirection::Vertical => a.rect.w,
+        };
+        if length == 0 {
+            return Err(TreeError::TooSmall);
+        }
+
+        let (left, right) = a
+            .left
+            .zip(a.right)
+            .expect("an internal node has two children");
+        let min_ratio = MIN_RATIO.max(self.min_length(left, split) as f32 / length as f32);
+        let max_ratio = MAX_RATIO.min(1.0 - self.min_length(right, split) as f32 / length as f32);
+        if min_ratio > max_ratio {
+            return Err(TreeError::TooSmall);
+        }
+
+        let a = &self[ancestor];
+        let delta = amount as f32 / length as f32;
+        let delta = if second { -delta } else { delta };
+        let ratio = (a.ratio + delta).clamp(min_ratio, max_ratio);
+        let rect = a.rect;
+
+        self.save_state();
+
+        self.node_mut(ancestor).ratio = ratio;
+        self.update(ancestor, rect);
+        Ok(())
+    }
+
+    /// Try to focus a node on the given coordinates, if the coordinates are invalid, nothing
+    /// happens.
+    pub fn focus_coords(&mut self, x: i32, y: i32) {
+        if let Some(node) = self.leaf_at(x, y) {
+            self.set_focus(node);
+        }
+    }
+
+    /// Focus the `n`-th leaf, counting from zero in left-to-right order. If there are not enough
+    /// leaves, the focus stays the same.
+    pub fn focus_nth(&mut self, n: usize) {
+        let leaf = self.leaf_ids().nth(n);
+
+        if let Some(node) = leaf {
+            self.set_focus(node);
+        }
+    }
+
+    /// Focus the leaf after the focused one in left-to-right order, regardless of where it is.
+    /// The last leaf is followed by the first one.
+    pub fn focus_next(&mut self) {
+        self.cycle_focus(true);
+    }
+
+    /// Focus the leaf before the focused one in left-to-right order, regardless of where it is.
+    /// The first leaf is preceded by the last one.
+    pub fn focus_prev(&mut self) {
+        self.cycle_focus(false);
+    }
+
+    /// Get the size of the first leaf, from left to right, holding `data`.
+    pub fn find(&self, data: &T) -> Option<Rectangle>
+    where
+        T: PartialEq,
+    {
+        self.find_leaf(data).map(|id| self[id].rect)
+    }
+
+    /// Focus the first leaf, from left to right, holding `data`. If there is no such leaf, the
+    /// focus stays the same.
+    pub fn focus_data(&mut self, data: &T)
+    where
+        T: PartialEq,
+    {
+        if let Some(leaf) = self.find_leaf(data) {
+            self.set_focus(leaf);
+        }
+    }
+
+    /// Focus the previously focused leaf. Calling this again returns to the current leaf. If
+    /// there is no such leaf, the focus stays the same.
+    pub fn focus_last(&mut self) {
+        if let Some(last) = self.history.pop_front() {
+            self.set_focus(last);
+        }
+    }
+
+    /// Get the previously focused leaves, the most recent first. Removed leaves are dropped from
+    /// the history and at most `FOCUS_HISTORY_LEN` are remembered.
+    pub fn focus_history(&self) -> impl Iterator<Item = NodeId> + '_ {
+        self.history.iter().copied()
+    }
+
+    /// Iterate over the containers of the focused leaf, from it's parent up to the root, e.g. to
+    /// select or resize a whole group of leaves. The iterator is empty if the focused leaf is the
+    /// root or the tree is empty.
+    pub fn ancestors_of_focused(&self) -> impl Iterator<Item = (NodeId, NodeView<T>)> + '_ {
+        let parent = self.focused.and_then(|f| self[f].parent);
+        let ancestors = move || iter::successors(parent, |&id| self[id].parent);
+        let mut depth = ancestors().count();
+
+        ancestors().map(move |id| {
+            depth -= 1;
+            (id, NodeView::new(&self[id], depth, self.is_hidden(id)))
+        })
+    }
+
+    /// Make the focused leaf fill the whole tree, hiding all other nodes, or restore the layout
+    /// if there already is a fullscreen leaf. The layout is also restored when the focus moves to
+    /// another leaf, or when a leaf is inserted or removed.
+    ///
+    /// Returns `TreeError::Empty` if the tree is empty.
+    pub fn toggle_fullscreen(&mut self) -> Result<(), TreeError> {
+        if self.fullscreen.is_some() {
+            self.leave_fullscreen();
+            return Ok(());
+        }
+
+        let f = self.focused.ok_or(TreeError::Empty)?;
+        let size = self.size;
+        let rect = mem::replace(&mut self.node_mut(f).rect, size);
+        self.fullscreen = Some((f, rect));
+        self.emit(TreeEvent::Resized { id: f, rect: size });
+        Ok(())
+    }
+
+    /// Get the fullscreen leaf, if there is one.
+    pub fn get_fullscreen(&self) -> Option<NodeId> {
+        self.fullscreen.map(|(f, _)| f)
+    }
+
+    /// Take the focused leaf out of the layout without removing it from the tree, e.g. to minimize
+    /// a window. It's space is filled the same way as with `delete_focused`, but the leaf keeps
+    /// it's id, data and metadata until it's put back with `show`.
+    ///
+    /// Returns `TreeError::Empty` if the tree is empty.
+    pub fn hide_focused(&mut self) -> Result<(), TreeError> {
+        let f = self.focused.ok_or(TreeError::Empty)?;
+
+        self.save_state();
+        let rect = self.take_out(f);
+        self.hidden.push(f);
+        self.emit(TreeEvent::Deleted { id: f, rect });
+        self.close_gap_of(f, rect);
+        Ok(())
+    }
+
+    /// Put the hidden leaf holding `data` back into the layout, wherever `insert` would put a new
+    /// leaf, and focus it. If several hidden leaves hold `data`, the one hidden first is shown.
+    ///
+    /// Returns `TreeError::NotHidden` if no hidden leaf holds `data`, or `TreeError::TooSmall` if
+    /// there is no room for the leaf, in which case it stays hidden.
+    pub fn show(&mut self, data: &T) -> Result<(), TreeError>
+    where
+        T: PartialEq,
+    {
+        let i = self
+            .hidden
+            .iter()
+            .position(|&id| self[id].data.as_ref() == Some(data))
+            .ok_or(TreeError::NotHidden)?;
+        let id = self.hidden[i];
+
+        // the leaf is only taken off the list once it fits
+        self.insert_placed(
+            move |tree, rect, _| {
+                tree.hidden.remove(i);
+                tree.node_mut(id).rect = rect;
+                id
+            },
+            true,
+        )
+        .map(|_| ())
+    }
+
+    /// Get the leaves hidden with `hide_focused`, in the order in which they were hidden.
+    pub fn hidden_leaves(&self) -> impl Iterator<Item = NodeId> + '_ {
+        self.hidden.iter().copied()
+    }
+
+    /// Take the focused leaf out of the layout and let it float on top of the tree, keeping it's
+    /// current rectangle. It's space is filled the same way as with `delete_focused` and the focus
+    /// moves on, as floating leaves don't take part in the layout or the focus. The leaf is put on
+    /// top of the other floating leaves and can be moved with `set_floating_rect` until it's put
+    /// back into the layout with `tile`.
+    ///
+    /// Returns `TreeError::Empty` if the tree is empty.
+    pub fn float_focused(&mut self) -> Result<(), TreeError> {
+        let f = self.focused.ok_or(TreeError::Empty)?;
+
+        self.save_state();
+        let rect = self.take_out(f);
+        self.floating.push(f);
+        self.emit(TreeEvent::Deleted { id: f, rect });
+        self.close_gap_of(f, rect);
+        Ok(())
+    }
+
+    /// Put the floating leaf `id` back into the layout, wherever `insert` would put a new leaf,
+    /// and focus it.
+    ///
+    /// Returns `TreeError::NotFloating` if `id` is not floating, or `TreeError::TooSmall` if there
+    /// is no room for the leaf, in which case it keeps floating.
+    pub fn tile(&mut self, id: NodeId) -> Result<(), TreeError> {
+        let i = self.floating_index(id)?;
+
+        self.insert_placed(
+            move |tree, rect, _| {
+                tree.floating.remove(i);
+                tree.node_mut(id).rect = rect;
+                id
+            },
+            true,
+        )
+        .map(|_| ())
+    }
+
+    /// Move and resize the floating leaf `id` to `rect`, which doesn't have to be inside of the
+    /// tree.
+    ///
+    /// Returns `TreeError::NotFloating` if `id` is not floating.
+    pub fn set_floating_rect(&mut self, id: NodeId, rect: Rectangle) -> Result<(), TreeError> {
+        self.floating_index(id)?;
+
+        if mem::replace(&mut self.node_mut(id).rect, rect) != rect {
+            self.emit(TreeEvent::Resized { id, rect });
+        }
+        Ok(())
+    }
+
+    /// Move the floating leaf `id` by `dx` and `dy` pixels, keeping it's size.
+    ///
+    /// Returns `TreeError::NotFloating` if `id` is not floating.
+    pub fn move_floating(&mut self, id: NodeId, dx: i32, dy: i32) -> Result<(), TreeError> {
+        self.floating_index(id)?;
+
+        let rect = self[id].rect;
+        self.set_floating_rect(id, Rectangle::new(rect.x + dx, rect.y + dy, rect.w, rect.h))
+    }
+
+    /// Put the floating leaf `id` on top of all other floating leaves.
+    ///
+    /// Returns `TreeError::NotFloating` if `id` is not floating.
+    pub fn raise_floating(&mut self, id: NodeId) -> Result<(), TreeError> {
+        let i = self.floating_index(id)?;
+        self.floating.remove(i);
+        self.floating.push(id);
+        Ok(())
+    }
+
+    /// Put the floating leaf `id` below all other floating leaves.
+    ///
+    /// Returns `TreeError::NotFloating` if `id` is not floating.
+    pub fn lower_floating(&mut self, id: NodeId) -> Result<(), TreeError> {
+        let i = self.floating_index(id)?;
+        self.floating.remove(i);
+        self.floating.insert(0, id);
+        Ok(())
+    }
+
+    /// Get the floating leaves in stacking order, from the bottom to the top.
+    pub fn floating_leaves(&self) -> impl Iterator<Item = NodeId> + '_ {
+        self.floating.iter().copied()
+    }
+
+    /// Return true if `id` is a floating leaf.
+    pub fn is_floating(&self, id: NodeId) -> bool {
+        self.floating.contains(&id)
+    }
+
+    /// Return true if the node `id` is covered by a fullscreen leaf, or is a leaf hidden with
+    /// `hide_focused`.
+    pub fn is_hidden(&self, id: NodeId) -> bool {
+        self.hidden.contains(&id) || matches!(self.fullscreen, Some((f, _)) if f != id)
+    }
+
+    /// Attach `value` to the node `id` under `key`, returning the value previously stored there.
+    /// Metadata moves together with the data of a leaf when it is swapped with another one.
+    ///
+    /// Returns `TreeError::InvalidNode` if the node has been removed from the tree.
+    pub fn set_meta(
+        &mut self,
+        id: NodeId,
+        key: impl Into<String>,
+        value: impl Into<String>,
+    ) -> Result<Option<String>, TreeError> {
+        let node = self.try_node_mut(id)?;
+        Ok(node.meta.insert(key.into(), value.into()))
+    }
+
+    /// Get the metadata value stored under `key` on the node `id`, `None` if there is no such
+    /// value or no such node.
+    pub fn get_meta(&self, id: NodeId, key: &str) -> Option<&str> {
+        self.node(id)?.get_meta(key)
+    }
+
+    /// Remove the metadata value stored under `key` on the node `id` and return it.
+    ///
+    /// Returns `TreeError::InvalidNode` if the node has been removed from the tree.
+    pub fn remove_meta(&mut self, id: NodeId, key: &str) -> Result<Option<String>, TreeError> {
+        Ok(self.try_node_mut(id)?.meta.remove(key))
+    }
+
+    /// Set the `SplitDirection` of the currently focused Node.
+    ///
+    /// Returns `TreeError::Empty` if the tree is empty.
+    pub fn set_split(&mut self, split: SplitDirection) -> Result<(), TreeError> {
+        let f = self.focused.ok_or(TreeError::Empty)?;
+        self.node_mut(f).split = split;
+        Ok(())
+    }
+
+    /// Toggle the `SplitDirection` of the currently focused Node.
+    ///
+    /// Returns `TreeError::Empty` if the tree is empty.
+    pub fn toggle_split(&mut self) -> Result<(), TreeError> {
+        let f = self.focused.ok_or(TreeError::Empty)?;
+        let f = self.node_mut(f);
+
+        match f.split {
+            SplitDirection::Vertical => f.split = SplitDirection::Horizontal,
+            SplitDirection::Horizontal => f.split = SplitDirection::Vertical,
+        }
+        Ok(())
+    }
+
+    /// Revert the last operation which changed the shape of the tree or the data in it, restoring
+    /// the focus as it was before the operation. These are the insertions, including `show` and
+    /// `tile`, the removals, including `hide_focused`, `float_focused` and
+    /// `detach_focused_subtree`, as well as `attach`, `swap_in_direction`, `resize_focused`,
+    /// `rotate_focused`, `promote_focused`, `mirror`, `gridify_focused_parent`, `replace_data`,
+    /// `set_focused_data` and `map_data`. Moving the focus, toggling the split or fullscreen,
+    /// resizing the whole tree and changing metadata are not undone. Returns `false` if there is
+    /// nothing to undo.
+    pub fn undo(&mut self) -> bool {
+        match self.undo_stack.pop() {
+            Some(state) => {
+                let current = self.restore(state);
+                self.redo_stack.push(current);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Re-apply the last operation reverted by `undo`. Returns `false` if there is nothing to
+    /// redo.
+    pub fn redo(&mut self) -> bool {
+        match self.redo_stack.pop() {
+            Some(state) => {
+                let current = self.restore(state);
+                self.undo_stack.push(current);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Push a copy of the current tree onto the undo stack. Any redo history is discarded, since
+    /// it no longer follows from the new state.
+    fn save_state(&mut self) {
+        let copy = Snapshot {
+            nodes: self.nodes.clone(),
+            free: self.free.clone(),
+            root: self.root,
+            focused: self.focused,
+            fullscreen: self.fullscreen,
+            hidden: self.hidden.clone(),
+            floating: self.floating.clone(),
+        };
+        self.undo_stack.push(copy);
+        self.redo_stack.clear();
+    }
+
+    /// Replace the current tree with `state`, returning the replaced one. The restored tree is laid
+    /// out again in case it was saved with a different size. Leaves which don't exist in `state`
+    /// are dropped from the focus history.
+    fn restore(&mut self, state: Snapshot<T>) -> Snapshot<T> {
+        let before: Vec<_> = self.leaf_ids().map(|id| (id, self[id].rect)).collect();
+
+        let current = Snapshot {
+            nodes: mem::replace(&mut self.nodes, state.nodes),
+            free: mem::replace(&mut self.free, state.free),
+            root: mem::replace(&mut self.root, state.root),
+            focused: mem::replace(&mut self.focused, state.focused),
+            fullscreen: mem::replace(&mut self.fullscreen, state.fullscreen),
+            hidden: mem::replace(&mut self.hidden, state.hidden),
+            floating: mem::replace(&mut self.floating, state.floating),
+        };
+
+        // the changes are reported all at once below
+        let observers = mem::take(&mut self.observers);
+        self.relayout_dirty();
+        self.observers = observers;
+        self.emit_changes(&before, current.focused);
+
+        let (nodes, focused) = (&self.nodes, self.focused);
+        let (hidden, floating) = (&self.hidden, &self.floating);
+        self.history.retain(|&h| {
+            Some(h) != focused
+                && !hidden.contains(&h)
+                && !floating.contains(&h)
+                && matches!(nodes.get(h.0), Some(Some(n)) if n.leaf)
+        });
+
+        current
+    }
+
+    /// Report how the leaves changed compared to the leaves in `before` and the previously
+    /// `focused` leaf.
+    fn emit_changes(&mut self, before: &[(NodeId, Rectangle)], focused: Option<NodeId>) {
+        let after: Vec<_> = self.leaf_ids().map(|id| (id, self[id].rect)).collect();
+
+        for &(id, rect) in before {
+            if !after.iter().any(|&(a, _)| a == id) {
+                self.emit(TreeEvent::Deleted { id, rect });
+            }
+        }
+        for &(id, rect) in after.iter() {
+            match before.iter().find(|&&(b, _)| b == id) {
+                None => self.emit(TreeEvent::Inserted { id, rect }),
+                Some(&(_, old)) if old != rect => self.emit(TreeEvent::Resized { id, rect }),
+                Some(_) => {}
+            }
+        }
+        if let Some(id) = self.focused.filter(|&f| Some(f) != focused) {
+            self.emit(TreeEvent::FocusChanged { id });
+        }
+    }
+
+    /// Iterate over the ids of all leaves, from left to right.
+    fn leaf_ids(&self) -> impl Iterator<Item = NodeId> + '_ {
+        self.walk().into_iter().filter(|&id| self[id].leaf)
+    }
+
+    fn cycle_focus(&mut self, forward: bool) {
+        let focused = match self.focused {
+            Some(f) => f,
+            None => return,
+        };
+        let leaves: Vec<_> = self.leaf_ids().collect();
+        let i = leaves
+            .iter()
+            .position(|&id| id == focused)
+            .expect("the focused node is a leaf");
+
+        let next = if forward {
+            (i + 1) % leaves.len()
+        } else {
+            (i + leaves.len() - 1) % leaves.len()
+        };
+        self.set_focus(leaves[next]);
+    }
 
-/// Type alias for a reference counting pointer to a Node.
-type BSPTreeNode = Rc<RefCell<Node>>;
+    /// Like `neighbor_id`, telling why there is no neighbor.
+    fn neighbor_result(&self, direction: MoveDirection) -> Result<NodeId, TreeError> {
+        if self.focused.is_none() {
+            return Err(TreeError::Empty);
+        }
+        self.neighbor_id(direction).ok_or(TreeError::NoNeighbor)
+    }
 
-type NodeData = u32;
+    /// Find the leaf next to the focused one in the given `direction`.
+    fn neighbor_id(&self, direction: MoveDirection) -> Option<NodeId> {
+        let focused = self.focused?;
+        let rect = self[focused].rect;
+        let (x, y) = direction.apply_move(rect);
+
+        let (x, y) = match self.leaf_at(x, y).filter(|&node| node != focused) {
+            None if self.wrap_focus => {
+                let area = self.get_area();
+                match direction {
+                    MoveDirection::Left => (area.right() - 1, rect.y),
+                    MoveDirection::Right => (area.x, rect.y),
+                    MoveDirection::Up => (rect.x, area.bottom() - 1),
+                    MoveDirection::Down => (rect.x, area.y),
+                }
+            }
+            _ => (x, y),
+        };
+        let node = self.leaf_at(x, y).filter(|&node| node != focused)?;
 
-/// Defines the four ways in which you can move focus around in the BSPTree.
-#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub enum MoveDirection {
-    Left,
-    Right,
-    Up,
-    Down,
-}
+        Some(self.enter(node, focused, direction, (x, y)))
+    }
 
-impl MoveDirection {
-    pub fn apply_move(&self, rect: Rectangle) -> (i32, i32) {
-        match self {
-            Self::Left => (rect.x as i32 - 1, rect.y as i32),
-            Self::Right => (rect.x as i32 + rect.w as i32 + 1, rect.y as i32),
-            Self::Up => (rect.x as i32, rect.y as i32 - 1),
-            Self::Down => (rect.x as i32, rect.y as i32 + rect.h as i32 + 1),
+    /// Choose the leaf to focus when moving from `focused` in `direction` into the container
+    /// holding `node`, the leaf at the point `probe`. Where the container is split across the
+    /// direction of the move, the side which was focused last is chosen, otherwise the leaf at
+    /// the probe wins.
+    fn enter(
+        &self,
+        node: NodeId,
+        focused: NodeId,
+        direction: MoveDirection,
+        probe: (i32, i32),
+    ) -> NodeId {
+        if self.fullscreen.is_some() {
+            return node;
         }
-    }
-}
-/// The way in which a Node in the BSP Tree will be split.
-#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
-pub enum SplitDirection {
-    Horizontal,
-    Vertical,
-}
 
-impl SplitDirection {
-    pub fn split(&self, rect: Rectangle) -> (Rectangle, Rectangle) {
-        match self {
-            Self::Horizontal => (
-                Rectangle::new(rect.x, rect.y, rect.w, rect.h / 2),
-                Rectangle::new(rect.x, rect.y + rect.h / 2, rect.w, rect.h / 2),
-            ),
-            Self::Vertical => (
-                Rectangle::new(rect.x, rect.y, rect.w / 2, rect.h),
-                Rectangle::new(rect.x + rect.w / 2, rect.y, rect.w / 2, rect.h),
-            ),
+        // the largest container holding `node`, but not `focused`
+        let ancestors: Vec<_> = iter::successors(Some(focused), |&id| self[id].parent).collect();
+        let mut container = node;
+        while let Some(parent) = self[container].parent.filter(|p| !ancestors.contains(p)) {
+            container = parent;
         }
-    }
-}
 
-/// Structure representing a simple rectangle.
-/// The `x` and `y` fields represent the **top-left** corner of the rectangle.
-/// The `w` and `h` fields represent the width and height of the Rectangle starting from the top
-/// left point.
-#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash, PartialOrd, Ord)]
-pub struct Rectangle {
-    pub x: u32,
-    pub y: u32,
-    pub w: u32,
-    pub h: u32,
-}
+        let along = SplitDirection::along(direction);
+        let mut id = container;
+        while let (Some(l), Some(r)) = (self[id].left, self[id].right) {
+            let n = &self[id];
+            id = match n.last_focused {
+                Some(Side::First) if n.split != along => l,
+                Some(Side::Second) if n.split != along => r,
+                _ if self[r].rect.is_inside(probe.0, probe.1) => r,
+                _ => l,
+            };
+        }
 
-impl Rectangle {
-    pub fn new(x: u32, y: u32, w: u32, h: u32) -> Self {
-        Self { x, y, w, h }
+        id
     }
 
-    pub fn is_inside(&self, x_: i32, y_: i32) -> bool {
-        (x_ as u32 >= self.x && x_ as u32 <= self.x + self.w)
-            && (y_ as u32 >= self.y && y_ as u32 <= self.y + self.h)
+    /// Find the first leaf, from left to right, holding `data`.
+    fn find_leaf(&self, data: &T) -> Option<NodeId>
+    where
+        T: PartialEq,
+    {
+        self.walk()
+            .into_iter()
+            .find(|&id| self[id].data.as_ref() == Some(data))
     }
-}
 
-impl fmt::Display for Rectangle {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "({}, {}); ({}, {})", self.x, self.y, self.w, self.h)
+    /// Remove the leaf `id` and let it's sibling take the place of their parent. If the leaf was
+    /// focused, the focus moves to the node which fills it's space.
+    fn remove(&mut self, id: NodeId) -> Option<T> {
+        self.leave_fullscreen();
+        let (parent, rect) = (self[id].parent, self[id].rect);
+        let data = self.release_subtree(id);
+
+        self.close_gap(id, parent, rect);
+        data
     }
-}
 
-/// A Binary Space Partitioning Tree is a type of binary tree with with all nodes having either two
-/// or zero descendants. The BSP Tree can be used to divide a rectangle into chunks, each the
-/// half the size of the previous chunk.
-#[derive(Clone, Debug, PartialEq)]
-pub struct BSPTree {
-    root: Option<BSPTreeNode>,
-    focused: Option<BSPTreeNode>,
-    size: Rectangle,
-}
+    /// Let the sibling of the node `id`, which has been taken out of the tree, take the place of
+    /// their `parent`. If the focused leaf was taken out, the focus moves to the node which fills
+    /// `rect`, the old space of `id`.
+    fn close_gap(&mut self, id: NodeId, parent: Option<NodeId>, rect: Rectangle) {
+        // taking out the root leaves the tree empty
+        let parent = match parent {
+            Some(p) => p,
+            None => {
+                // hidden and floating leaves keep their slots
+                if self.detached().next().is_none() {
+                    self.nodes.clear();
+                    self.free.clear();
+                }
+                self.root = None;
+                self.focused = None;
+                self.history.clear();
+                return;
+            }
+        };
 
-impl BSPTree {
-    /// Create a new BSPTree with a given `size`.
-    pub fn new(size: Rectangle) -> Self {
-        Self {
-            root: None,
-            focused: None,
-            size,
+        let p = &self[parent];
+        let sibling = if p.left == Some(id) { p.right } else { p.left }
+            .expect("an internal node has two children");
+        let (grandparent, right_child, prect) = (p.parent, p.right_child, p.rect);
+
+        self.release(parent);
+        self.link(sibling, grandparent, right_child);
+        self.update(sibling, prect);
+
+        if self
+            .focused
+            .is_some_and(|f| self.node(f).is_none() || self.is_detached(f))
+        {
+            self.focused = None;
+            self.focus_coords(rect.x, rect.y);
+            if self.focused.is_none() {
+                self.focus_nth(0);
+            }
         }
     }
 
-    /// Insert a new node as into the tree at the currently focused node.
-    pub fn insert(&mut self, data: NodeData) {
-        if self.root.is_none() {
-            let mut n = Node::new(self.size, SplitDirection::Vertical, data);
-            n.focused = true;
-            let node = Rc::new(RefCell::new(n));
+    /// Unlink the leaf `id` from the layout, keeping it in the arena, and forget it in the focus
+    /// history. It has to be put on the list of hidden or floating leaves and the gap has to be
+    /// closed with `close_gap_of` afterwards. Returns the space the leaf used to fill.
+    fn take_out(&mut self, id: NodeId) -> Rectangle {
+        self.leave_fullscreen();
+
+        let n = self.node_mut(id);
+        n.focused = false;
+        n.right_child = false;
+        self.history.retain(|&h| h != id);
+        self.node_mut(id).rect
+    }
 
-            self.root = Some(node.clone());
-            self.focused = Some(node.clone());
-            return;
-        }
+    /// Close the gap left by the leaf `id` which has been taken out with `take_out`.
+    fn close_gap_of(&mut self, id: NodeId, rect: Rectangle) {
+        let parent = self.node_mut(id).parent.take();
+        self.close_gap(id, parent, rect);
+    }
 
-        let tmp = self.focused.as_mut().unwrap().clone();
-        let mut focused = tmp.borrow_mut();
+    /// Remove the hidden or floating leaf `id`, which is not linked to any other node.
+    fn remove_detached(&mut self, id: NodeId) -> Option<T> {
+        self.hidden.retain(|&h| h != id);
+        self.floating.retain(|&f| f != id);
 
-        let (lsize, rsize) = focused.split.split(focused.rect);
-        focused.leaf = false;
-        focused.focused = false;
+        // the leaf was reported as deleted when it left the layout
+        let observers = mem::take(&mut self.observers);
+        let data = self.release_subtree(id);
+        self.observers = observers;
 
-        let prev_data = focused.data.unwrap();
-        focused.data = None;
+        // like in `close_gap`, the arena is only reset once no leaf is left
+        if self.root.is_none() && self.detached().next().is_none() {
+            self.nodes.clear();
+            self.free.clear();
+        }
+        data
+    }
 
-        let mut left = Node::new(lsize, focused.split, prev_data);
-        let mut right = Node::new(rsize, focused.split, data);
+    /// Iterate over the hidden and floating leaves, which are not part of the layout.
+    fn detached(&self) -> impl Iterator<Item = NodeId> + '_ {
+        self.hidden.iter().chain(self.floating.iter()).copied()
+    }
 
-        left.parent = Some(self.focused.as_ref().unwrap().clone());
-        right.parent = Some(self.focused.as_ref().unwrap().clone());
-        right.focused = true;
-        right.right_child = true;
+    fn is_detached(&self, id: NodeId) -> bool {
+        self.hidden.contains(&id) || self.floating.contains(&id)
+    }
 
-        let new_focused = Rc::new(RefCell::new(right));
+    fn floating_index(&self, id: NodeId) -> Result<usize, TreeError> {
+        self.floating
+            .iter()
+            .position(|&f| f == id)
+            .ok_or(TreeError::NotFloating { id })
+    }
 
-        focused.left = Some(Rc::new(RefCell::new(left)));
-        focused.right = Some(new_focused.clone());
+    /// Release the node `id` and all of it's descendants, forgetting the leaves in the focus
+    /// history. Returns the data of `id` itself.
+    fn release_subtree(&mut self, id: NodeId) -> Option<T> {
+        let mut data = None;
+        let mut stack = vec![id];
+
+        while let Some(n) = stack.pop() {
+            let node = self.release(n);
+            stack.extend(node.left);
+            stack.extend(node.right);
+            if node.leaf {
+                self.history.retain(|&h| h != n);
+                self.emit(TreeEvent::Deleted {
+                    id: n,
+                    rect: node.rect,
+                });
+            }
+            if n == id {
+                data = node.data;
+            }
+        }
 
-        drop(focused);
+        data
+    }
 
-        self.focused = Some(new_focused);
+    /// Find the focused leaf by its `focused` flag.
+    fn find_focused(&self) -> Option<NodeId> {
+        self.walk().into_iter().find(|&id| self[id].focused)
     }
 
-    /// Delete the currently focused node.
-    /// Focus is set to the node which fills the space of the deleted node.
-    pub fn delete_focused(&mut self) {
-        if self.focused.is_none() {
+    /// Move the focus to the leaf `id`, remembering the previously focused leaf.
+    fn set_focus(&mut self, id: NodeId) {
+        if self.focused == Some(id) {
             return;
         }
 
-        // If we try to remove the root node, we just return.
-        if self.focused.as_ref().unwrap().borrow().parent.is_none() {
-            self.root = None;
-            self.focused = None;
-            return;
+        self.leave_fullscreen();
+        self.history.retain(|&h| h != id);
+        if let Some(f) = self.focused {
+            self.node_mut(f).focused = false;
+            self.history.push_front(f);
+            self.history.truncate(FOCUS_HISTORY_LEN);
         }
+        self.node_mut(id).focused = true;
+        self.focused = Some(id);
+        self.remember_focus(id);
+        self.emit(TreeEvent::FocusChanged { id });
+    }
 
-        let focused = self.focused.as_ref().unwrap().clone();
-        let rect = focused.borrow().rect;
-
-        if let Some(parent) = focused.borrow().parent.clone() {
-            let sibling = if focused.borrow().right_child {
-                parent.borrow().left.as_ref().unwrap().clone()
+    /// Let every ancestor of the leaf `id` remember on which side the leaf is.
+    fn remember_focus(&mut self, id: NodeId) {
+        let mut child = id;
+        while let Some(parent) = self[child].parent {
+            let side = if self[child].right_child {
+                Side::Second
             } else {
-                parent.borrow().right.as_ref().unwrap().clone()
+                Side::First
             };
+            self.node_mut(parent).last_focused = Some(side);
+            child = parent;
+        }
+    }
 
-            // set the correct parameters on the `sibling`
-            {
-                let mut s = sibling.borrow_mut();
-                s.parent = parent.borrow().parent.clone();
-                s.rect = parent.borrow().rect;
-                s.right_child = parent.borrow().right_child;
-
-                // set the correct child of the parent of the parent.
-                if let Some(par) = s.parent.as_ref() {
-                    if s.right_child {
-                        par.borrow_mut().right = Some(sibling.clone());
-                    } else {
-                        par.borrow_mut().left = Some(sibling.clone());
+    /// Mark the node `id` and it's ancestors as dirty, so that `relayout_dirty` visits them.
+    fn mark_dirty(&mut self, id: NodeId) {
+        let mut next = Some(id);
+
+        while let Some(id) = next {
+            let n = self.node_mut(id);
+            if n.dirty {
+                break;
+            }
+            n.dirty = true;
+            next = n.parent;
+        }
+    }
+
+    /// Pass `event` to all registered callbacks.
+    fn emit(&mut self, event: TreeEvent) {
+        for observer in self.observers.0.iter_mut() {
+            observer(&event);
+        }
+    }
+
+    /// Put the fullscreen leaf back into it's place in the layout.
+    fn leave_fullscreen(&mut self) {
+        if let Some((f, rect)) = self.fullscreen.take() {
+            self.node_mut(f).rect = rect;
+            self.emit(TreeEvent::Resized { id: f, rect });
+        }
+    }
+
+    fn node_mut(&mut self, id: NodeId) -> &mut Node<T> {
+        self.nodes[id.0]
+            .as_mut()
+            .expect("the node has been removed from the tree")
+    }
+
+    fn try_node_mut(&mut self, id: NodeId) -> Result<&mut Node<T>, TreeError> {
+        self.nodes
+            .get_mut(id.0)
+            .and_then(Option::as_mut)
+            .ok_or(TreeError::InvalidNode { id })
+    }
+
+    /// Store `node` in a free slot of the arena.
+    fn alloc(&mut self, node: Node<T>) -> NodeId {
+        match self.free.pop() {
+            Some(id) => {
+                self.nodes[id.0] = Some(node);
+                id
+            }
+            None => {
+                self.nodes.push(Some(node));
+                NodeId(self.nodes.len() - 1)
+            }
+        }
+    }
+
+    /// Remove the node `id` from the arena, without touching the nodes pointing to it.
+    fn release(&mut self, id: NodeId) -> Node<T> {
+        let node = self.nodes[id.0]
+            .take()
+            .expect("the node has been removed from the tree");
+        self.free.push(id);
+        node
+    }
+
+    /// Move the leaf `leaf` down into a child of a new container covering `rect`, which is split
+    /// in `ratio`, so that the leaf keeps it's id. The other child is created by `make` from it's
+    /// rectangle and put on the given `side`. Returns the id of the other child.
+    fn graft(
+        &mut self,
+        leaf: NodeId,
+        rect: Rectangle,
+        split: SplitDirection,
+        ratio: f32,
+        side: Side,
+        make: impl FnOnce(&mut Self, Rectangle) -> NodeId,
+    ) -> NodeId {
+        let l = &self[leaf];
+        let (parent, right_child) = (l.parent, l.right_child);
+        let (lsize, rsize) = split.split_rounded(rect, ratio, self.rounding);
+        let (leaf_size, new_size, new_right) = match side {
+            Side::First => (rsize, lsize, false),
+            Side::Second => (lsize, rsize, true),
+        };
+
+        let container = self.alloc(Node::container(rect, split, ratio));
+        let new = make(self, new_size);
+        self.link(container, parent, right_child);
+        self.link(leaf, Some(container), !new_right);
+        self.link(new, Some(container), new_right);
+
+        self.node_mut(leaf).rect = leaf_size;
+        self.emit(TreeEvent::Resized {
+            id: leaf,
+            rect: leaf_size,
+        });
+        new
+    }
+
+    /// Whether all the leaves of `layout` would have at least the minimum size when laid out in
+    /// `rect`.
+    fn fits(&self, layout: &Layout<T>, rect: Rectangle) -> bool {
+        let mut stack = vec![(layout, rect)];
+
+        while let Some((layout, rect)) = stack.pop() {
+            match layout {
+                Layout::Leaf { .. } => {
+                    if rect.w < self.min_size.0 || rect.h < self.min_size.1 {
+                        return false;
                     }
                 }
+                Layout::Split {
+                    split,
+                    ratio,
+                    left,
+                    right,
+                } => {
+                    let (lrect, rrect) = split.split_rounded(rect, *ratio, self.rounding);
+                    stack.push((right, rrect));
+                    stack.push((left, lrect));
+                }
             }
+        }
 
-            // make the sibling the new parent
-            parent.replace(sibling.borrow().clone());
+        true
+    }
 
-            // update the size of the subtrees
-            let p = parent.borrow_mut();
-            let (ls, rs) = p.split.split(p.rect);
-            if let Some(l) = p.left.clone() {
-                l.borrow_mut().update(ls);
-            }
+    /// Make `child` the left or right child of `parent`, or the root if there is no parent.
+    fn link(&mut self, child: NodeId, parent: Option<NodeId>, right_child: bool) {
+        match parent {
+            Some(p) if right_child => self.node_mut(p).right = Some(child),
+            Some(p) => self.node_mut(p).left = Some(child),
+            None => self.root = Some(child),
+        }
+
+        let c = self.node_mut(child);
+        c.parent = parent;
+        c.right_child = right_child;
+    }
 
-            if let Some(r) = p.right.clone() {
-                r.borrow_mut().update(rs);
+    /// The smallest length the subtree starting at `id` can have along the axis divided by
+    /// `split`, without any of it's leaves becoming smaller than the minimum size.
+    fn min_length(&self, id: NodeId, split: SplitDirection) -> u32 {
+        let leaf = match split {
+            SplitDirection::Horizontal => self.min_size.1,
+            SplitDirection::Vertical => self.min_size.0,
+        };
+        // containers are visited again once the lengths of both children are known
+        let mut stack = vec![(id, false)];
+        let mut lengths: Vec<u32> = vec![];
+
+        while let Some((id, visited)) = stack.pop() {
+            let n = &self[id];
+            match (n.left, n.right) {
+                (Some(l), Some(r)) if !visited => {
+                    stack.push((id, true));
+                    stack.push((r, false));
+                    stack.push((l, false));
+                }
+                (Some(_), Some(_)) => {
+                    let right = lengths.pop().expect("the right child has a length");
+                    let left = lengths.pop().expect("the left child has a length");
+                    lengths.push(if n.split == split {
+                        left + right
+                    } else {
+                        left.max(right)
+                    });
+                }
+                _ => lengths.push(leaf),
             }
+        }
+
+        lengths.pop().expect("the subtree has a length")
+    }
+
+    /// Divide `rect` into `count` parts along `split`, the way `join` lays out `count` nodes.
+    fn spread(&self, rect: Rectangle, count: usize, split: SplitDirection) -> Vec<Rectangle> {
+        if count == 1 {
+            return vec![rect];
+        }
+
+        let first = count / 2;
+        let ratio = first as f32 / count as f32;
+        let (lrect, rrect) = split.split_rounded(rect, ratio, self.rounding);
+        let mut rects = self.spread(lrect, first, split);
+        rects.extend(self.spread(rrect, count - first, split));
+        rects
+    }
+
+    /// Join the nodes `ids` in a balanced subtree covering `rect`, in which every node is split
+    /// with `split` in proportion to the number of nodes on each side. Returns the id of the top
+    /// node, which is not linked to any parent. `ids` must not be empty.
+    fn join(&mut self, ids: &[NodeId], rect: Rectangle, split: SplitDirection) -> NodeId {
+        if let [id] = ids {
+            return *id;
+        }
+
+        let (first, second) = ids.split_at(ids.len() / 2);
+        let ratio = first.len() as f32 / ids.len() as f32;
+        let (lrect, rrect) = split.split_rounded(rect, ratio, self.rounding);
+        let id = self.alloc(Node::container(rect, split, ratio));
+        let left = self.join(first, lrect, split);
+        let right = self.join(second, rrect, split);
+        self.link(left, Some(id), false);
+        self.link(right, Some(id), true);
+        id
+    }
 
-            drop(p);
+    /// Swap the children of the node `id`, keeping the size of both. The rectangles need to be
+    /// updated afterwards.
+    fn swap_children(&mut self, id: NodeId) {
+        let rounding = self.rounding;
+        let n = self.node_mut(id);
+        mem::swap(&mut n.left, &mut n.right);
+
+        // `1.0 - ratio` can be rounded the other way, so the ratio is picked to give the first
+        // child exactly the length the second one had
+        let length = match n.split {
+            SplitDirection::Vertical => n.rect.w,
+            SplitDirection::Horizontal => n.rect.h,
         };
+        let (_, snd) = SplitDirection::divide(length, n.ratio, rounding);
+        n.ratio = SplitDirection::ratio_of(length, snd, rounding).unwrap_or(1.0 - n.ratio);
+        let (left, right) = (n.left, n.right);
 
-        self.focus_coords(rect.x as i32, rect.y as i32);
+        for (child, right_child) in [(left, false), (right, true)] {
+            if let Some(c) = child {
+                self.node_mut(c).right_child = right_child;
+            }
+        }
+    }
+
+    /// Update the size of the node `id` as well as it's children. A fullscreen leaf keeps filling
+    /// the tree, only the rectangle it returns to is updated.
+    fn update(&mut self, id: NodeId, rect: Rectangle) {
+        self.lay_out(id, rect, true);
     }
 
-    /// Find a node corresponding to the given coordinates.
-    pub fn get_node(&self, x: i32, y: i32) -> Option<BSPTreeNode> {
-        self.root.as_ref()?;
+    /// Lay out the node `id` in `rect` like `update`. Unless `all` is set, the subtrees which are
+    /// not dirty and keep their size are skipped, as they are already laid out.
+    fn lay_out(&mut self, id: NodeId, rect: Rectangle, all: bool) {
+        let (size, rounding) = (self.size, self.rounding);
+        let mut stack = vec![(id, rect)];
 
-        let mut node = self.root.clone();
-        let mut new_node;
-        while let Some(n) = node.as_ref() {
-            new_node = None;
-            if let Some(left) = n.borrow().left.as_ref() {
-                if left.borrow().rect.is_inside(x, y) {
-                    new_node = Some(left.clone());
+        while let Some((id, rect)) = stack.pop() {
+            if !all && !self[id].dirty && self.layout_rect(&self[id], id) == rect {
+                continue;
+            }
+
+            let n = self.nodes[id.0]
+                .as_mut()
+                .expect("the node has been removed from the tree");
+            let old = mem::replace(&mut n.rect, rect);
+            n.dirty = false;
+            let (lrect, rrect) = n.split.split_rounded(rect, n.ratio, rounding);
+            let (left, right) = (n.left, n.right);
+
+            if let Some((f, layout)) = self.fullscreen.as_mut() {
+                if *f == id {
+                    *layout = rect;
+                    n.rect = size;
                 }
             }
-            if let Some(right) = n.borrow().right.as_ref() {
-                if right.borrow().rect.is_inside(x, y) {
-                    new_node = Some(right.clone());
+
+            if n.leaf && n.rect != old {
+                let rect = n.rect;
+                self.emit(TreeEvent::Resized { id, rect });
+            }
+
+            // visit the left child first, like a recursive walk would
+            stack.extend(right.map(|r| (r, rrect)));
+            stack.extend(left.map(|l| (l, lrect)));
+        }
+    }
+
+    /// Add the nodes described by `layout` to the arena, filling the space of `rect`. Returns the
+    /// id of the top node, which is not linked to any parent. The layout is taken apart one level
+    /// at a time, so that neither building nor dropping it recurses, however deep it is.
+    fn build(&mut self, layout: Layout<T>, rect: Rectangle) -> NodeId {
+        let mut top = None;
+        // the layouts still to build, with the parent and side they are linked to
+        let mut stack = vec![(layout, rect, None, false)];
+
+        while let Some((layout, rect, parent, right_child)) = stack.pop() {
+            let id = match layout {
+                Layout::Leaf {
+                    data,
+                    split,
+                    focused,
+                } => {
+                    let mut node = Node::new(rect, split, data);
+                    node.focused = focused;
+                    self.alloc(node)
                 }
+                Layout::Split {
+                    split,
+                    ratio,
+                    left,
+                    right,
+                } => {
+                    let id = self.alloc(Node::container(rect, split, ratio));
+                    let (lrect, rrect) = split.split_rounded(rect, ratio, self.rounding);
+                    // the left side is built first, so the ids are handed out in pre-order
+                    stack.push((*right, rrect, Some(id), true));
+                    stack.push((*left, lrect, Some(id), false));
+                    id
+                }
+            };
+
+            match parent {
+                Some(_) => self.link(id, parent, right_child),
+                None => top = Some(id),
             }
+        }
 
-            if new_node.is_none() {
-                break;
+        top.expect("a layout has at least one node")
+    }
+
+    /// Describe the subtree starting at the node `id`. Like `build`, this doesn't recurse, so
+    /// that it works for trees of any depth.
+    fn layout_of(&self, id: NodeId) -> Layout<T> {
+        // containers are visited again once the layouts of both children are done
+        let mut stack = vec![(id, false)];
+        let mut layouts = vec![];
+
+        while let Some((id, visited)) = stack.pop() {
+            let n = &self[id];
+            match (n.left, n.right, n.data.as_ref()) {
+                (Some(l), Some(r), _) if !visited => {
+                    stack.push((id, true));
+                    stack.push((r, false));
+                    stack.push((l, false));
+                }
+                (Some(_), Some(_), _) => {
+                    let right = layouts.pop().expect("the right child has a layout");
+                    let left = layouts.pop().expect("the left child has a layout");
+                    layouts.push(Layout::Split {
+                        split: n.split,
+                        ratio: n.ratio,
+                        left: Box::new(left),
+                        right: Box::new(right),
+                    });
+                }
+                (_, _, Some(data)) => layouts.push(Layout::Leaf {
+                    data: data.clone(),
+                    split: n.split,
+                    focused: n.focused,
+                }),
+                _ => unreachable!("a node is either a leaf with data or has two children"),
             }
+        }
+
+        layouts.pop().expect("the subtree has a layout")
+    }
+
+    /// Iterate over all nodes of the tree in pre-order.
+    pub fn iter(&self) -> Iter<'_, T> {
+        self.iter_order(TraversalOrder::PreOrder)
+    }
+
+    /// Iterate over all nodes of the tree in the given `order`.
+    pub fn iter_order(&self, order: TraversalOrder) -> Iter<'_, T> {
+        Iter {
+            tree: self,
+            order,
+            stack: self.root.iter().map(|&r| (r, 0, false)).collect(),
+        }
+    }
+
+    /// Iterate over all nodes of the tree in pre-order, each node comes before it's children.
+    pub fn iter_preorder(&self) -> Iter<'_, T> {
+        self.iter_order(TraversalOrder::PreOrder)
+    }
+
+    /// Iterate over all nodes of the tree in in-order, each node comes between it's children.
+    pub fn iter_inorder(&self) -> Iter<'_, T> {
+        self.iter_order(TraversalOrder::InOrder)
+    }
+
+    /// Iterate over all nodes of the tree in post-order, each node comes after it's children.
+    pub fn iter_postorder(&self) -> Iter<'_, T> {
+        self.iter_order(TraversalOrder::PostOrder)
+    }
 
-            node = new_node.clone();
+    /// Iterate over all nodes of the tree in level-order, starting with the root and visiting
+    /// each level from left to right.
+    pub fn iter_bfs(&self) -> BfsIter<'_, T> {
+        BfsIter {
+            tree: self,
+            queue: self.root.iter().map(|&r| (r, 0)).collect(),
         }
+    }
 
-        if let Some(n) = node.clone() {
-            if !n.borrow().leaf {
-                return None;
+    /// Walk the whole tree with the `visitor`. This follows the parent links of the nodes, so it
+    /// doesn't allocate.
+    pub fn visit(&self, visitor: &mut impl TreeVisitor<T>) {
+        let mut id = match self.root {
+            Some(r) => r,
+            None => return,
+        };
+        let mut depth = 0;
+
+        loop {
+            // go down along the left children
+            let n = &self[id];
+            if let (false, Some(left)) = (n.leaf, n.left) {
+                visitor.enter_internal(id, n, depth);
+                id = left;
+                depth += 1;
+                continue;
+            }
+            visitor.visit_leaf(id, n, depth);
+
+            // go back up until coming from a left child, whose sibling is visited next
+            loop {
+                let n = &self[id];
+                let parent = match n.parent {
+                    Some(p) => p,
+                    None => return,
+                };
+                if !n.right_child {
+                    id = self[parent]
+                        .right
+                        .expect("an internal node has two children");
+                    break;
+                }
+                depth -= 1;
+                visitor.leave_internal(parent, &self[parent], depth);
+                id = parent;
             }
         }
+    }
 
-        node
+    /// Iterate over the data and size of all leaves, from left to right.
+    pub fn leaves(&self) -> impl Iterator<Item = (T, Rectangle)> + '_ {
+        self.iter()
+            .filter_map(|node| node.data.map(|data| (data, node.rect)))
     }
 
-    /// Try to move focus in the given `direction`. If there is nowhere to move, the focus stays
-    /// the same.
-    pub fn move_focus(&mut self, direction: MoveDirection) {
-        if self.root.is_none() {
+    /// Get the data, size and focus of every visible leaf, from left to right, followed by the
+    /// floating leaves from the bottom to the top, e.g. to draw a frame without holding on to the
+    /// tree. Leaves covered by a fullscreen leaf are left out, the gaps are not applied.
+    pub fn layout(&self) -> Vec<LeafGeometry<T>> {
+        self.leaf_ids()
+            .filter(|&id| !self.is_hidden(id))
+            .chain(self.floating_leaves())
+            .filter_map(|id| {
+                let n = &self[id];
+                Some(LeafGeometry {
+                    data: n.data.clone()?,
+                    rect: n.rect,
+                    focused: n.focused,
+                })
+            })
+            .collect()
+    }
+
+    /// Call `f` with the data and size of every leaf, from left to right. Unlike `leaves` this
+    /// doesn't clone the data.
+    pub fn for_each_leaf(&self, mut f: impl FnMut(&T, Rectangle)) {
+        for id in self.leaf_ids() {
+            let n = &self[id];
+            if let Some(data) = n.data.as_ref() {
+                f(data, n.rect);
+            }
+        }
+    }
+
+    /// Replace the data of every leaf, including the hidden and floating ones, with the result of
+    /// `f`. The leaves in the layout are passed from left to right, followed by the hidden and
+    /// the floating ones.
+    pub fn map_data(&mut self, mut f: impl FnMut(T) -> T) {
+        if self.root.is_none() && self.detached().next().is_none() {
             return;
         }
 
-        let (x, y) = direction.apply_move(self.focused.as_ref().unwrap().borrow().rect);
-        self.focus_coords(x, y);
+        self.save_state();
+
+        let ids: Vec<_> = self.leaf_ids().chain(self.detached()).collect();
+        for id in ids {
+            let n = self.node_mut(id);
+            n.data = n.data.take().map(&mut f);
+        }
     }
 
-    /// Try to focus a node on the given coordinates, if the coordinates are invalid, nothing
-    /// happens.
-    pub fn focus_coords(&mut self, x: i32, y: i32) {
-        if let Some(node) = self.get_node(x, y) {
-            self.focused.as_mut().unwrap().borrow_mut().focused = false;
-            node.borrow_mut().focused = true;
-            self.focused = Some(node);
+    /// Compare the trees by their shape, the splits and sizes of their nodes and the data of their
+    /// leaves only. Unlike `==` this ignores node ids, the focus and the undo history, so trees
+    /// which ended up with the same layout in different ways are equal.
+    pub fn structural_eq(&self, other: &Self) -> bool
+    where
+        T: PartialEq,
+    {
+        let (mut a, mut b) = (self.iter(), other.iter());
+        loop {
+            match (a.next_id(), b.next_id()) {
+                (None, None) => return true,
+                (Some((x, dx)), Some((y, dy))) => {
+                    let (x, y) = (&self[x], &other[y]);
+                    let same = dx == dy
+                        && x.leaf == y.leaf
+                        && x.split == y.split
+                        && x.rect == y.rect
+                        && x.data == y.data;
+                    if !same {
+                        return false;
+                    }
+                }
+                _ => return false,
+            }
         }
     }
 
-    /// Set the `SplitDirection` of the currently focused Node.
-    pub fn set_split(&self, split: SplitDirection) {
-        if let Some(f) = self.focused.as_ref() {
-            f.borrow_mut().split = split
+    /// Compare the leaves of this tree to the ones of `other`, e.g. an older and a newer version
+    /// of the same layout, so that only the leaves which changed have to be updated. Leaves are
+    /// matched by their data, leaves with equal data are matched in left-to-right order. Leaves
+    /// which were removed come first, followed by the resized and inserted leaves in the order of
+    /// `other`.
+    pub fn diff(&self, other: &Self) -> Vec<TreeChange<T>>
+    where
+        T: PartialEq,
+    {
+        let before: Vec<_> = self.leaves().collect();
+        let mut matched = vec![false; before.len()];
+        let mut changes = vec![];
+
+        for (data, rect) in other.leaves() {
+            let old = (0..before.len()).find(|&i| !matched[i] && before[i].0 == data);
+            match old {
+                Some(i) => {
+                    matched[i] = true;
+                    if before[i].1 != rect {
+                        changes.push(TreeChange::Resized {
+                            data,
+                            from: before[i].1,
+                            to: rect,
+                        });
+                    }
+                }
+                None => changes.push(TreeChange::Inserted { data, rect }),
+            }
         }
+
+        let removed = before
+            .into_iter()
+            .zip(matched)
+            .filter(|&(_, matched)| !matched)
+            .map(|((data, rect), _)| TreeChange::Removed { data, rect });
+        removed.chain(changes).collect()
     }
 
-    /// Toggle the `SplitDirection` of the currently focused Node.
-    pub fn toggle_split(&self) {
-        if let Some(f) = self.focused.as_ref() {
-            let split = f.borrow().split;
+    /// Get the ids of all nodes of the tree in pre-order.
+    pub fn walk(&self) -> Vec<NodeId> {
+        match self.root {
+            Some(r) => self.walk_from(r),
+            None => vec![],
+        }
+    }
 
-            match split {
-                SplitDirection::Vertical => f.borrow_mut().split = SplitDirection::Horizontal,
-                SplitDirection::Horizontal => f.borrow_mut().split = SplitDirection::Vertical,
-            }
+    /// Get the ids of all nodes of the subtree starting at `id` in pre-order.
+    fn walk_from(&self, id: NodeId) -> Vec<NodeId> {
+        let mut vec = vec![];
+        let mut stack = vec![id];
+
+        while let Some(id) = stack.pop() {
+            vec.push(id);
+            let n = &self[id];
+            stack.extend(n.right);
+            stack.extend(n.left);
         }
+        vec
     }
+}
 
+impl<T: Clone + fmt::Debug> BSPTree<T> {
     /// Print the BSP Tree.
     ///
     /// The `print_type` can be:
     /// - `0` - print in the `pre-order` order
     /// - `1` - print in the `in-order` order
     /// - `any other` - print in the `post-order` order
+    #[deprecated(note = "use `print_order` or `write_tree` with a `TraversalOrder` instead")]
     pub fn print(&self, print_type: i32) {
-        if let Some(r) = self.root.as_ref() {
-            match print_type {
-                0 => r.borrow().print_pre(0),
-                1 => r.borrow().print_in(0),
-                _ => r.borrow().print_post(0),
-            }
-        }
+        let order = match print_type {
+            0 => TraversalOrder::PreOrder,
+            1 => TraversalOrder::InOrder,
+            _ => TraversalOrder::PostOrder,
+        };
+        self.print_order(order);
     }
 
-    pub fn walk(&self) -> Vec<BSPTreeNode> {
-        let mut vec = vec![];
+    /// Print the BSP Tree to stdout, visiting the nodes in the given `order`.
+    pub fn print_order(&self, order: TraversalOrder) {
+        let mut out = String::new();
+        self.write_tree(&mut out, order)
+            .expect("writing to a String can't fail");
+        print!("{out}");
+    }
 
-        if let Some(r) = self.root.as_ref() {
-            vec.push(r.clone());
-            r.borrow().walk(&mut vec);
+    /// Write the BSP Tree to `out`, one node per line, visiting the nodes in the given `order`.
+    /// Each node is indented by four spaces per level of depth.
+    pub fn write_tree(&self, out: &mut impl fmt::Write, order: TraversalOrder) -> fmt::Result {
+        let mut nodes = self.iter_order(order);
+        while let Some((id, depth)) = nodes.next_id() {
+            writeln!(out, "{:indent$}{}", "", self[id], indent = depth * 4)?;
         }
-        vec
+        Ok(())
+    }
+
+    /// Write the structure of the tree as a Graphviz DOT graph, which can be turned into a picture
+    /// with e.g. `dot -Tpng`. Leaves are labeled with their data, other nodes with their split
+    /// and ratio, and the focused leaf is drawn in bold.
+    pub fn to_dot(&self, out: &mut impl io::Write) -> io::Result<()> {
+        writeln!(out, "digraph bsptree {{")?;
+        writeln!(out, "    node [shape=box];")?;
+
+        for id in self.walk() {
+            let n = &self[id];
+            let label = match n.data.as_ref() {
+                Some(data) => format!("{data:?}"),
+                None => format!("{:?} {}", n.split, n.ratio),
+            };
+            let style = if n.focused { ", style=bold" } else { "" };
+
+            writeln!(
+                out,
+                "    n{} [label=\"{}\\n{}\"{style}];",
+                id.0,
+                label.replace('\\', "\\\\").replace('"', "\\\""),
+                n.rect
+            )?;
+            for child in [n.left, n.right].into_iter().flatten() {
+                writeln!(out, "    n{} -> n{};", id.0, child.0)?;
+            }
+        }
+
+        writeln!(out, "}}")
     }
 }
 
-/// A Node in the BSP Tree has a reference to it's parent Node, and to it's two children nodes. It
-/// also has a `rect` field which has the size of the space it represents. The `metadata` field is
-/// used to store any arbitrary metadata for the Node. The `split` field indicates how the area
-/// should be split when adding children.
+/// A Node in the BSP Tree has the id of it's parent Node, and of it's two children nodes. It
+/// also has a `rect` field which has the size of the space it represents. The `split` field
+/// indicates how the area should be split when adding children.
 #[derive(Debug, Clone, PartialEq)]
-pub struct Node {
+pub struct Node<T = NodeData> {
     rect: Rectangle,
-    left: Option<BSPTreeNode>,
-    right: Option<BSPTreeNode>,
-    parent: Option<BSPTreeNode>,
+    left: Option<NodeId>,
+    right: Option<NodeId>,
+    parent: Option<NodeId>,
     split: SplitDirection,
+    ratio: f32,
     leaf: bool,
-    data: Option<NodeData>,
+    data: Option<T>,
     focused: bool,
     right_child: bool,
+    /// Arbitrary key-value pairs attached to the node, e.g. to mark it as sticky or urgent.
+    meta: BTreeMap<String, String>,
+    /// Whether the children of the node, or of one of it's descendants, have to be laid out
+    /// again. The ancestors of a dirty node are dirty as well.
+    dirty: bool,
+    /// The border width of the node, overriding the one of the tree.
+    border: Option<u32>,
+    /// The child of an internal node which contained the focused leaf most recently.
+    last_focused: Option<Side>,
 }
 
-impl std::fmt::Display for Node {
+impl<T: fmt::Debug> std::fmt::Display for Node<T> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
@@ -327,19 +3394,44 @@ impl std::fmt::Display for Node {
     }
 }
 
-impl Node {
+impl<T> Node<T> {
     /// Create a new node with Rectangle `rect`..
-    pub fn new(rect: Rectangle, split: SplitDirection, data: NodeData) -> Self {
+    pub fn new(rect: Rectangle, split: SplitDirection, data: T) -> Self {
         Self {
             rect,
             left: None,
             right: None,
             parent: None,
             split,
+            ratio: DEFAULT_RATIO,
             leaf: true,
             data: Some(data),
             focused: false,
             right_child: false,
+            meta: BTreeMap::new(),
+            dirty: false,
+            border: None,
+            last_focused: None,
+        }
+    }
+
+    /// Create an internal node, without data, which is split in `ratio` between it's children.
+    fn container(rect: Rectangle, split: SplitDirection, ratio: f32) -> Self {
+        Self {
+            rect,
+            left: None,
+            right: None,
+            parent: None,
+            split,
+            ratio,
+            leaf: false,
+            data: None,
+            focused: false,
+            right_child: false,
+            meta: BTreeMap::new(),
+            dirty: false,
+            border: None,
+            last_focused: None,
         }
     }
 
@@ -349,8 +3441,8 @@ impl Node {
     }
 
     /// Get the data stored in the node.
-    pub fn get_data(&self) -> Option<NodeData> {
-        self.data
+    pub fn get_data(&self) -> Option<&T> {
+        self.data.as_ref()
     }
 
     /// Get the size of the node.
@@ -358,73 +3450,49 @@ impl Node {
         self.rect
     }
 
-    /// Recursively walk both sides of the subtree starting from this node.
-    pub fn walk(&self, v: &mut Vec<BSPTreeNode>) {
-        if let Some(l) = self.left.clone() {
-            v.push(l.clone());
-            l.borrow().walk(v);
-        }
-        if let Some(r) = self.right.clone() {
-            v.push(r.clone());
-            r.borrow().walk(v);
-        }
+    /// Get the ratio in which the node is split between it's children.
+    pub fn get_ratio(&self) -> f32 {
+        self.ratio
     }
 
-    /// Update the size of the current node as well as it's children.
-    pub fn update(&mut self, rect: Rectangle) {
-        self.rect = rect;
-        let (lrect, rrect) = self.split.split(rect);
-
-        if let Some(l) = self.left.clone() {
-            l.borrow_mut().update(lrect);
-        }
-        if let Some(r) = self.right.clone() {
-            r.borrow_mut().update(rrect);
-        }
+    /// Return true if the node is a leaf, i.e. has data and no children.
+    pub fn is_leaf(&self) -> bool {
+        self.leaf
     }
 
-    fn print_pre(&self, indent: usize) {
-        println!(
-            "{}{self}",
-            " ".chars().cycle().take(indent).collect::<String>(),
-        );
-
-        if let Some(left) = self.left.clone() {
-            left.borrow().print_pre(indent + 4);
-        }
-
-        if let Some(right) = self.right.clone() {
-            right.borrow().print_pre(indent + 4)
-        }
+    /// Get the direction in which the node is split, or in which a leaf is split once it's split
+    /// further.
+    pub fn split(&self) -> SplitDirection {
+        self.split
     }
 
-    fn print_in(&self, indent: usize) {
-        if let Some(left) = self.left.clone() {
-            left.borrow().print_in(indent + 4);
-        }
+    /// Get the first child, the left or top one, or `None` for a leaf.
+    pub fn left(&self) -> Option<NodeId> {
+        self.left
+    }
 
-        println!(
-            "{}{self}",
-            " ".chars().cycle().take(indent).collect::<String>(),
-        );
+    /// Get the second child, the right or bottom one, or `None` for a leaf.
+    pub fn right(&self) -> Option<NodeId> {
+        self.right
+    }
 
-        if let Some(right) = self.right.clone() {
-            right.borrow().print_in(indent + 4)
-        }
+    /// Get the parent of the node, `None` for the root and for hidden or floating leaves.
+    pub fn parent(&self) -> Option<NodeId> {
+        self.parent
     }
 
-    fn print_post(&self, indent: usize) {
-        if let Some(left) = self.left.clone() {
-            left.borrow().print_post(indent + 4);
-        }
+    /// Get the border width of the node, `None` if it uses the border width of the tree.
+    pub fn get_border(&self) -> Option<u32> {
+        self.border
+    }
 
-        if let Some(right) = self.right.clone() {
-            right.borrow().print_post(indent + 4)
-        }
+    /// Get the metadata value stored under `key`.
+    pub fn get_meta(&self, key: &str) -> Option<&str> {
+        self.meta.get(key).map(String::as_str)
+    }
 
-        println!(
-            "{}{self}",
-            " ".chars().cycle().take(indent).collect::<String>(),
-        );
+    /// Iterate over all metadata of the node, ordered by key.
+    pub fn meta(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.meta.iter().map(|(k, v)| (k.as_str(), v.as_str()))
     }
 }
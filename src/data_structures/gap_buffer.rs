@@ -1,4 +1,19 @@
+use std::borrow::Cow;
 use std::boxed::Box;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, Read, Write};
+use std::iter;
+use std::mem;
+use std::ops::{Index, Range};
+use std::path::Path;
+use std::str::{self, Utf8Error};
+
+#[cfg(feature = "regex")]
+use regex::bytes::Regex;
+#[cfg(feature = "unicode-segmentation")]
+use unicode_segmentation::UnicodeSegmentation;
 
 #[derive(Debug, Clone)]
 /// GapBuffer is a data structure for efficient work with strings. It allows very fast insertions
@@ -11,88 +26,371 @@ pub struct GapBuffer {
     right: usize,
     pub capacity: usize,
     buffer: Box<[u8]>,
+    /// Groups of edits reverted by `undo`, the last one first.
+    undo_stack: Vec<Vec<Edit>>,
+    redo_stack: Vec<Vec<Edit>>,
+    /// The number of transactions begun and not yet committed.
+    transactions: usize,
+    /// The edits of the open transaction.
+    pending: Vec<Edit>,
+    growth: Growth,
+    /// Offsets of the secondary cursors, sorted and without duplicates.
+    cursors: Vec<usize>,
+    /// Content cut or copied into the kill ring, the most recent first.
+    kill_ring: VecDeque<(usize, Vec<u8>)>,
+    next_kill: usize,
+    registers: HashMap<String, Vec<u8>>,
+}
+
+/// Identifies a register of a `GapBuffer`, which holds content for `paste`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum RegisterId {
+    /// An entry of the kill ring, added by `cut_range` or `copy_range`. Only the last
+    /// `KILL_RING_LEN` entries are kept.
+    Kill(usize),
+    /// A register with a name chosen by the caller, e.g. `"a"`.
+    Named(String),
+}
+
+/// How much a `GapBuffer` grows when the gap runs out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Growth {
+    /// Grow the gap by a fixed number of bytes.
+    By(usize),
+    /// Double the capacity, so that inserting a lot of content takes only a few reallocations.
+    Double,
+}
+
+impl Default for Growth {
+    fn default() -> Self {
+        Growth::By(GROW_BY)
+    }
+}
+
+/// The kinds of characters which make up the words skipped by `move_word_left` and
+/// `move_word_right`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CharClass {
+    Word,
+    Space,
+    Punctuation,
+}
+
+impl CharClass {
+    fn of(c: char) -> Self {
+        if c.is_whitespace() {
+            CharClass::Space
+        } else if c.is_alphanumeric() || c == '_' {
+            CharClass::Word
+        } else {
+            CharClass::Punctuation
+        }
+    }
+}
+
+/// A change of the content, recorded for `undo` and `redo`.
+#[derive(Debug, Clone, PartialEq)]
+enum Edit {
+    /// `bytes` were inserted at offset `at`.
+    Insert { at: usize, bytes: Vec<u8> },
+    /// `bytes` were deleted from offset `at`.
+    Delete { at: usize, bytes: Vec<u8> },
 }
 
 pub const GROW_BY: usize = 32;
 
+/// The number of entries kept in the kill ring.
+pub const KILL_RING_LEN: usize = 16;
+
+/// The number of bytes by which `from_file` grows the buffer when the file is longer than expected.
+const READ_CHUNK: usize = 64 * 1024;
+
 impl Default for GapBuffer {
     fn default() -> Self {
-        let mut vec = Vec::with_capacity(GROW_BY);
-        vec.extend_from_slice(&[0; GROW_BY]);
-        Self {
-            left: 0,
-            right: GROW_BY - 1,
-            capacity: GROW_BY,
-            buffer: vec.into_boxed_slice(),
+        Self::new(GROW_BY)
+    }
+}
+
+/// Writes the content of the buffer. Invalid UTF-8 is replaced like with `to_string_lossy`.
+impl fmt::Display for GapBuffer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.as_str_slices() {
+            Ok((before, after)) => write!(f, "{before}{after}"),
+            Err(_) => f.write_str(&self.to_string_lossy()),
         }
     }
 }
 
-impl ToString for GapBuffer {
-    fn to_string(&self) -> String {
-        let mut out = Vec::with_capacity(self.capacity);
+/// Get the byte at a position of the content, counted without the gap.
+///
+/// Panics if the index is out of bounds.
+impl Index<usize> for GapBuffer {
+    type Output = u8;
 
-        out.extend(
-            self.buffer[0..self.left]
-                .iter()
-                .chain(&self.buffer[self.right + 1..]),
+    fn index(&self, index: usize) -> &u8 {
+        let len = self.len();
+        assert!(
+            index < len,
+            "index {index} is out of bounds of a GapBuffer of length {len}"
         );
 
-        String::from_utf8(out).expect("Unable to construct string from a GapBuffer.")
+        &self.buffer[self.physical(index)]
+    }
+}
+
+/// Buffers are equal if their content is, no matter where the gap is, how large it is or what
+/// their undo history is.
+impl PartialEq for GapBuffer {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.bytes().eq(other.bytes())
+    }
+}
+
+impl Eq for GapBuffer {}
+
+impl PartialEq<str> for GapBuffer {
+    fn eq(&self, other: &str) -> bool {
+        let (before, after) = self.as_slices();
+        let other = other.as_bytes();
+
+        other.len() == self.len()
+            && &other[..before.len()] == before
+            && &other[before.len()..] == after
+    }
+}
+
+impl PartialEq<&str> for GapBuffer {
+    fn eq(&self, other: &&str) -> bool {
+        self == *other
+    }
+}
+
+impl PartialEq<GapBuffer> for str {
+    fn eq(&self, other: &GapBuffer) -> bool {
+        other == self
+    }
+}
+
+impl PartialEq<GapBuffer> for &str {
+    fn eq(&self, other: &GapBuffer) -> bool {
+        other == *self
+    }
+}
+
+/// Takes over the bytes, adding a gap of `GROW_BY` bytes after them, so that the cursor is at the
+/// end.
+impl From<Vec<u8>> for GapBuffer {
+    fn from(mut bytes: Vec<u8>) -> Self {
+        let len = bytes.len();
+        bytes.resize(len + GROW_BY, 0);
+        Self::from_parts(bytes, len)
+    }
+}
+
+impl From<String> for GapBuffer {
+    fn from(str: String) -> Self {
+        Self::from(str.into_bytes())
+    }
+}
+
+impl From<&str> for GapBuffer {
+    fn from(str: &str) -> Self {
+        let mut bytes = Vec::with_capacity(str.len() + GROW_BY);
+        bytes.extend_from_slice(str.as_bytes());
+        Self::from(bytes)
+    }
+}
+
+impl FromIterator<char> for GapBuffer {
+    fn from_iter<I: IntoIterator<Item = char>>(iter: I) -> Self {
+        Self::from(iter.into_iter().collect::<String>())
+    }
+}
+
+/// Inserts the bytes at the cursor as one edit, like `insert`.
+impl Extend<u8> for GapBuffer {
+    fn extend<I: IntoIterator<Item = u8>>(&mut self, iter: I) {
+        let bytes: Vec<u8> = iter.into_iter().collect();
+        self.insert(&bytes)
+    }
+}
+
+/// Inserts the written bytes at the cursor, like `insert`, so that the cursor ends up after them.
+impl Write for GapBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.insert(buf);
+        Ok(buf.len())
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        self.insert(buf);
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
     }
 }
 
 impl GapBuffer {
+    /// Create an empty `GapBuffer` with a buffer of `capacity` bytes, at least one, all of which
+    /// are the gap.
     pub fn new(capacity: usize) -> Self {
+        Self::from_parts(vec![0; capacity.max(1)], 0)
+    }
+
+    /// Create a `GapBuffer` from a `buffer` with the first `len` bytes of content, everything
+    /// after which is the gap, so that the cursor is at the end. The gap must not be empty.
+    fn from_parts(buffer: Vec<u8>, len: usize) -> Self {
+        let capacity = buffer.len();
         Self {
-            left: 0,
+            left: len,
             right: capacity - 1,
             capacity,
-            buffer: Vec::with_capacity(capacity).into_boxed_slice(),
+            buffer: buffer.into_boxed_slice(),
+            undo_stack: vec![],
+            redo_stack: vec![],
+            transactions: 0,
+            pending: vec![],
+            growth: Growth::default(),
+            cursors: vec![],
+            kill_ring: VecDeque::new(),
+            next_kill: 0,
+            registers: HashMap::new(),
         }
     }
 
-    /// Grow the `GapBuffer` by `GROW_STEP` bytes.
-    fn grow(&mut self) {
-        let mut new_buff: Vec<u8> = vec![0u8; self.capacity + GROW_BY];
+    /// Create an empty `GapBuffer` which can hold `n` bytes of content without growing.
+    pub fn with_capacity(n: usize) -> Self {
+        Self::new(n + 1)
+    }
 
-        new_buff.extend_from_slice(&self.buffer[0..self.left]);
-        new_buff.splice(
-            self.right + GROW_BY..new_buff.capacity(),
-            self.buffer[self.right..].iter().copied(),
-        );
+    /// Read the file at `path` into a new `GapBuffer`, with the cursor at the start. The buffer is
+    /// allocated once from the size of the file, with a gap of `GROW_BY` bytes, and the file is
+    /// read straight into it. The loaded content is not part of the undo history.
+    pub fn from_file(path: impl AsRef<Path>) -> io::Result<Self> {
+        let mut file = File::open(path)?;
+        let size = file.metadata()?.len();
+        let mut gb = Self::with_capacity(usize::try_from(size).unwrap_or(0) + GROW_BY);
+
+        loop {
+            // the file may have grown since it's size was read
+            if gb.gap_len() == 1 {
+                gb.reserve(READ_CHUNK);
+            }
+
+            match file.read(&mut gb.buffer[gb.left..gb.right]) {
+                Ok(0) => break,
+                Ok(n) => gb.left += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        gb.seek(0);
+        Ok(gb)
+    }
+
+    /// Write the content to the file at `path`, creating or truncating it. Both halves of the
+    /// content are written as they are, without copying them.
+    pub fn write_to_file(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut file = File::create(path)?;
+        let (before, after) = self.as_slices();
+
+        file.write_all(before)?;
+        file.write_all(after)?;
+        file.flush()
+    }
+
+    /// Set how the buffer grows when the gap runs out.
+    pub fn set_growth(&mut self, growth: Growth) {
+        self.growth = growth;
+    }
+
+    /// Make room for inserting at least `additional` bytes at the cursor, reallocating the
+    /// buffer at most once. The gap grows according to the growth policy, or to just the needed
+    /// size if that is not enough.
+    pub fn reserve(&mut self, additional: usize) {
+        // one byte of the gap is always kept free
+        let needed = additional + 1;
+        if self.gap_len() < needed {
+            self.resize_gap((self.gap_len() + self.growth_step()).max(needed))
+        }
+    }
+
+    /// The number of bytes by which the gap grows.
+    fn growth_step(&self) -> usize {
+        match self.growth {
+            Growth::By(n) => n.max(1),
+            Growth::Double => self.capacity,
+        }
+    }
+
+    /// Reallocate the buffer to the length of the content and a gap of one byte, the least there
+    /// can be. The cursor stays where it is.
+    pub fn shrink_to_fit(&mut self) {
+        self.resize_gap(1)
+    }
 
-        self.right += GROW_BY;
-        self.capacity += GROW_BY;
+    /// Reallocate the buffer if the gap is larger than `max_gap` bytes, e.g. after a large
+    /// deletion, so that the gap is `max_gap` bytes long. The gap is always at least one byte.
+    pub fn compact(&mut self, max_gap: usize) {
+        let max_gap = max_gap.max(1);
+        if self.gap_len() > max_gap {
+            self.resize_gap(max_gap)
+        }
+    }
+
+    /// Move the content to a new buffer with a gap of `gap` bytes at the cursor.
+    fn resize_gap(&mut self, gap: usize) {
+        let capacity = self.len() + gap;
+        let mut new_buff: Vec<u8> = vec![0u8; capacity];
+
+        new_buff[0..self.left].copy_from_slice(&self.buffer[0..self.left]);
+        new_buff[self.left + gap..].copy_from_slice(&self.buffer[self.right + 1..]);
+
+        self.right = self.left + gap - 1;
+        self.capacity = capacity;
         self.buffer = new_buff.into_boxed_slice();
     }
 
     /// Insert one byte at the current cursor position.
     /// If the gap is empty, grow the buffer as needed.
     pub fn insert_byte(&mut self, c: u8) {
-        if self.left + 1 == self.right {
-            self.grow()
-        }
+        self.reserve(1);
 
         // insert char at the start of the gap
         self.buffer[self.left] = c;
         self.left += 1;
+        self.cursors_inserted(self.left - 1, 1);
+        self.record(Edit::Insert {
+            at: self.left - 1,
+            bytes: vec![c],
+        });
     }
 
-    /// Insert one char at the current cursor position.
-    /// If the gap is empty, grow the buffer as needed.
+    /// Insert one char at the current cursor position, encoded as UTF-8, so that it takes up to
+    /// four bytes. If the gap is too small, grow the buffer as needed.
     pub fn insert_char(&mut self, c: char) {
-        self.insert_byte(c as u8)
+        let mut bytes = [0; 4];
+        self.insert(c.encode_utf8(&mut bytes).as_bytes())
     }
 
     /// Insert a slice of bytes on the current cursor position.
     /// If the buffer or gap is too small, grow the buffer as needed.
     pub fn insert(&mut self, slice: &[u8]) {
+        self.put(slice);
+        self.record(Edit::Insert {
+            at: self.left - slice.len(),
+            bytes: slice.to_vec(),
+        });
+    }
+
+    /// Insert `slice` at the cursor without recording it for `undo`.
+    fn put(&mut self, slice: &[u8]) {
         // grow enough to accommodate the new slice
         let len = slice.len();
-        while len > self.right - self.left {
-            self.grow();
-        }
+        self.reserve(len);
 
         // insert the slice into the gap
         for (si, i) in (self.left..self.left + len).enumerate() {
@@ -100,6 +398,15 @@ impl GapBuffer {
         }
 
         self.left += len;
+        self.cursors_inserted(self.left - len, len);
+    }
+
+    /// Insert a slice of bytes `index` bytes from the start of the content, moving the cursor
+    /// there first, so that it ends up after the inserted bytes. An `index` past the end inserts
+    /// at the end.
+    pub fn insert_at(&mut self, index: usize, slice: &[u8]) {
+        self.seek(index);
+        self.insert(slice)
     }
 
     /// Insert a sting slice on the current cursor position.
@@ -114,9 +421,9 @@ impl GapBuffer {
         let new_left = if n > self.left { 0 } else { self.left - n };
         let new_right = self.right - (self.left - new_left);
 
-        for (l, r) in (new_left..self.left).zip(new_right + 1..) {
-            self.buffer.swap(l, r);
-        }
+        // the moved bytes can overlap with their old place when the gap is small
+        self.buffer.copy_within(new_left..self.left, new_right + 1);
+        self.buffer[new_left..self.left.min(new_right + 1)].fill(0);
 
         self.left = new_left;
         self.right = new_right;
@@ -132,9 +439,9 @@ impl GapBuffer {
         };
         let new_left = self.left + (new_right - self.right);
 
-        for (r, l) in (self.right + 1..=new_right).zip(self.left..) {
-            self.buffer.swap(r, l);
-        }
+        self.buffer
+            .copy_within(self.right + 1..new_right + 1, self.left);
+        self.buffer[(self.right + 1).max(new_left)..new_right + 1].fill(0);
 
         self.left = new_left;
         self.right = new_right;
@@ -142,19 +449,530 @@ impl GapBuffer {
 
     /// Delete `n` bytes from the GapBuffer. Does nothing if the buffer is empty. The memory is
     /// not actually deleted or freed, the gap simply grows larger with each byte deleted.
-    /// This funcion grows the buffer from the `left` side.
-    pub fn delete_left(&mut self, n: usize) {
-        self.left = if n > self.left { 0 } else { self.left - n }
+    /// This funcion grows the buffer from the `left` side. Returns the deleted bytes, e.g. to
+    /// undo the deletion or to cut the text.
+    pub fn delete_left(&mut self, n: usize) -> Vec<u8> {
+        let deleted = self.cut_left(n);
+        self.record(Edit::Delete {
+            at: self.left,
+            bytes: deleted.clone(),
+        });
+        deleted
+    }
+
+    /// Delete `n` bytes before the cursor without recording it for `undo`.
+    fn cut_left(&mut self, n: usize) -> Vec<u8> {
+        let new_left = self.left.saturating_sub(n);
+        let deleted = self.buffer[new_left..self.left].to_vec();
+
+        self.cursors_deleted(new_left..self.left);
+        self.left = new_left;
+        deleted
     }
 
     /// Delete `n` bytes from the GapBuffer. Does nothing if the buffer is empty. The memory is
     /// not actually deleted or freed, the gap simply grows larger with each byte deleted.
-    /// This funcion grows the buffer from the `right` side.
-    pub fn delete_right(&mut self, n: usize) {
-        self.right = if self.right + n > self.capacity - 1 {
+    /// This funcion grows the buffer from the `right` side. Returns the deleted bytes, like
+    /// `delete_left`.
+    pub fn delete_right(&mut self, n: usize) -> Vec<u8> {
+        let deleted = self.cut_right(n);
+        self.record(Edit::Delete {
+            at: self.left,
+            bytes: deleted.clone(),
+        });
+        deleted
+    }
+
+    /// Delete `n` bytes after the cursor without recording it for `undo`.
+    fn cut_right(&mut self, n: usize) -> Vec<u8> {
+        let new_right = if self.right + n > self.capacity - 1 {
             self.capacity - 1
         } else {
             self.right + n
+        };
+        let deleted = self.buffer[self.right + 1..=new_right].to_vec();
+
+        self.cursors_deleted(self.left..self.left + deleted.len());
+        self.right = new_right;
+        deleted
+    }
+
+    /// Delete the bytes in `range`, counted without the gap, leaving the cursor where they were.
+    /// The range is cut off at the end of the content. Returns the deleted bytes, like
+    /// `delete_left`.
+    pub fn delete_range(&mut self, range: Range<usize>) -> Vec<u8> {
+        let end = range.end.min(self.len());
+        let start = range.start.min(end);
+
+        self.seek(start);
+        self.delete_right(end - start)
+    }
+
+    /// Replace the bytes in `range` with `str`, like `delete_range` followed by `insert_str`, so
+    /// that the cursor ends up after the new text. Both are one step of `undo`. Returns the replaced
+    /// bytes.
+    pub fn replace_range(&mut self, range: Range<usize>, str: impl AsRef<str>) -> Vec<u8> {
+        self.begin_transaction();
+        let deleted = self.delete_range(range);
+        self.insert_str(str);
+        self.commit();
+        deleted
+    }
+
+    /// Return the offset of the end of the word after the cursor, skipping any whitespace before
+    /// it, or the end of the content if there is no word. A word is a run of letters, digits and
+    /// underscores, or of other non-whitespace characters. With the `unicode-segmentation` feature
+    /// the Unicode word boundaries are used instead, as long as the content is valid UTF-8.
+    pub fn next_word_boundary(&self) -> usize {
+        self.word_end(self.left)
+    }
+
+    /// Return the offset of the start of the word before the cursor, skipping any whitespace after
+    /// it, or zero if there is no word. Words are the same as for `next_word_boundary`.
+    pub fn prev_word_boundary(&self) -> usize {
+        self.word_start(self.left)
+    }
+
+    /// Move the cursor to the end of the `n`th word after it, e.g. for Ctrl+Right.
+    pub fn move_word_right(&mut self, n: usize) {
+        for _ in 0..n {
+            self.seek(self.next_word_boundary())
+        }
+    }
+
+    /// Move the cursor to the start of the `n`th word before it, e.g. for Ctrl+Left.
+    pub fn move_word_left(&mut self, n: usize) {
+        for _ in 0..n {
+            self.seek(self.prev_word_boundary())
+        }
+    }
+
+    /// Find the end of the word after `from`.
+    fn word_end(&self, mut from: usize) -> usize {
+        #[cfg(feature = "unicode-segmentation")]
+        if let Ok(content) = str::from_utf8(&self.slice(0..self.len())) {
+            return content
+                .split_word_bound_indices()
+                .map(|(start, word)| (start + word.len(), word))
+                .find(|&(end, word)| end > from && !word.trim().is_empty())
+                .map_or(content.len(), |(end, _)| end);
+        }
+
+        while let Some((_, len)) = self.decode(from).filter(|&(c, _)| c.is_whitespace()) {
+            from += len;
+        }
+        if let Some((c, _)) = self.decode(from) {
+            let class = CharClass::of(c);
+            while let Some((_, len)) = self
+                .decode(from)
+                .filter(|&(c, _)| CharClass::of(c) == class)
+            {
+                from += len;
+            }
+        }
+
+        from
+    }
+
+    /// Find the start of the word before `from`.
+    fn word_start(&self, mut from: usize) -> usize {
+        #[cfg(feature = "unicode-segmentation")]
+        if let Ok(content) = str::from_utf8(&self.slice(0..self.len())) {
+            return content
+                .split_word_bound_indices()
+                .rev()
+                .find(|&(start, word)| start < from && !word.trim().is_empty())
+                .map_or(0, |(start, _)| start);
+        }
+
+        while let Some((_, len)) = self.decode_before(from).filter(|&(c, _)| c.is_whitespace()) {
+            from -= len;
+        }
+        if let Some((c, _)) = self.decode_before(from) {
+            let class = CharClass::of(c);
+            while let Some((_, len)) = self
+                .decode_before(from)
+                .filter(|&(c, _)| CharClass::of(c) == class)
+            {
+                from -= len;
+            }
+        }
+
+        from
+    }
+
+    /// Decode the character starting at `offset`, returning it together with the number of bytes
+    /// it takes up. Invalid UTF-8 is decoded like by `chars`.
+    fn decode(&self, offset: usize) -> Option<(char, usize)> {
+        let (before, after) = self.as_slices();
+        let mut chars = Chars {
+            before: &before[offset.min(before.len())..],
+            after: &after[offset.saturating_sub(before.len()).min(after.len())..],
+        };
+
+        let left = chars.before.len() + chars.after.len();
+        let c = chars.next()?;
+        Some((c, left - chars.before.len() - chars.after.len()))
+    }
+
+    /// Decode the character ending at `offset`, like `decode`.
+    fn decode_before(&self, offset: usize) -> Option<(char, usize)> {
+        let offset = offset.min(self.len());
+        if offset == 0 {
+            return None;
+        }
+
+        // find the start of the character, which is at most four bytes long
+        let mut start = offset - 1;
+        while start > 0 && offset - start < 4 && self[start] & 0xc0 == 0x80 {
+            start -= 1;
+        }
+
+        match self.decode(start) {
+            Some((c, len)) if start + len == offset => Some((c, len)),
+            _ => Some((char::REPLACEMENT_CHARACTER, 1)),
+        }
+    }
+
+    /// Add a secondary cursor at `offset`, or at the end if `offset` is past it. The secondary
+    /// cursors keep their place in the content as it is edited, and cursors which end up at the
+    /// same offset, e.g. after a deletion, are merged.
+    pub fn add_cursor(&mut self, offset: usize) {
+        let offset = offset.min(self.len());
+        if let Err(i) = self.cursors.binary_search(&offset) {
+            self.cursors.insert(i, offset);
+        }
+    }
+
+    /// Remove the secondary cursor at `offset`. Returns false if there is none.
+    pub fn remove_cursor(&mut self, offset: usize) -> bool {
+        match self.cursors.binary_search(&offset) {
+            Ok(i) => {
+                self.cursors.remove(i);
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    /// Remove all the secondary cursors.
+    pub fn clear_cursors(&mut self) {
+        self.cursors.clear()
+    }
+
+    /// Return the offsets of the secondary cursors, in ascending order. The cursor at the gap is
+    /// not one of them.
+    pub fn cursors(&self) -> &[usize] {
+        &self.cursors
+    }
+
+    /// Insert `str` at the cursor and at every secondary cursor, as one step of `undo`. All the
+    /// cursors end up after the text inserted at them.
+    pub fn insert_at_all_cursors(&mut self, str: impl AsRef<str>) {
+        let str = str.as_ref();
+        let main = self.left;
+        let mut offsets = self.cursors.clone();
+        if let Err(i) = offsets.binary_search(&main) {
+            offsets.insert(i, main);
+        }
+
+        // from the back, so that the offsets of the earlier cursors stay valid
+        self.begin_transaction();
+        for &offset in offsets.iter().rev() {
+            self.seek(offset);
+            self.insert_str(str);
+        }
+        self.commit();
+
+        let before = offsets.iter().filter(|&&offset| offset <= main).count();
+        self.seek(main + before * str.len());
+    }
+
+    /// Shift the secondary cursors at or after `at` by `len` inserted bytes.
+    fn cursors_inserted(&mut self, at: usize, len: usize) {
+        for cursor in self.cursors.iter_mut().filter(|c| **c >= at) {
+            *cursor += len;
+        }
+    }
+
+    /// Move the secondary cursors in the deleted `range` to it's start and shift the ones after
+    /// it back.
+    fn cursors_deleted(&mut self, range: Range<usize>) {
+        for cursor in &mut self.cursors {
+            if *cursor >= range.end {
+                *cursor -= range.len();
+            } else if *cursor > range.start {
+                *cursor = range.start;
+            }
+        }
+        self.cursors.dedup();
+    }
+
+    /// Revert the last edit, or all the edits of the last transaction, moving the cursor to where
+    /// the edit was made. An open transaction is committed first. Returns `false` if there is
+    /// nothing to undo.
+    pub fn undo(&mut self) -> bool {
+        self.close_transactions();
+        match self.undo_stack.pop() {
+            Some(edits) => {
+                for edit in edits.iter().rev() {
+                    match edit {
+                        Edit::Insert { at, bytes } => {
+                            self.seek(*at);
+                            self.cut_right(bytes.len());
+                        }
+                        Edit::Delete { at, bytes } => {
+                            self.seek(*at);
+                            self.put(bytes);
+                        }
+                    }
+                }
+                self.redo_stack.push(edits);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Re-apply the last edit or transaction reverted by `undo`, moving the cursor to the end of
+    /// it. Returns `false` if there is nothing to redo.
+    pub fn redo(&mut self) -> bool {
+        self.close_transactions();
+        match self.redo_stack.pop() {
+            Some(edits) => {
+                for edit in &edits {
+                    match edit {
+                        Edit::Insert { at, bytes } => {
+                            self.seek(*at);
+                            self.put(bytes);
+                        }
+                        Edit::Delete { at, bytes } => {
+                            self.seek(*at);
+                            self.cut_right(bytes.len());
+                        }
+                    }
+                }
+                self.undo_stack.push(edits);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Start grouping the following edits into one step of `undo`, until `commit` is called.
+    /// Transactions can be nested, in which case the outermost one makes up the step.
+    pub fn begin_transaction(&mut self) {
+        self.transactions += 1;
+    }
+
+    /// Close the transaction opened by the last `begin_transaction`. Does nothing if there is no
+    /// open transaction.
+    pub fn commit(&mut self) {
+        match self.transactions {
+            0 => {}
+            1 => self.close_transactions(),
+            _ => self.transactions -= 1,
+        }
+    }
+
+    /// Record `edit` for `undo`. Any redo history is discarded, since it no longer follows from
+    /// the new content.
+    fn record(&mut self, edit: Edit) {
+        let (Edit::Insert { bytes, .. } | Edit::Delete { bytes, .. }) = &edit;
+        if bytes.is_empty() {
+            return;
+        }
+
+        self.redo_stack.clear();
+        if self.transactions > 0 {
+            self.pending.push(edit);
+        } else {
+            self.undo_stack.push(vec![edit]);
+        }
+    }
+
+    /// Close all the open transactions, turning their edits into one step of `undo`.
+    fn close_transactions(&mut self) {
+        self.transactions = 0;
+        if !self.pending.is_empty() {
+            let edits = mem::take(&mut self.pending);
+            self.undo_stack.push(edits);
+        }
+    }
+
+    /// Delete all the content, keeping the buffer allocated. Like other deletions this can be
+    /// reverted with `undo`.
+    pub fn clear(&mut self) {
+        self.truncate(0)
+    }
+
+    /// Delete the content after the first `len` bytes. The cursor stays where it is, unless it
+    /// was past `len`, in which case it's moved to the end. Does nothing if the content is not
+    /// longer than `len`.
+    pub fn truncate(&mut self, len: usize) {
+        self.cut_tail(len);
+    }
+
+    /// Split the content at `at`, moving everything after it into a new `GapBuffer` with the
+    /// cursor at the end, like `truncate` does for `self`. Returns an empty buffer if `at` is
+    /// past the end of the content.
+    pub fn split_off(&mut self, at: usize) -> GapBuffer {
+        GapBuffer::from(self.cut_tail(at))
+    }
+
+    /// Delete and return the content after the first `len` bytes, for `truncate` and `split_off`.
+    fn cut_tail(&mut self, len: usize) -> Vec<u8> {
+        let cursor = self.left;
+        let tail = self.delete_range(len..self.len());
+
+        if cursor < len {
+            self.seek(cursor);
+        }
+        tail
+    }
+
+    /// Delete the bytes in `range`, like `delete_range`, and add them to the kill ring. Returns the
+    /// register holding them.
+    pub fn cut_range(&mut self, range: Range<usize>) -> RegisterId {
+        let cut = self.delete_range(range);
+        self.kill(cut)
+    }
+
+    /// Add the bytes in `range` to the kill ring, without deleting them. Returns the register
+    /// holding them.
+    pub fn copy_range(&mut self, range: Range<usize>) -> RegisterId {
+        let copy = self.slice(range).into_owned();
+        self.kill(copy)
+    }
+
+    /// Delete the bytes in `range`, like `delete_range`, and put them into the register `name`,
+    /// replacing what it held before.
+    pub fn cut_range_to(&mut self, range: Range<usize>, name: impl Into<String>) -> RegisterId {
+        let cut = self.delete_range(range);
+        self.set_register(name, cut)
+    }
+
+    /// Put `bytes` into the register `name`, replacing what it held before.
+    pub fn set_register(
+        &mut self,
+        name: impl Into<String>,
+        bytes: impl Into<Vec<u8>>,
+    ) -> RegisterId {
+        let name = name.into();
+        self.registers.insert(name.clone(), bytes.into());
+        RegisterId::Named(name)
+    }
+
+    /// Get the content of a register, `None` if it was never set or dropped from the kill ring.
+    pub fn register(&self, id: &RegisterId) -> Option<&[u8]> {
+        match id {
+            RegisterId::Kill(n) => self
+                .kill_ring
+                .iter()
+                .find(|(kill, _)| kill == n)
+                .map(|(_, bytes)| &bytes[..]),
+            RegisterId::Named(name) => self.registers.get(name).map(|bytes| &bytes[..]),
+        }
+    }
+
+    /// Iterate over the registers of the kill ring, the most recent first.
+    pub fn kill_ring(&self) -> impl Iterator<Item = RegisterId> + '_ {
+        self.kill_ring.iter().map(|&(n, _)| RegisterId::Kill(n))
+    }
+
+    /// Insert the content of a register at the cursor, like `insert`. Returns false if the
+    /// register holds nothing.
+    pub fn paste(&mut self, id: &RegisterId) -> bool {
+        match self.register(id) {
+            Some(bytes) => {
+                let bytes = bytes.to_vec();
+                self.insert(&bytes);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Add `bytes` to the front of the kill ring, dropping the oldest entry if it's full.
+    fn kill(&mut self, bytes: Vec<u8>) -> RegisterId {
+        let n = self.next_kill;
+        self.next_kill += 1;
+        self.kill_ring.push_front((n, bytes));
+        self.kill_ring.truncate(KILL_RING_LEN);
+        RegisterId::Kill(n)
+    }
+
+    /// Return the number of bytes of content, i.e. the capacity without the gap.
+    pub fn len(&self) -> usize {
+        self.capacity - self.gap_len()
+    }
+
+    /// Return true if the buffer holds no content.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Return the number of bytes in the gap, including the one byte which is always kept free.
+    pub fn gap_len(&self) -> usize {
+        self.right + 1 - self.left
+    }
+
+    /// Return the position of the cursor, i.e. the number of bytes before the gap.
+    pub fn cursor(&self) -> usize {
+        self.left
+    }
+
+    /// Move the cursor to `pos` bytes from the start of the content, or to the end if `pos` is
+    /// past it. Only the bytes between the cursor and `pos` are moved.
+    pub fn seek(&mut self, pos: usize) {
+        let pos = pos.min(self.len());
+
+        if pos < self.left {
+            self.left_by(self.left - pos)
+        } else {
+            self.right_by(pos - self.left)
+        }
+    }
+
+    /// Return the number of lines, i.e. one more than the number of newlines, so that a buffer
+    /// ending with a newline has an empty last line for the cursor to be on.
+    pub fn line_count(&self) -> usize {
+        self.bytes().filter(|&b| b == b'\n').count() + 1
+    }
+
+    /// Return the offset of the byte in column `col` of line `line`, both counted from zero and
+    /// the column in bytes. The column may be the end of the line. Returns `None` if there is no
+    /// such line or the line is shorter.
+    pub fn offset_of(&self, line: usize, col: usize) -> Option<usize> {
+        let start = self.line_start(line)?;
+        let end = self.find_byte(b'\n', start).unwrap_or(self.len());
+
+        (start + col <= end).then_some(start + col)
+    }
+
+    /// Return the line and column of `offset`, the opposite of `offset_of`. An offset past the
+    /// end is treated as the end of the content.
+    pub fn position_of(&self, offset: usize) -> (usize, usize) {
+        let offset = offset.min(self.len());
+        let (mut line, mut start) = (0, 0);
+
+        while let Some(newline) = self.find_byte(b'\n', start).filter(|&n| n < offset) {
+            line += 1;
+            start = newline + 1;
+        }
+
+        (line, offset - start)
+    }
+
+    /// Move the cursor to the start of line `line`, counted from zero. Returns false and leaves the
+    /// cursor alone if there is no such line.
+    pub fn goto_line(&mut self, line: usize) -> bool {
+        match self.line_start(line) {
+            Some(start) => {
+                self.seek(start);
+                true
+            }
+            None => false,
         }
     }
 
@@ -163,8 +981,319 @@ impl GapBuffer {
         (self.left, self.right)
     }
 
-    #[cfg(test)]
-    pub(crate) fn buffer(&self) -> &[u8] {
+    /// Return the content before and after the gap, without copying it.
+    pub fn as_slices(&self) -> (&[u8], &[u8]) {
+        (&self.buffer[0..self.left], &self.buffer[self.right + 1..])
+    }
+
+    /// Return the content before and after the gap as strings, without copying it, e.g. to draw
+    /// the text on every frame. Fails if either half is not valid UTF-8, which is also the case
+    /// when the cursor is in the middle of a multi-byte character.
+    pub fn as_str_slices(&self) -> Result<(&str, &str), Utf8Error> {
+        let (before, after) = self.as_slices();
+        Ok((str::from_utf8(before)?, str::from_utf8(after)?))
+    }
+
+    /// Return the content as a string, replacing invalid UTF-8 with `U+FFFD`. A character split by
+    /// the gap is kept intact.
+    pub fn to_string_lossy(&self) -> String {
+        String::from_utf8_lossy(&self.bytes().collect::<Vec<_>>()).into_owned()
+    }
+
+    /// Iterate over the bytes of the content, skipping the gap.
+    pub fn bytes(&self) -> impl Iterator<Item = u8> + '_ {
+        let (before, after) = self.as_slices();
+        before.iter().chain(after).copied()
+    }
+
+    /// Return a reader over the content, skipping the gap.
+    pub fn reader(&self) -> Reader<'_> {
+        let (before, after) = self.as_slices();
+        Reader { before, after }
+    }
+
+    /// Iterate over the characters of the content, skipping the gap. Invalid UTF-8 is replaced like
+    /// with `to_string_lossy`.
+    pub fn chars(&self) -> Chars<'_> {
+        let (before, after) = self.as_slices();
+        Chars { before, after }
+    }
+
+    /// Iterate over the lines of the content, without the line endings, like `str::lines`. Only
+    /// lines spanning the gap or containing invalid UTF-8 are copied.
+    pub fn lines(&self) -> Lines<'_> {
+        Lines {
+            buffer: self,
+            pos: 0,
+        }
+    }
+
+    /// Find the offset of the first occurrence of `needle` starting at or after `from`. The content
+    /// is searched across the gap, without copying it.
+    pub fn find(&self, needle: &str, from: usize) -> Option<usize> {
+        let needle = needle.as_bytes();
+        let Some(&first) = needle.first() else {
+            return (from <= self.len()).then_some(from);
+        };
+
+        let mut pos = from;
+        loop {
+            let start = self.find_byte(first, pos)?;
+            if start + needle.len() > self.len() {
+                return None;
+            }
+            if self.matches_at(needle, start) {
+                return Some(start);
+            }
+            pos = start + 1;
+        }
+    }
+
+    /// Find the offset of the last occurrence of `needle` ending at or before `end`, e.g. to
+    /// search backwards from the cursor. An `end` past the end of the content searches all of it.
+    pub fn rfind(&self, needle: &str, end: usize) -> Option<usize> {
+        let needle = needle.as_bytes();
+        let last = end.min(self.len()).checked_sub(needle.len())?;
+
+        (0..=last)
+            .rev()
+            .find(|&start| self.matches_at(needle, start))
+    }
+
+    /// Iterate over the offsets of all the occurrences of `needle` which don't overlap, from the
+    /// start of the content, like `str::match_indices`.
+    pub fn find_all<'a>(&'a self, needle: &'a str) -> impl Iterator<Item = usize> + 'a {
+        let mut from = 0;
+        iter::from_fn(move || {
+            let start = self.find(needle, from)?;
+            from = start + needle.len().max(1);
+            Some(start)
+        })
+    }
+
+    /// Find the first match of `regex` starting at or after `from` and return it's range. The
+    /// content is copied when the gap is in the middle of it, as the regex needs it in one piece.
+    #[cfg(feature = "regex")]
+    pub fn find_regex(&self, regex: &Regex, from: usize) -> Option<Range<usize>> {
+        let content = self.slice(0..self.len());
+        if from > content.len() {
+            return None;
+        }
+
+        regex.find_at(&content, from).map(|m| m.range())
+    }
+
+    /// Replace all the matches of `regex` with `replacement`, in which `$name` and `$1` refer to
+    /// the capture groups like with `Regex::replace_all`. All the replacements are one step of
+    /// `undo`. The cursor ends up after the first replacement, or stays where it was if nothing
+    /// matches. Returns the number of replacements.
+    #[cfg(feature = "regex")]
+    pub fn replace_regex(&mut self, regex: &Regex, replacement: &str) -> usize {
+        let replacements: Vec<_> = {
+            let content = self.slice(0..self.len());
+            regex
+                .captures_iter(&content)
+                .map(|captures| {
+                    let mut expanded = Vec::new();
+                    captures.expand(replacement.as_bytes(), &mut expanded);
+                    (captures.get(0).expect("the whole match").range(), expanded)
+                })
+                .collect()
+        };
+
+        // from the back, so that the ranges of the earlier matches stay valid
+        self.begin_transaction();
+        for (range, expanded) in replacements.iter().rev() {
+            self.delete_range(range.clone());
+            self.insert(expanded);
+        }
+        self.commit();
+
+        replacements.len()
+    }
+
+    /// Get the byte at position `index` of the content, counted without the gap, or `None` if it's
+    /// out of bounds.
+    pub fn byte_at(&self, index: usize) -> Option<u8> {
+        (index < self.len()).then(|| self.buffer[self.physical(index)])
+    }
+
+    /// Get the character starting at byte `index` of the content, counted without the gap, or
+    /// `None` if it's out of bounds. An index in the middle of a character or at invalid UTF-8
+    /// gives `U+FFFD`, like `chars`.
+    pub fn char_at(&self, index: usize) -> Option<char> {
+        self.decode(index).map(|(c, _)| c)
+    }
+
+    /// Return the bytes in `range` of the content, counted without the gap. The range is cut off
+    /// at the end of the content. The bytes are only copied if the range spans the gap.
+    pub fn slice(&self, range: Range<usize>) -> Cow<'_, [u8]> {
+        let end = range.end.min(self.len());
+        let start = range.start.min(end);
+
+        if end <= self.left || start >= self.left {
+            Cow::Borrowed(&self.buffer[self.physical(start)..self.physical(start) + end - start])
+        } else {
+            let mut bytes = self.buffer[start..self.left].to_vec();
+            bytes.extend_from_slice(&self.buffer[self.right + 1..self.physical(end)]);
+            Cow::Owned(bytes)
+        }
+    }
+
+    /// Find the offset at which line `line` starts.
+    fn line_start(&self, line: usize) -> Option<usize> {
+        let mut start = 0;
+        for _ in 0..line {
+            start = self.find_byte(b'\n', start)? + 1;
+        }
+
+        Some(start)
+    }
+
+    /// Find the first occurrence of `byte` in the content at or after `from`.
+    fn find_byte(&self, byte: u8, from: usize) -> Option<usize> {
+        let (before, after) = self.as_slices();
+        if from < before.len() {
+            if let Some(i) = before[from..].iter().position(|&b| b == byte) {
+                return Some(from + i);
+            }
+        }
+
+        let start = from.saturating_sub(before.len()).min(after.len());
+        after[start..]
+            .iter()
+            .position(|&b| b == byte)
+            .map(|i| before.len() + start + i)
+    }
+
+    /// Whether the content starting at `start` begins with `needle`.
+    fn matches_at(&self, needle: &[u8], start: usize) -> bool {
+        start + needle.len() <= self.len()
+            && needle
+                .iter()
+                .enumerate()
+                .all(|(i, &byte)| self.buffer[self.physical(start + i)] == byte)
+    }
+
+    /// Translate a position of the content to an index into the backing array, skipping the gap.
+    fn physical(&self, index: usize) -> usize {
+        if index < self.left {
+            index
+        } else {
+            index + self.gap_len()
+        }
+    }
+
+    /// Return the raw backing array, including the gap.
+    pub fn buffer(&self) -> &[u8] {
         &self.buffer
     }
 }
+
+/// Iterator over the characters of a `GapBuffer`, created by `GapBuffer::chars`.
+#[derive(Debug, Clone)]
+pub struct Chars<'a> {
+    before: &'a [u8],
+    after: &'a [u8],
+}
+
+impl Iterator for Chars<'_> {
+    type Item = char;
+
+    fn next(&mut self) -> Option<char> {
+        // a character is at most 4 bytes long, which may be split by the gap
+        let mut bytes = [0; 4];
+        let first = self.before.len().min(4);
+        let second = self.after.len().min(4 - first);
+        bytes[..first].copy_from_slice(&self.before[..first]);
+        bytes[first..first + second].copy_from_slice(&self.after[..second]);
+        let n = first + second;
+        if n == 0 {
+            return None;
+        }
+
+        let (c, len) = match str::from_utf8(&bytes[..n]) {
+            Ok(str) => str.chars().next().map(|c| (c, c.len_utf8()))?,
+            Err(e) if e.valid_up_to() > 0 => {
+                let c = str::from_utf8(&bytes[..e.valid_up_to()])
+                    .ok()?
+                    .chars()
+                    .next()?;
+                (c, c.len_utf8())
+            }
+            Err(e) => (char::REPLACEMENT_CHARACTER, e.error_len().unwrap_or(n)),
+        };
+
+        let skip = len.min(self.before.len());
+        self.before = &self.before[skip..];
+        self.after = &self.after[len - skip..];
+        Some(c)
+    }
+}
+
+/// Iterator over the lines of a `GapBuffer`, created by `GapBuffer::lines`.
+#[derive(Debug, Clone)]
+pub struct Lines<'a> {
+    buffer: &'a GapBuffer,
+    pos: usize,
+}
+
+impl<'a> Iterator for Lines<'a> {
+    type Item = Cow<'a, str>;
+
+    fn next(&mut self) -> Option<Cow<'a, str>> {
+        let len = self.buffer.len();
+        if self.pos >= len {
+            return None;
+        }
+
+        let newline = self.buffer.find_byte(b'\n', self.pos);
+        let mut end = newline.unwrap_or(len);
+        if newline.is_some() && end > self.pos && self.buffer[end - 1] == b'\r' {
+            end -= 1;
+        }
+
+        let line = match self.buffer.slice(self.pos..end) {
+            Cow::Borrowed(bytes) => String::from_utf8_lossy(bytes),
+            Cow::Owned(bytes) => Cow::Owned(String::from_utf8_lossy(&bytes).into_owned()),
+        };
+        self.pos = newline.map_or(len, |n| n + 1);
+        Some(line)
+    }
+}
+
+/// Reads the content of a `GapBuffer`, created by `GapBuffer::reader`.
+#[derive(Debug, Clone)]
+pub struct Reader<'a> {
+    before: &'a [u8],
+    after: &'a [u8],
+}
+
+impl Read for Reader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let first = self.before.len().min(buf.len());
+        let second = self.after.len().min(buf.len() - first);
+
+        buf[..first].copy_from_slice(&self.before[..first]);
+        buf[first..first + second].copy_from_slice(&self.after[..second]);
+        self.before = &self.before[first..];
+        self.after = &self.after[second..];
+        Ok(first + second)
+    }
+}
+
+impl BufRead for Reader<'_> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.before.is_empty() {
+            Ok(self.after)
+        } else {
+            Ok(self.before)
+        }
+    }
+
+    fn consume(&mut self, amt: usize) {
+        let first = self.before.len().min(amt);
+        self.before = &self.before[first..];
+        self.after = &self.after[(amt - first).min(self.after.len())..];
+    }
+}
@@ -1,4 +1,8 @@
+pub mod codec;
 pub mod data_structures;
+pub mod render;
+pub mod screens;
+pub mod workspaces;
 
 #[cfg(test)]
 mod tests;
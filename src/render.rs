@@ -0,0 +1,285 @@
+use crate::data_structures::bsptree::{BSPTree, Rectangle};
+
+use std::fmt;
+use std::io;
+
+/// The kind of area being drawn. Frontends decide how each kind looks, e.g. the Tk demo maps
+/// them to colors and the ASCII renderer maps them to characters.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+pub enum Fill {
+    Background,
+    Split,
+    Leaf,
+    Focused,
+}
+
+impl Fill {
+    /// The character used for this kind of area by `render_ascii`.
+    pub fn as_char(&self) -> char {
+        match self {
+            Self::Background => ' ',
+            Self::Split => '+',
+            Self::Leaf => '.',
+            Self::Focused => '#',
+        }
+    }
+
+    /// The color used for this kind of area by `BSPTree::to_svg`.
+    pub fn as_color(&self) -> &'static str {
+        match self {
+            Self::Background => "white",
+            Self::Split => "blue",
+            Self::Leaf => "red",
+            Self::Focused => "green",
+        }
+    }
+}
+
+/// Produce the list of rectangles that make up a picture of the `tree`, in the order in which
+/// they should be painted. The first entry is always the background covering the whole tree,
+/// later entries are painted over earlier ones. The gaps of the tree are applied to the leaves and
+/// nodes hidden by a fullscreen leaf are left out. Floating leaves come last, in stacking order.
+pub fn draw_list<T: Clone>(tree: &BSPTree<T>) -> Vec<(Rectangle, Fill)> {
+    let size = tree.get_size();
+    let area = tree.get_area();
+    let gaps = tree.gaps();
+    let mut list = vec![(size, Fill::Background)];
+
+    for node in tree.iter().filter(|node| !node.hidden) {
+        let fill = if !node.leaf {
+            Fill::Split
+        } else if node.focused {
+            Fill::Focused
+        } else {
+            Fill::Leaf
+        };
+        let rect = if node.leaf {
+            gaps.apply(node.rect, area)
+        } else {
+            node.rect
+        };
+        list.push((rect, fill));
+    }
+    for id in tree.floating_leaves() {
+        list.push((tree[id].get_rect(), Fill::Leaf));
+    }
+
+    list
+}
+
+/// A grid of `Fill`s onto which a tree can be painted without needing a display. The tree is
+/// scaled to fit the framebuffer, so a small framebuffer can be used to get a coarse picture of a
+/// large layout.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Framebuffer {
+    width: u32,
+    height: u32,
+    pixels: Vec<Fill>,
+}
+
+impl Framebuffer {
+    /// Create a new framebuffer of size `width` x `height`, filled with the background.
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![Fill::Background; width as usize * height as usize],
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Get the fill at the given pixel, or `None` if it is outside of the framebuffer.
+    pub fn get(&self, x: u32, y: u32) -> Option<Fill> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+
+        Some(self.pixels[self.index(x, y)])
+    }
+
+    /// Paint the `tree` onto the framebuffer, replacing its previous contents.
+    pub fn draw<T: Clone>(&mut self, tree: &BSPTree<T>) {
+        let size = tree.get_size();
+
+        for (rect, fill) in draw_list(tree) {
+            // scale the rectangle relative to the tree's top-left corner, floating leaves can be
+            // left of or above it
+            let x0 = self.scale(offset(rect.x, size.x), size.w, self.width);
+            let y0 = self.scale(offset(rect.y, size.y), size.h, self.height);
+            let x1 = self.scale(offset(rect.right(), size.x), size.w, self.width);
+            let y1 = self.scale(offset(rect.bottom(), size.y), size.h, self.height);
+
+            for y in y0..y1.min(self.height) {
+                for x in x0..x1.min(self.width) {
+                    let i = self.index(x, y);
+                    self.pixels[i] = fill;
+                }
+            }
+        }
+    }
+
+    fn index(&self, x: u32, y: u32) -> usize {
+        y as usize * self.width as usize + x as usize
+    }
+
+    fn scale(&self, value: u32, from: u32, to: u32) -> u32 {
+        if from == 0 {
+            return 0;
+        }
+
+        (value as u64 * to as u64 / from as u64) as u32
+    }
+
+    /// Convert the framebuffer to text, one line per row, using `Fill::as_char`.
+    pub fn to_ascii(&self) -> String {
+        let mut out = String::with_capacity((self.width as usize + 1) * self.height as usize);
+
+        for row in self.pixels.chunks(self.width.max(1) as usize) {
+            out.extend(row.iter().map(Fill::as_char));
+            out.push('\n');
+        }
+
+        out
+    }
+}
+
+/// Get how far `value` is past `origin`, or 0 if it is before it.
+fn offset(value: i32, origin: i32) -> u32 {
+    (value as i64 - origin as i64).clamp(0, u32::MAX as i64) as u32
+}
+
+/// Render the `tree` as a `cols` x `rows` grid of characters.
+pub fn render_ascii<T: Clone>(tree: &BSPTree<T>, cols: u32, rows: u32) -> String {
+    let mut fb = Framebuffer::new(cols, rows);
+    fb.draw(tree);
+    fb.to_ascii()
+}
+
+impl<T: Clone> BSPTree<T> {
+    /// Draw the leaves as boxes on a `cols` x `rows` grid of characters, one line per row. Unlike
+    /// `render_ascii`, which fills the areas from `draw_list` with `Fill::as_char`, only the
+    /// outlines of the leaves are drawn and the inside of the focused leaf is filled with `#`.
+    /// The same leaves as in `draw_list` are drawn, with the gaps applied and the floating leaves
+    /// on top, clipped to the tree. Neighboring leaves share their borders.
+    pub fn render_boxes(&self, cols: u32, rows: u32) -> String {
+        let (cols, rows) = (cols as usize, rows as usize);
+        // there are no cells to draw into, like with `render_ascii`
+        if cols == 0 || rows == 0 {
+            return String::new();
+        }
+
+        let size = self.get_size();
+        let mut grid = vec![vec![' '; cols]; rows];
+
+        // scale a coordinate relative to the tree's top-left corner onto the grid
+        let scale = |value: i32, length: u32, cells: usize| -> usize {
+            if length == 0 || cells == 0 {
+                return 0;
+            }
+            ((value as i64 * cells as i64 / length as i64) as usize).min(cells - 1)
+        };
+        let put = |grid: &mut Vec<Vec<char>>, x: usize, y: usize, ch: char| {
+            let cell = &mut grid[y][x];
+            *cell = match (*cell, ch) {
+                ('|', '-') | ('-', '|') | ('+', _) => '+',
+                _ => ch,
+            };
+        };
+
+        for (rect, fill) in draw_list(self) {
+            if !matches!(fill, Fill::Leaf | Fill::Focused) {
+                continue;
+            }
+            // floating leaves can be partly or completely outside of the tree
+            let rect = match rect.intersection(&size) {
+                Some(rect) => rect,
+                None => continue,
+            };
+            let x0 = scale(rect.x - size.x, size.w, cols);
+            let y0 = scale(rect.y - size.y, size.h, rows);
+            let x1 = scale(rect.right() - size.x, size.w, cols);
+            let y1 = scale(rect.bottom() - size.y, size.h, rows);
+
+            // clear the inside first, so floating leaves cover what is below them
+            let inside = if fill == Fill::Focused { '#' } else { ' ' };
+            for row in grid.iter_mut().take(y1).skip(y0 + 1) {
+                if x1 > x0 {
+                    row[x0 + 1..x1].fill(inside);
+                }
+            }
+            for x in x0..=x1 {
+                let ch = if x == x0 || x == x1 { '+' } else { '-' };
+                put(&mut grid, x, y0, ch);
+                put(&mut grid, x, y1, ch);
+            }
+            for y in y0 + 1..y1 {
+                put(&mut grid, x0, y, '|');
+                put(&mut grid, x1, y, '|');
+            }
+        }
+
+        let mut out = String::with_capacity((cols + 1) * rows);
+        for row in grid {
+            out.extend(row);
+            out.push('\n');
+        }
+        out
+    }
+}
+
+impl<T: Clone + fmt::Display> BSPTree<T> {
+    /// Write a picture of the tree as an SVG document. Every visible leaf is labeled with it's
+    /// data, the colors are taken from `Fill::as_color`.
+    pub fn to_svg(&self, out: &mut impl io::Write) -> io::Result<()> {
+        let size = self.get_size();
+        writeln!(
+            out,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" \
+             viewBox=\"{} {} {} {}\">",
+            size.w, size.h, size.x, size.y, size.w, size.h
+        )?;
+
+        for (rect, fill) in draw_list(self) {
+            writeln!(
+                out,
+                "  <rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"/>",
+                rect.x,
+                rect.y,
+                rect.w,
+                rect.h,
+                fill.as_color()
+            )?;
+        }
+
+        for node in self.iter().filter(|node| node.leaf && !node.hidden) {
+            let data = match node.data {
+                Some(data) => data.to_string(),
+                None => continue,
+            };
+            let (x, y) = self.gaps().apply(node.rect, self.get_area()).center();
+            writeln!(
+                out,
+                "  <text x=\"{}\" y=\"{}\" text-anchor=\"middle\" \
+                 dominant-baseline=\"middle\">{}</text>",
+                x,
+                y,
+                escape_xml(&data)
+            )?;
+        }
+
+        writeln!(out, "</svg>")
+    }
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
@@ -0,0 +1,215 @@
+use crate::data_structures::bsptree::{BSPTree, MoveDirection, NodeData, Rectangle, TreeError};
+
+/// One `BSPTree` for each output of a multi-monitor setup, each covering the rectangle of it's
+/// output. The screens are placed next to each other by their rectangles, so that moving the
+/// focus or a leaf off the edge of one screen continues on the adjacent one. One of the screens
+/// is active, i.e. holds the focus.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Screens<T = NodeData> {
+    trees: Vec<BSPTree<T>>,
+    active: usize,
+}
+
+impl<T: Clone> Screens<T> {
+    /// Create an empty tree for each of the `outputs`, the first one is active.
+    ///
+    /// Panics if there are no outputs.
+    pub fn new(outputs: &[Rectangle]) -> Self {
+        assert!(!outputs.is_empty(), "there has to be at least one screen");
+        Self {
+            trees: outputs.iter().map(|&rect| BSPTree::new(rect)).collect(),
+            active: 0,
+        }
+    }
+
+    /// Get the number of screens.
+    pub fn len(&self) -> usize {
+        self.trees.len()
+    }
+
+    /// There is always at least one screen, this exists for consistency with `len`.
+    pub fn is_empty(&self) -> bool {
+        self.trees.is_empty()
+    }
+
+    /// Get the index of the active screen.
+    pub fn active_index(&self) -> usize {
+        self.active
+    }
+
+    /// Get the tree of the active screen.
+    pub fn active(&self) -> &BSPTree<T> {
+        &self.trees[self.active]
+    }
+
+    /// Get the tree of the active screen for modification.
+    pub fn active_mut(&mut self) -> &mut BSPTree<T> {
+        &mut self.trees[self.active]
+    }
+
+    /// Get the tree of screen `n`, `None` if there is no such screen.
+    pub fn get(&self, n: usize) -> Option<&BSPTree<T>> {
+        self.trees.get(n)
+    }
+
+    /// Get the tree of screen `n` for modification, `None` if there is no such screen.
+    pub fn get_mut(&mut self, n: usize) -> Option<&mut BSPTree<T>> {
+        self.trees.get_mut(n)
+    }
+
+    /// Iterate over the trees of all the screens, in order.
+    pub fn iter(&self) -> impl Iterator<Item = &BSPTree<T>> {
+        self.trees.iter()
+    }
+
+    /// Make screen `n` the active one. Returns false if there is no such screen.
+    pub fn focus_screen(&mut self, n: usize) -> bool {
+        if n >= self.trees.len() {
+            return false;
+        }
+
+        self.active = n;
+        true
+    }
+
+    /// Get the index of the screen covering the given coordinates.
+    pub fn screen_at(&self, x: i32, y: i32) -> Option<usize> {
+        self.trees
+            .iter()
+            .position(|tree| tree.get_size().is_inside(x, y))
+    }
+
+    /// Move the focus in the given `direction`, like `BSPTree::move_focus`. If there is no leaf in
+    /// that direction on the active screen, the focus moves to the adjacent screen, onto the leaf
+    /// along the edge it's entered from which is closest to the focused leaf. An empty screen
+    /// becomes active without a focused leaf.
+    ///
+    /// Returns `TreeError::NoNeighbor` if there is no leaf and no screen in that direction.
+    pub fn move_focus(&mut self, direction: MoveDirection) -> Result<(), TreeError> {
+        match self.active_mut().move_focus(direction) {
+            Err(TreeError::NoNeighbor | TreeError::Empty) => {}
+            result => return result,
+        }
+
+        let (n, (x, y)) = self.crossing(direction).ok_or(TreeError::NoNeighbor)?;
+        self.trees[n].focus_coords(x, y);
+        self.active = n;
+        Ok(())
+    }
+
+    /// Swap the focused leaf with the leaf in the given `direction`, like
+    /// `BSPTree::swap_in_direction`. If there is no leaf in that direction on the active screen,
+    /// the data, metadata and borders are exchanged with the closest leaf of the adjacent screen, or the
+    /// leaf is moved there if that screen is empty. The focus follows the leaf.
+    ///
+    /// Returns `TreeError::Empty` if the active screen is empty or `TreeError::NoNeighbor` if
+    /// there is no leaf and no screen in that direction. If the leaf does not fit into the empty
+    /// screen the error is returned and both screens are left untouched.
+    pub fn swap(&mut self, direction: MoveDirection) -> Result<(), TreeError> {
+        match self.active_mut().swap_in_direction(direction) {
+            Err(TreeError::NoNeighbor) => {}
+            result => return result,
+        }
+
+        let (n, (x, y)) = self.crossing(direction).ok_or(TreeError::NoNeighbor)?;
+        let source = self.active;
+        let (data, meta, border) = self.focused_leaf(source);
+
+        if self.trees[n].is_empty() {
+            self.trees[n].insert(data)?;
+            self.put_state(n, meta, border);
+            self.trees[source].delete_focused()?;
+        } else {
+            self.trees[n].focus_coords(x, y);
+            let (other, other_meta, other_border) = self.focused_leaf(n);
+            self.trees[n].set_focused_data(data);
+            self.put_state(n, meta, border);
+            self.trees[source].set_focused_data(other);
+            self.put_state(source, other_meta, other_border);
+        }
+
+        self.active = n;
+        Ok(())
+    }
+
+    /// Find the screen entered when leaving the active screen in `direction`, together with the
+    /// point at which it's entered. The closest screen overlapping the active one across the
+    /// direction wins, preferring the one next to the focused leaf. Screens without any room for
+    /// leaves, e.g. because all of it is reserved, are skipped.
+    fn crossing(&self, direction: MoveDirection) -> Option<(usize, (i32, i32))> {
+        let tree = self.active();
+        let size = tree.get_size();
+        let from = match tree.get_focused() {
+            Some(f) => tree[f].get_rect(),
+            None => size,
+        };
+
+        let (n, _) = self
+            .trees
+            .iter()
+            .enumerate()
+            .filter(|&(n, other)| n != self.active && other.get_area().area() > 0)
+            .filter_map(|(n, other)| {
+                let rect = other.get_size();
+                // the gap between the screens and whether they overlap across the direction
+                let (gap, overlaps, next_to) = match direction {
+                    MoveDirection::Left => (size.x - rect.right(), ys(size, rect), ys(from, rect)),
+                    MoveDirection::Right => (rect.x - size.right(), ys(size, rect), ys(from, rect)),
+                    MoveDirection::Up => (size.y - rect.bottom(), xs(size, rect), xs(from, rect)),
+                    MoveDirection::Down => (rect.y - size.bottom(), xs(size, rect), xs(from, rect)),
+                };
+                (gap >= 0 && overlaps).then_some((n, (gap, !next_to, n)))
+            })
+            .min_by_key(|&(_, key)| key)?;
+
+        // the point on the edge of the other screen closest to the focused leaf
+        let area = self.trees[n].get_area();
+        let clamp_x = from.x.clamp(area.x, area.right() - 1);
+        let clamp_y = from.y.clamp(area.y, area.bottom() - 1);
+        let point = match direction {
+            MoveDirection::Left => (area.right() - 1, clamp_y),
+            MoveDirection::Right => (area.x, clamp_y),
+            MoveDirection::Up => (clamp_x, area.bottom() - 1),
+            MoveDirection::Down => (clamp_x, area.y),
+        };
+        Some((n, point))
+    }
+
+    /// Get the data and metadata of the focused leaf of screen `n`, which must not be empty.
+    fn focused_leaf(&self, n: usize) -> (T, Vec<(String, String)>, Option<u32>) {
+        let tree = &self.trees[n];
+        let node = &tree[tree.get_focused().expect("the screen has a focused leaf")];
+        let meta = node
+            .meta()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        let data = node.get_data().expect("a leaf has data").clone();
+        (data, meta, node.get_border())
+    }
+
+    /// Replace the metadata and the border of the focused leaf of screen `n`, which must not be
+    /// empty.
+    fn put_state(&mut self, n: usize, meta: Vec<(String, String)>, border: Option<u32>) {
+        let tree = &mut self.trees[n];
+        let id = tree.get_focused().expect("the screen has a focused leaf");
+        let keys: Vec<_> = tree[id].meta().map(|(k, _)| k.to_string()).collect();
+
+        for key in keys {
+            let _ = tree.remove_meta(id, &key);
+        }
+        for (key, value) in meta {
+            let _ = tree.set_meta(id, key, value);
+        }
+        let _ = tree.set_node_border(id, border);
+    }
+}
+
+/// Whether the rectangles share a part of the x axis.
+fn xs(a: Rectangle, b: Rectangle) -> bool {
+    a.x < b.right() && b.x < a.right()
+}
+
+/// Whether the rectangles share a part of the y axis.
+fn ys(a: Rectangle, b: Rectangle) -> bool {
+    a.y < b.bottom() && b.y < a.bottom()
+}
@@ -2,14 +2,2316 @@
 mod test {
     use crate::data_structures::bsptree::*;
 
+    use std::sync::{Arc, Mutex};
+
     #[test]
     fn bs() {
         let mut tree = BSPTree::new(Rectangle::new(0, 0, 64, 64));
 
-        tree.insert(2);
-        tree.insert(3);
-        tree.insert(4);
+        tree.insert(2).unwrap();
+        tree.insert(3).unwrap();
+        tree.insert(4).unwrap();
+
+        tree.print_order(TraversalOrder::PreOrder);
+    }
+
+    #[test]
+    fn bs_write_tree() {
+        let mut tree = BSPTree::new(Rectangle::new(0, 0, 64, 64));
+
+        let mut out = String::new();
+        tree.write_tree(&mut out, TraversalOrder::PreOrder).unwrap();
+        assert!(out.is_empty());
+
+        tree.insert(1).unwrap();
+        tree.insert(2).unwrap();
+
+        let root = "value:None size:(0, 0); (64, 64) focus:false right_child:false";
+        let left = "    value:Some(1) size:(0, 0); (32, 64) focus:false right_child:false";
+        let right = "    value:Some(2) size:(32, 0); (32, 64) focus:true right_child:true";
+        for (order, lines) in [
+            (TraversalOrder::PreOrder, [root, left, right]),
+            (TraversalOrder::InOrder, [left, root, right]),
+            (TraversalOrder::PostOrder, [left, right, root]),
+        ] {
+            let mut out = String::new();
+            tree.write_tree(&mut out, order).unwrap();
+            assert_eq!(out, lines.join("\n") + "\n");
+        }
+    }
+
+    #[test]
+    fn bs_stats() {
+        let mut tree = BSPTree::new(Rectangle::new(0, 0, 64, 64));
+        assert!(tree.is_empty());
+        assert_eq!(
+            (tree.node_count(), tree.leaf_count(), tree.depth()),
+            (0, 0, 0)
+        );
+
+        tree.insert(1).unwrap();
+        assert!(!tree.is_empty());
+        assert_eq!(
+            (tree.node_count(), tree.leaf_count(), tree.depth()),
+            (1, 1, 0)
+        );
+
+        tree.insert(2).unwrap();
+        tree.insert(3).unwrap();
+        tree.focus_nth(0);
+        tree.insert(4).unwrap();
+        assert_eq!(
+            (tree.node_count(), tree.leaf_count(), tree.depth()),
+            (7, 4, 2)
+        );
+        assert_eq!(tree.node_count(), tree.walk().len());
+
+        tree.focus_nth(3);
+        tree.delete_focused().unwrap();
+        assert_eq!(
+            (tree.node_count(), tree.leaf_count(), tree.depth()),
+            (5, 3, 2)
+        );
+    }
+
+    #[test]
+    fn bs_validate() {
+        let mut tree = BSPTree::new(Rectangle::new(0, 0, 64, 64));
+        assert_eq!(tree.validate(), Ok(()));
+
+        tree.insert(1).unwrap();
+        assert_eq!(tree.validate(), Ok(()));
+
+        tree.insert(2).unwrap();
+        tree.toggle_split().unwrap();
+        tree.insert(3).unwrap();
+        tree.insert_with(4, SplitDirection::Vertical, Side::First)
+            .unwrap();
+        tree.resize_focused(MoveDirection::Right, 8).unwrap();
+        tree.rotate_focused().unwrap();
+        tree.mirror(SplitDirection::Horizontal);
+        tree.toggle_fullscreen().unwrap();
+        assert_eq!(tree.validate(), Ok(()));
+
+        tree.focus_nth(0);
+        tree.delete_focused().unwrap();
+        tree.focus_nth(1);
+        tree.delete_focused().unwrap();
+        assert_eq!(tree.validate(), Ok(()));
+
+        assert!(tree.undo());
+        assert_eq!(tree.validate(), Ok(()));
+
+        let restored = BSPTree::from_layout(tree.to_layout().unwrap(), tree.get_size());
+        assert_eq!(restored.validate(), Ok(()));
+        assert!(restored.structural_eq(&tree));
+    }
+
+    #[test]
+    fn bs_undo_redo() {
+        let mut tree = BSPTree::new(Rectangle::new(0, 0, 64, 64));
+
+        tree.insert(1).unwrap();
+        tree.insert(2).unwrap();
+        tree.delete_focused().unwrap();
+        assert_eq!(tree.walk().len(), 1);
+
+        assert!(tree.undo());
+        let nodes = tree.walk();
+        assert_eq!(nodes.len(), 3);
+        assert_eq!(tree[nodes[2]].get_data(), Some(&2));
+        assert!(tree[nodes[2]].is_focused());
+
+        assert!(tree.undo());
+        assert!(tree.undo());
+        assert!(tree.walk().is_empty());
+        assert!(!tree.undo());
+
+        assert!(tree.redo());
+        assert!(tree.redo());
+        assert_eq!(tree.walk().len(), 3);
+
+        tree.insert(3).unwrap();
+        assert!(!tree.redo());
+    }
+
+    #[test]
+    fn bs_insert_with() {
+        let mut tree = BSPTree::new(Rectangle::new(0, 0, 64, 64));
+
+        tree.insert_with(1, SplitDirection::Horizontal, Side::First)
+            .unwrap();
+        // open below
+        tree.insert_with(2, SplitDirection::Horizontal, Side::Second)
+            .unwrap();
+        // open to the left
+        tree.insert_with(3, SplitDirection::Vertical, Side::First)
+            .unwrap();
+
+        assert_eq!(
+            tree.leaves().collect::<Vec<_>>(),
+            vec![
+                (1, Rectangle::new(0, 0, 64, 32)),
+                (3, Rectangle::new(0, 32, 32, 32)),
+                (2, Rectangle::new(32, 32, 32, 32)),
+            ]
+        );
+        assert_eq!(
+            tree.find(&3),
+            tree.get_focused().map(|f| tree[f].get_rect())
+        );
+
+        // the new leaf keeps splitting the way it was inserted
+        tree.insert(4).unwrap();
+        assert_eq!(tree.find(&4), Some(Rectangle::new(16, 32, 16, 32)));
+    }
+
+    #[test]
+    fn bs_remove_data() {
+        let mut tree = BSPTree::new(Rectangle::new(0, 0, 64, 64));
+
+        tree.insert(1).unwrap();
+        tree.insert(2).unwrap();
+        tree.insert(3).unwrap();
+
+        assert_eq!(tree.remove_data(&2), Some(2));
+        assert_eq!(tree.remove_data(&5), None);
+        assert_eq!(
+            tree.leaves().collect::<Vec<_>>(),
+            vec![
+                (1, Rectangle::new(0, 0, 32, 64)),
+                (3, Rectangle::new(32, 0, 32, 64)),
+            ]
+        );
+        // the focus stays where it was
+        assert_eq!(tree[tree.get_focused().unwrap()].get_data(), Some(&3));
+
+        assert_eq!(tree.remove_data(&3), Some(3));
+        assert_eq!(tree[tree.get_focused().unwrap()].get_data(), Some(&1));
+
+        assert!(tree.undo());
+        assert_eq!(tree.leaves().count(), 2);
+    }
+
+    #[test]
+    fn bs_remove_hidden_data() {
+        let mut tree = BSPTree::new(Rectangle::new(0, 0, 64, 64));
+
+        tree.insert(1).unwrap();
+        tree.insert(2).unwrap();
+        tree.insert(3).unwrap();
+        tree.hide_focused().unwrap();
+        let focused = tree.get_focused();
+
+        assert_eq!(tree.remove_data(&3), Some(3));
+        assert_eq!(tree.hidden_leaves().count(), 0);
+        assert_eq!(tree.get_focused(), focused);
+        assert_eq!(tree.show(&3), Err(TreeError::NotHidden));
+        assert_eq!(tree.validate(), Ok(()));
+
+        assert!(tree.undo());
+        assert_eq!(tree.hidden_leaves().count(), 1);
+
+        // the last leaf is hidden and then removed
+        let mut tree = BSPTree::new(Rectangle::new(0, 0, 64, 64));
+        tree.insert(1).unwrap();
+        tree.hide_focused().unwrap();
+        assert_eq!(tree.remove_data(&1), Some(1));
+        assert_eq!(tree.remove_data(&1), None);
+        assert_eq!(tree.validate(), Ok(()));
+        tree.insert(2).unwrap();
+        assert_eq!(tree.find(&2), Some(Rectangle::new(0, 0, 64, 64)));
+    }
+
+    #[test]
+    fn bs_remove_floating_data() {
+        let mut tree = BSPTree::new(Rectangle::new(0, 0, 64, 64));
+
+        tree.insert(1).unwrap();
+        tree.insert(2).unwrap();
+        tree.float_focused().unwrap();
+        tree.insert(3).unwrap();
+        tree.float_focused().unwrap();
+        assert_eq!(tree.floating_leaves().count(), 2);
+
+        assert_eq!(tree.remove_data(&2), Some(2));
+        let floating: Vec<_> = tree.floating_leaves().collect();
+        assert_eq!(floating.len(), 1);
+        assert_eq!(tree[floating[0]].get_data(), Some(&3));
+        assert_eq!(
+            tree.leaves().collect::<Vec<_>>(),
+            [(1, Rectangle::new(0, 0, 64, 64))]
+        );
+        assert_eq!(tree.validate(), Ok(()));
+
+        assert!(tree.undo());
+        assert_eq!(tree.floating_leaves().count(), 2);
+    }
+
+    #[test]
+    fn bs_neighbor() {
+        let mut tree = BSPTree::new(Rectangle::new(0, 0, 64, 64));
+        assert_eq!(tree.neighbor(MoveDirection::Left), None);
+
+        tree.insert(1).unwrap();
+        tree.insert(2).unwrap();
+        tree.toggle_split().unwrap();
+        tree.insert(3).unwrap();
+
+        assert_eq!(
+            tree.neighbor(MoveDirection::Up),
+            Some((2, Rectangle::new(32, 0, 32, 32)))
+        );
+        assert_eq!(
+            tree.neighbor(MoveDirection::Left),
+            Some((1, Rectangle::new(0, 0, 32, 64)))
+        );
+        assert_eq!(tree.neighbor(MoveDirection::Right), None);
+        assert_eq!(tree.neighbor(MoveDirection::Down), None);
+
+        // the focus didn't move
+        assert_eq!(tree[tree.get_focused().unwrap()].get_data(), Some(&3));
+
+        let (data, _) = tree.neighbor(MoveDirection::Up).unwrap();
+        tree.move_focus(MoveDirection::Up).unwrap();
+        assert_eq!(tree[tree.get_focused().unwrap()].get_data(), Some(&data));
+    }
+
+    #[test]
+    fn bs_swap_in_direction() {
+        let mut tree = BSPTree::new(Rectangle::new(0, 0, 64, 64));
+
+        tree.insert(1).unwrap();
+        tree.insert(2).unwrap();
+
+        tree.swap_in_direction(MoveDirection::Left).unwrap();
+        let left = tree.leaf_at(0, 0).unwrap();
+        let right = tree.leaf_at(63, 0).unwrap();
+        assert_eq!(tree[left].get_data(), Some(&2));
+        assert!(tree[left].is_focused());
+        assert_eq!(tree[right].get_data(), Some(&1));
+        assert!(!tree[right].is_focused());
+
+        // nothing to the left of the leftmost leaf
+        assert_eq!(
+            tree.swap_in_direction(MoveDirection::Left),
+            Err(TreeError::NoNeighbor)
+        );
+        assert_eq!(tree[tree.leaf_at(0, 0).unwrap()].get_data(), Some(&2));
+
+        assert!(tree.undo());
+        assert_eq!(tree[tree.leaf_at(0, 0).unwrap()].get_data(), Some(&1));
+    }
+
+    #[test]
+    fn bs_swap_across_subtrees() {
+        let mut tree = BSPTree::new(Rectangle::new(0, 0, 64, 64));
+
+        tree.insert(1).unwrap();
+        tree.insert(2).unwrap();
+        tree.toggle_split().unwrap();
+        tree.insert(3).unwrap();
+
+        tree.swap_in_direction(MoveDirection::Left).unwrap();
+
+        let left = tree.leaf_at(0, 0).unwrap();
+        let bottom_right = tree.leaf_at(63, 63).unwrap();
+        assert_eq!(tree[left].get_data(), Some(&3));
+        assert_eq!(tree[left].get_rect(), Rectangle::new(0, 0, 32, 64));
+        assert!(tree[left].is_focused());
+        assert_eq!(tree[bottom_right].get_data(), Some(&1));
+        assert_eq!(
+            tree[bottom_right].get_rect(),
+            Rectangle::new(32, 32, 32, 32)
+        );
+        assert_eq!(tree[tree.leaf_at(63, 0).unwrap()].get_data(), Some(&2));
+    }
+
+    #[test]
+    fn bs_focus_nth() {
+        let mut tree = BSPTree::new(Rectangle::new(0, 0, 64, 64));
+
+        tree.insert(1).unwrap();
+        tree.insert(2).unwrap();
+        tree.insert(3).unwrap();
+
+        tree.focus_nth(0);
+        assert!(tree[tree.leaf_at(0, 0).unwrap()].is_focused());
+
+        tree.focus_nth(2);
+        let focused: Vec<_> = tree
+            .walk()
+            .into_iter()
+            .filter(|&n| tree[n].is_focused())
+            .collect();
+        assert_eq!(focused.len(), 1);
+        assert_eq!(tree[focused[0]].get_data(), Some(&3));
+
+        tree.focus_nth(3);
+        assert!(tree[focused[0]].is_focused());
+    }
+
+    #[test]
+    fn bs_find_data() {
+        let mut tree = BSPTree::new(Rectangle::new(0, 0, 64, 64));
+        assert_eq!(tree.find(&1), None);
+
+        tree.insert(1).unwrap();
+        tree.insert(2).unwrap();
+        tree.toggle_split().unwrap();
+        tree.insert(3).unwrap();
+
+        assert_eq!(tree.find(&2), Some(Rectangle::new(32, 0, 32, 32)));
+        assert_eq!(tree.find(&4), None);
+
+        tree.focus_data(&1);
+        assert!(tree[tree.leaf_at(0, 0).unwrap()].is_focused());
+
+        tree.focus_data(&4);
+        assert!(tree[tree.leaf_at(0, 0).unwrap()].is_focused());
+    }
+
+    #[test]
+    fn bs_focus_history() {
+        let mut tree = BSPTree::new(Rectangle::new(0, 0, 64, 64));
+
+        tree.focus_last();
+        tree.insert(1).unwrap();
+        let first = tree.get_focused().unwrap();
+        tree.insert(2).unwrap();
+        let second = tree.get_focused().unwrap();
+        tree.insert(3).unwrap();
+        let third = tree.get_focused().unwrap();
+        assert_eq!(
+            tree.focus_history().collect::<Vec<_>>(),
+            vec![second, first]
+        );
+
+        tree.focus_last();
+        assert_eq!(tree.get_focused(), Some(second));
+        tree.focus_last();
+        assert_eq!(tree.get_focused(), Some(third));
+
+        // deleted leaves are pruned
+        tree.delete_focused().unwrap();
+        assert_eq!(tree.get_focused(), Some(second));
+        assert_eq!(tree.focus_history().collect::<Vec<_>>(), vec![first]);
+
+        tree.focus_last();
+        assert_eq!(tree.get_focused(), Some(first));
+
+        // the history is bounded
+        for n in 0..FOCUS_HISTORY_LEN as u32 * 2 {
+            tree.insert(n).unwrap();
+        }
+        assert_eq!(tree.focus_history().count(), FOCUS_HISTORY_LEN);
+    }
+
+    #[test]
+    fn bs_fullscreen() {
+        let size = Rectangle::new(0, 0, 64, 64);
+        let mut tree = BSPTree::new(size);
+
+        tree.insert(1).unwrap();
+        let first = tree.get_focused().unwrap();
+        tree.insert(2).unwrap();
+        tree.toggle_split().unwrap();
+        tree.insert(3).unwrap();
+        let third = tree.get_focused().unwrap();
+
+        tree.toggle_fullscreen().unwrap();
+        assert_eq!(tree.get_fullscreen(), Some(third));
+        assert_eq!(tree[third].get_rect(), size);
+        assert!(tree.is_hidden(first));
+        assert!(!tree.is_hidden(third));
+        assert_eq!(tree.iter().filter(|n| n.hidden).count(), 4);
+        assert_eq!(tree.leaf_at(0, 0), Some(third));
+
+        // the layout is still updated underneath
+        tree.resize_focused(MoveDirection::Up, 16).unwrap();
+        assert_eq!(tree[third].get_rect(), size);
+
+        tree.toggle_fullscreen().unwrap();
+        assert_eq!(tree.get_fullscreen(), None);
+        assert_eq!(tree[third].get_rect(), Rectangle::new(32, 16, 32, 48));
+
+        // moving the focus away restores the layout
+        tree.toggle_fullscreen().unwrap();
+        tree.focus_nth(0);
+        assert_eq!(tree.get_fullscreen(), None);
+        assert_eq!(tree[third].get_rect(), Rectangle::new(32, 16, 32, 48));
+        assert_eq!(tree.iter().filter(|n| n.hidden).count(), 0);
+    }
+
+    #[test]
+    fn bs_meta() {
+        let mut tree = BSPTree::new(Rectangle::new(0, 0, 64, 64));
+
+        tree.insert(1).unwrap();
+        let first = tree.get_focused().unwrap();
+        tree.insert(2).unwrap();
+        let second = tree.get_focused().unwrap();
+
+        assert_eq!(tree.set_meta(first, "sticky", "yes"), Ok(None));
+        assert_eq!(tree.set_meta(first, "tag", "web"), Ok(None));
+        assert_eq!(
+            tree.set_meta(first, "tag", "mail"),
+            Ok(Some(String::from("web")))
+        );
+        assert_eq!(tree.get_meta(first, "tag"), Some("mail"));
+        assert_eq!(tree.get_meta(second, "tag"), None);
+        assert_eq!(
+            tree[first].meta().collect::<Vec<_>>(),
+            vec![("sticky", "yes"), ("tag", "mail")]
+        );
+
+        // the metadata follows the data
+        tree.swap_in_direction(MoveDirection::Left).unwrap();
+        assert_eq!(tree[second].get_data(), Some(&1));
+        assert_eq!(tree.get_meta(second, "sticky"), Some("yes"));
+        assert_eq!(tree.get_meta(first, "sticky"), None);
+
+        assert_eq!(
+            tree.remove_meta(second, "sticky"),
+            Ok(Some(String::from("yes")))
+        );
+        assert_eq!(tree.remove_meta(second, "sticky"), Ok(None));
+
+        // metadata can't be attached to removed nodes
+        tree.delete_focused().unwrap();
+        assert_eq!(
+            tree.set_meta(first, "sticky", "yes"),
+            Err(TreeError::InvalidNode { id: first })
+        );
+    }
+
+    #[test]
+    fn bs_traversal_orders() {
+        let mut tree = BSPTree::new(Rectangle::new(0, 0, 64, 64));
+
+        tree.insert(1).unwrap();
+        tree.insert(2).unwrap();
+        tree.insert(3).unwrap();
+
+        // the root splits 1 from a node which splits 2 and 3
+        let order = |nodes: Iter<'_, NodeData>| -> Vec<_> {
+            nodes.map(|n| (n.data, n.depth, n.leaf)).collect()
+        };
+        assert_eq!(
+            order(tree.iter_preorder()),
+            vec![
+                (None, 0, false),
+                (Some(1), 1, true),
+                (None, 1, false),
+                (Some(2), 2, true),
+                (Some(3), 2, true)
+            ]
+        );
+        assert_eq!(
+            order(tree.iter_inorder()),
+            vec![
+                (Some(1), 1, true),
+                (None, 0, false),
+                (Some(2), 2, true),
+                (None, 1, false),
+                (Some(3), 2, true)
+            ]
+        );
+        assert_eq!(
+            order(tree.iter_postorder()),
+            vec![
+                (Some(1), 1, true),
+                (Some(2), 2, true),
+                (Some(3), 2, true),
+                (None, 1, false),
+                (None, 0, false)
+            ]
+        );
+        assert_eq!(
+            order(tree.iter()),
+            order(tree.iter_order(TraversalOrder::PreOrder))
+        );
+
+        let empty: BSPTree = BSPTree::new(Rectangle::new(0, 0, 64, 64));
+        assert_eq!(empty.iter_postorder().count(), 0);
+    }
+
+    #[test]
+    fn bs_visit() {
+        struct Recorder(Vec<String>);
+
+        impl TreeVisitor<NodeData> for Recorder {
+            fn enter_internal(&mut self, _id: NodeId, _node: &Node, depth: usize) {
+                self.0.push(format!("enter {depth}"));
+            }
+
+            fn visit_leaf(&mut self, _id: NodeId, node: &Node, depth: usize) {
+                self.0
+                    .push(format!("leaf {} {depth}", node.get_data().unwrap()));
+            }
+
+            fn leave_internal(&mut self, _id: NodeId, _node: &Node, depth: usize) {
+                self.0.push(format!("leave {depth}"));
+            }
+        }
+
+        let mut tree = BSPTree::new(Rectangle::new(0, 0, 64, 64));
+        let mut recorder = Recorder(vec![]);
+        tree.visit(&mut recorder);
+        assert!(recorder.0.is_empty());
+
+        tree.insert(1).unwrap();
+        tree.visit(&mut recorder);
+        assert_eq!(recorder.0, vec!["leaf 1 0"]);
+
+        tree.insert(2).unwrap();
+        tree.insert(3).unwrap();
+        tree.focus_data(&1);
+        tree.insert(4).unwrap();
+        let mut recorder = Recorder(vec![]);
+        tree.visit(&mut recorder);
+        assert_eq!(
+            recorder.0,
+            vec![
+                "enter 0", "enter 1", "leaf 1 2", "leaf 4 2", "leave 1", "enter 1", "leaf 2 2",
+                "leaf 3 2", "leave 1", "leave 0",
+            ]
+        );
+    }
+
+    #[test]
+    fn bs_detach_attach() {
+        let mut tree = BSPTree::new(Rectangle::new(0, 0, 64, 64));
+
+        assert_eq!(tree.detach_focused_subtree(), None);
+
+        tree.insert(1).unwrap();
+        tree.insert(2).unwrap();
+        tree.insert_with(3, SplitDirection::Horizontal, Side::Second)
+            .unwrap();
+        let id = tree.get_focused().unwrap();
+        tree.set_meta(id, "tag", "three").unwrap();
+
+        // 2 and 3 are split horizontally on the right
+        let subtree = tree.detach_focused_subtree().unwrap();
+        assert_eq!(tree.leaf_count(), 1);
+        assert_eq!(tree.find(&1), Some(Rectangle::new(0, 0, 64, 64)));
+        assert_eq!(tree.validate(), Ok(()));
+
+        // put it back on the left
+        tree.attach(subtree.clone(), MoveDirection::Left).unwrap();
+        assert_eq!(tree.validate(), Ok(()));
+        assert_eq!(tree.find(&1), Some(Rectangle::new(32, 0, 32, 64)));
+        assert_eq!(tree.find(&2), Some(Rectangle::new(0, 0, 32, 32)));
+        assert_eq!(tree.find(&3), Some(Rectangle::new(0, 32, 32, 32)));
+        let focused = tree.get_focused().unwrap();
+        assert_eq!(tree[focused].get_data(), Some(&3));
+        assert_eq!(tree.get_meta(focused, "tag"), Some("three"));
+
+        // into another, empty tree
+        let mut other = BSPTree::new(Rectangle::new(0, 0, 32, 32));
+        other.attach(subtree.clone(), MoveDirection::Down).unwrap();
+        assert_eq!(other.leaf_count(), 2);
+        assert_eq!(other.find(&2), Some(Rectangle::new(0, 0, 32, 16)));
+        assert_eq!(other.validate(), Ok(()));
+
+        // leaves which would get too small are refused
+        let mut small = BSPTree::new(Rectangle::new(0, 0, 32, 32));
+        small.set_min_size(10, 10);
+        small.insert(4).unwrap();
+        assert_eq!(
+            small.attach(subtree, MoveDirection::Up),
+            Err(TreeError::TooSmall)
+        );
+        assert_eq!(small.leaf_count(), 1);
+
+        tree.undo();
+        assert_eq!(tree.leaf_count(), 1);
+        tree.undo();
+        assert_eq!(tree.leaf_count(), 3);
+    }
+
+    #[test]
+    fn bs_promote() {
+        let mut tree = BSPTree::new(Rectangle::new(0, 0, 64, 64));
+
+        tree.insert(1).unwrap();
+        assert_eq!(tree.promote_focused(), Err(TreeError::NoParent));
+        tree.insert(2).unwrap();
+        assert_eq!(tree.promote_focused(), Err(TreeError::NoParent));
+        assert_eq!(tree.find(&2), Some(Rectangle::new(32, 0, 32, 64)));
+
+        tree.insert_with(3, SplitDirection::Horizontal, Side::Second)
+            .unwrap();
+        tree.promote_focused().unwrap();
+        assert_eq!(tree.validate(), Ok(()));
+        assert_eq!(tree.find(&3), Some(Rectangle::new(0, 0, 32, 64)));
+        assert_eq!(tree.find(&2), Some(Rectangle::new(32, 0, 32, 32)));
+        assert_eq!(tree.find(&1), Some(Rectangle::new(32, 32, 32, 32)));
+        let focused = tree.get_focused().unwrap();
+        assert_eq!(tree[focused].get_data(), Some(&3));
+
+        tree.undo();
+        assert_eq!(tree.find(&3), Some(Rectangle::new(32, 32, 32, 32)));
+    }
+
+    #[test]
+    fn bs_insert_at() {
+        let rect = Rectangle::new(0, 0, 600, 100);
+        assert_eq!(
+            SplitDirection::Vertical.split_at(rect, 400),
+            (
+                Rectangle::new(0, 0, 400, 100),
+                Rectangle::new(400, 0, 200, 100)
+            )
+        );
+        assert_eq!(
+            SplitDirection::Horizontal.split_at(rect, 400),
+            (rect, Rectangle::new(0, 100, 600, 0))
+        );
+
+        let mut tree = BSPTree::new(Rectangle::new(0, 0, 640, 480));
+        tree.insert_at(1, SplitDirection::Vertical, 100, Side::Second)
+            .unwrap();
+        tree.insert_at(2, SplitDirection::Vertical, 480, Side::Second)
+            .unwrap();
+        assert_eq!(tree.find(&1), Some(Rectangle::new(0, 0, 480, 480)));
+        assert_eq!(tree.find(&2), Some(Rectangle::new(480, 0, 160, 480)));
+
+        // the proportion is kept when resizing
+        tree.resize(Rectangle::new(0, 0, 320, 480));
+        assert_eq!(tree.find(&1), Some(Rectangle::new(0, 0, 240, 480)));
+
+        tree.insert_at(3, SplitDirection::Horizontal, 120, Side::First)
+            .unwrap();
+        assert_eq!(tree.find(&3), Some(Rectangle::new(240, 0, 80, 120)));
+        assert_eq!(tree.find(&2), Some(Rectangle::new(240, 120, 80, 360)));
+    }
+
+    #[test]
+    fn bs_wrap_focus() {
+        let mut tree = BSPTree::new(Rectangle::new(0, 0, 64, 64));
+
+        tree.insert(1).unwrap();
+        tree.insert(2).unwrap();
+        tree.insert_with(3, SplitDirection::Horizontal, Side::Second)
+            .unwrap();
+
+        // 1 is on the left, 2 on the top right and 3 on the bottom right
+        assert_eq!(
+            tree.move_focus(MoveDirection::Right),
+            Err(TreeError::NoNeighbor)
+        );
+        assert_eq!(tree.neighbor(MoveDirection::Right), None);
+
+        tree.set_wrap_focus(true);
+        assert!(tree.get_wrap_focus());
+        tree.move_focus(MoveDirection::Right).unwrap();
+        assert_eq!(tree[tree.get_focused().unwrap()].get_data(), Some(&1));
+        // the right side was left from 3, so the focus returns there
+        tree.move_focus(MoveDirection::Left).unwrap();
+        assert_eq!(tree[tree.get_focused().unwrap()].get_data(), Some(&3));
+        tree.move_focus(MoveDirection::Down).unwrap();
+        assert_eq!(tree[tree.get_focused().unwrap()].get_data(), Some(&2));
+        tree.move_focus(MoveDirection::Up).unwrap();
+        assert_eq!(tree[tree.get_focused().unwrap()].get_data(), Some(&3));
+
+        // a leaf spanning the whole tree has nothing to wrap to
+        tree.move_focus(MoveDirection::Left).unwrap();
+        assert_eq!(tree.neighbor(MoveDirection::Up), None);
+    }
+
+    #[test]
+    fn bs_focus_cycle() {
+        let mut tree = BSPTree::new(Rectangle::new(0, 0, 64, 64));
+        let focused = |tree: &BSPTree| tree[tree.get_focused().unwrap()].get_data().copied();
+
+        tree.focus_next();
+        assert_eq!(tree.get_focused(), None);
+
+        tree.insert(1).unwrap();
+        tree.focus_next();
+        assert_eq!(focused(&tree), Some(1));
+
+        tree.insert(2).unwrap();
+        tree.insert(3).unwrap();
+        tree.focus_next();
+        assert_eq!(focused(&tree), Some(1));
+        tree.focus_next();
+        assert_eq!(focused(&tree), Some(2));
+        tree.focus_prev();
+        tree.focus_prev();
+        assert_eq!(focused(&tree), Some(3));
+        tree.focus_nth(1);
+        assert_eq!(focused(&tree), Some(2));
+    }
+
+    #[test]
+    fn bs_diff() {
+        let mut tree = BSPTree::new(Rectangle::new(0, 0, 64, 64));
+
+        tree.insert(1).unwrap();
+        tree.insert(2).unwrap();
+        let before = tree.clone();
+        assert_eq!(tree.diff(&before), vec![]);
+
+        tree.insert(3).unwrap();
+        tree.focus_data(&1);
+        tree.delete_focused().unwrap();
+
+        assert_eq!(
+            before.diff(&tree),
+            vec![
+                TreeChange::Removed {
+                    data: 1,
+                    rect: Rectangle::new(0, 0, 32, 64)
+                },
+                TreeChange::Resized {
+                    data: 2,
+                    from: Rectangle::new(32, 0, 32, 64),
+                    to: Rectangle::new(0, 0, 32, 64)
+                },
+                TreeChange::Inserted {
+                    data: 3,
+                    rect: Rectangle::new(32, 0, 32, 64)
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn bs_clone_independent() {
+        let mut tree = BSPTree::new(Rectangle::new(0, 0, 64, 64));
+
+        tree.insert(1).unwrap();
+        tree.insert(2).unwrap();
+        tree.insert(3).unwrap();
+        let copy = tree.clone();
+
+        // changing the original leaves the copy alone
+        tree.delete_focused().unwrap();
+        tree.resize_focused(MoveDirection::Left, 8).unwrap();
+        tree.set_meta(tree.get_focused().unwrap(), "tag", "web")
+            .unwrap();
+        assert_ne!(tree, copy);
+        assert_eq!(copy.leaf_count(), 3);
+        assert_eq!(copy.find(&3), Some(Rectangle::new(48, 0, 16, 64)));
+        assert_eq!(copy.validate(), Ok(()));
+
+        // and the other way around
+        let mut copy = copy;
+        let snapshot = tree.clone();
+        copy.delete_focused().unwrap();
+        copy.delete_focused().unwrap();
+        assert_eq!(tree, snapshot);
+        assert!(tree.structural_eq(&snapshot));
+        assert!(!tree.structural_eq(&copy));
+        assert_eq!(tree.validate(), Ok(()));
+    }
+
+    #[test]
+    fn bs_deep_tree() {
+        const DEPTH: usize = 100_000;
+
+        // every split has a leaf on the left and the rest of the chain on the right
+        let mut layout = Layout::Leaf {
+            data: DEPTH,
+            split: SplitDirection::Vertical,
+            focused: false,
+        };
+        for i in (0..DEPTH).rev() {
+            layout = Layout::Split {
+                split: SplitDirection::Vertical,
+                ratio: 0.5,
+                left: Box::new(Layout::Leaf {
+                    data: i,
+                    split: SplitDirection::Vertical,
+                    focused: false,
+                }),
+                right: Box::new(layout),
+            };
+        }
+
+        let mut tree = BSPTree::from_layout(layout, Rectangle::new(0, 0, 1 << 20, 64));
+        assert_eq!(tree.depth(), DEPTH);
+        assert_eq!(tree.leaf_count(), DEPTH + 1);
+        assert_eq!(tree.find(&0), Some(Rectangle::new(0, 0, 1 << 19, 64)));
+
+        tree.resize(Rectangle::new(0, 0, 1 << 21, 64));
+        assert_eq!(tree.find(&0), Some(Rectangle::new(0, 0, 1 << 20, 64)));
+
+        // the layout is handed to `from_layout`, which takes it apart without recursing
+        let layout = tree.to_layout().unwrap();
+        let copy = BSPTree::from_layout(layout, tree.get_size());
+        assert_eq!(copy.depth(), DEPTH);
+        assert_eq!(copy.find(&DEPTH), tree.find(&DEPTH));
+
+        // resizing looks at the minimum length of the whole chain
+        tree.focus_nth(0);
+        tree.resize_focused(MoveDirection::Right, 8).unwrap();
+        drop(copy);
+        drop(tree);
+    }
+
+    #[test]
+    fn bs_hide_show() {
+        let mut tree = BSPTree::new(Rectangle::new(0, 0, 64, 64));
+        assert_eq!(tree.hide_focused(), Err(TreeError::Empty));
+
+        tree.insert(1).unwrap();
+        tree.insert(2).unwrap();
+        tree.insert(3).unwrap();
+        let third = tree.get_focused().unwrap();
+        tree.set_meta(third, "tag", "scratch").unwrap();
+
+        // the sibling takes up the space
+        tree.hide_focused().unwrap();
+        assert!(tree.is_hidden(third));
+        assert_eq!(tree.hidden_leaves().collect::<Vec<_>>(), vec![third]);
+        assert_eq!(tree.leaf_count(), 2);
+        assert_eq!(tree.find(&3), None);
+        assert_eq!(tree.find(&2), Some(Rectangle::new(32, 0, 32, 64)));
+        assert_eq!(tree[tree.get_focused().unwrap()].get_data(), Some(&2));
+        assert_eq!(tree.validate(), Ok(()));
+
+        // hiding the last leaf leaves the tree empty, but the leaves are kept
+        tree.hide_focused().unwrap();
+        tree.hide_focused().unwrap();
+        assert!(tree.is_empty());
+        assert_eq!(tree.hidden_leaves().count(), 3);
+        assert_eq!(tree.validate(), Ok(()));
+
+        assert_eq!(tree.show(&4), Err(TreeError::NotHidden));
+        tree.show(&1).unwrap();
+        assert_eq!(tree.find(&1), Some(Rectangle::new(0, 0, 64, 64)));
+        tree.show(&3).unwrap();
+        assert_eq!(tree.get_focused(), Some(third));
+        assert_eq!(tree.get_meta(third, "tag"), Some("scratch"));
+        assert_eq!(tree.find(&3), Some(Rectangle::new(32, 0, 32, 64)));
+        assert_eq!(tree.hidden_leaves().count(), 1);
+        assert_eq!(tree.validate(), Ok(()));
+
+        // a leaf which doesn't fit stays hidden
+        tree.set_min_size(24, 24);
+        assert_eq!(tree.show(&2), Err(TreeError::TooSmall));
+        assert_eq!(tree.hidden_leaves().count(), 1);
+
+        tree.undo();
+        assert!(tree.is_hidden(third));
+        assert_eq!(tree.validate(), Ok(()));
+    }
+
+    #[test]
+    fn bs_reserve() {
+        let mut tree = BSPTree::new(Rectangle::new(0, 0, 64, 64));
+        let bar = tree.reserve(MoveDirection::Up, 8);
+        assert_eq!(tree.get_area(), Rectangle::new(0, 8, 64, 56));
+
+        tree.insert(1).unwrap();
+        tree.insert(2).unwrap();
+        assert_eq!(tree.find(&1), Some(Rectangle::new(0, 8, 32, 56)));
+        assert_eq!(tree.validate(), Ok(()));
+
+        // reservations stack, also on the same edge
+        let dock = tree.reserve(MoveDirection::Left, 16);
+        let second = tree.reserve(MoveDirection::Up, 8);
+        assert_eq!(tree.get_area(), Rectangle::new(16, 16, 48, 48));
+        assert_eq!(tree.find(&1), Some(Rectangle::new(16, 16, 24, 48)));
+        assert_eq!(tree.find(&2), Some(Rectangle::new(40, 16, 24, 48)));
+        assert_eq!(tree.validate(), Ok(()));
+
+        // a fullscreen leaf covers the reserved regions
+        tree.toggle_fullscreen().unwrap();
+        assert_eq!(tree.find(&2), Some(Rectangle::new(0, 0, 64, 64)));
+        tree.toggle_fullscreen().unwrap();
+
+        assert!(tree.unreserve(bar));
+        assert!(!tree.unreserve(bar));
+        assert!(tree.unreserve(second));
+        assert_eq!(tree.find(&1), Some(Rectangle::new(16, 0, 24, 64)));
+        assert!(tree.unreserve(dock));
+        assert_eq!(tree.get_area(), tree.get_size());
+        assert_eq!(tree.find(&1), Some(Rectangle::new(0, 0, 32, 64)));
+        assert_eq!(tree.validate(), Ok(()));
+    }
+
+    #[test]
+    fn bs_set_data() {
+        let mut tree = BSPTree::new(Rectangle::new(0, 0, 64, 64));
+        assert_eq!(tree.set_focused_data(1), None);
+
+        tree.insert(1).unwrap();
+        tree.insert(2).unwrap();
+        let focused = tree.get_focused();
+        assert_eq!(tree.set_focused_data(3), Some(2));
+        assert_eq!(tree.get_focused(), focused);
+        assert_eq!(tree.find(&3), Some(Rectangle::new(32, 0, 32, 64)));
+        assert_eq!(tree.find(&2), None);
+
+        assert_eq!(tree.replace_data(&1, 4), Some(1));
+        assert_eq!(tree.replace_data(&1, 5), None);
+        assert_eq!(tree.find(&4), Some(Rectangle::new(0, 0, 32, 64)));
+
+        // hidden leaves can be changed as well
+        tree.hide_focused().unwrap();
+        assert_eq!(tree.replace_data(&3, 6), Some(3));
+        tree.show(&6).unwrap();
+        assert_eq!(tree.validate(), Ok(()));
+
+        tree.undo();
+        tree.undo();
+        assert_eq!(tree.hidden_leaves().count(), 1);
+        assert_eq!(tree.find(&3), None);
+        tree.undo();
+        assert_eq!(tree.find(&3), Some(Rectangle::new(32, 0, 32, 64)));
+        assert_eq!(tree.find(&4), Some(Rectangle::new(0, 0, 32, 64)));
+    }
+
+    #[test]
+    fn bs_map_data() {
+        let mut tree = BSPTree::new(Rectangle::new(0, 0, 64, 64));
+        tree.map_data(|data| data + 1);
+        assert!(tree.is_empty());
+
+        tree.insert(1).unwrap();
+        tree.insert(2).unwrap();
+        tree.insert(3).unwrap();
+        tree.hide_focused().unwrap();
+
+        let mut seen = vec![];
+        tree.for_each_leaf(|&data, rect| seen.push((data, rect)));
+        assert_eq!(seen, tree.leaves().collect::<Vec<_>>());
+        assert_eq!(seen.len(), 2);
+
+        let mut order = vec![];
+        tree.map_data(|data| {
+            order.push(data);
+            data * 10
+        });
+        assert_eq!(order, vec![1, 2, 3]);
+        assert_eq!(tree.find(&10), Some(Rectangle::new(0, 0, 32, 64)));
+        assert_eq!(tree.find(&20), Some(Rectangle::new(32, 0, 32, 64)));
+        tree.show(&30).unwrap();
+        assert_eq!(tree.validate(), Ok(()));
+    }
+
+    #[test]
+    fn bs_layout_snapshot() {
+        let mut tree = BSPTree::new(Rectangle::new(0, 0, 64, 64));
+        assert_eq!(tree.layout(), vec![]);
+
+        tree.insert(1).unwrap();
+        tree.insert(2).unwrap();
+        let layout = tree.layout();
+        assert_eq!(
+            layout,
+            vec![
+                LeafGeometry {
+                    data: 1,
+                    rect: Rectangle::new(0, 0, 32, 64),
+                    focused: false,
+                },
+                LeafGeometry {
+                    data: 2,
+                    rect: Rectangle::new(32, 0, 32, 64),
+                    focused: true,
+                },
+            ]
+        );
+
+        // the snapshot doesn't change with the tree
+        tree.delete_focused().unwrap();
+        assert_eq!(layout.len(), 2);
+        tree.insert(3).unwrap();
+        tree.toggle_fullscreen().unwrap();
+        assert_eq!(
+            tree.layout(),
+            vec![LeafGeometry {
+                data: 3,
+                rect: Rectangle::new(0, 0, 64, 64),
+                focused: true,
+            }]
+        );
+    }
+
+    #[test]
+    fn bs_relayout_dirty() {
+        let mut tree = BSPTree::new(Rectangle::new(0, 0, 64, 64));
+        tree.insert(1).unwrap();
+        tree.insert(2).unwrap();
+        tree.insert_with(3, SplitDirection::Horizontal, Side::Second)
+            .unwrap();
+        tree.focus_data(&1);
+        tree.insert_with(4, SplitDirection::Horizontal, Side::Second)
+            .unwrap();
+        assert!(!tree.needs_relayout());
+
+        let events = Arc::new(Mutex::new(vec![]));
+        let log = events.clone();
+        tree.on_change(move |e| log.lock().unwrap().push(*e));
+
+        // the container of the right half
+        let container = tree
+            .walk()
+            .into_iter()
+            .find(|&id| {
+                tree[id].get_data().is_none()
+                    && tree[id].get_rect() == Rectangle::new(32, 0, 32, 64)
+            })
+            .unwrap();
+        tree.set_ratio(container, 0.25).unwrap();
+        assert!(tree.needs_relayout());
+        assert_eq!(tree.find(&2), Some(Rectangle::new(32, 0, 32, 32)));
+
+        // only the leaves of the changed container are laid out again
+        tree.relayout_dirty();
+        assert!(!tree.needs_relayout());
+        assert_eq!(tree.find(&2), Some(Rectangle::new(32, 0, 32, 16)));
+        assert_eq!(tree.find(&3), Some(Rectangle::new(32, 16, 32, 48)));
+        let resized = events
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|e| matches!(e, TreeEvent::Resized { .. }))
+            .count();
+        assert_eq!(resized, 2);
+        assert_eq!(tree.validate(), Ok(()));
+
+        let removed = tree.get_focused().unwrap();
+        tree.delete_focused().unwrap();
+        assert_eq!(
+            tree.set_ratio(removed, 0.5),
+            Err(TreeError::InvalidNode { id: removed })
+        );
+
+        // a resize still lays out everything
+        tree.resize(Rectangle::new(0, 0, 128, 64));
+        assert_eq!(tree.find(&1), Some(Rectangle::new(0, 0, 64, 64)));
+        assert_eq!(tree.find(&3), Some(Rectangle::new(64, 16, 64, 48)));
+        assert_eq!(tree.validate(), Ok(()));
+    }
+
+    #[test]
+    fn bs_ancestors() {
+        let mut tree = BSPTree::new(Rectangle::new(0, 0, 64, 64));
+        assert_eq!(tree.ancestors_of_focused().count(), 0);
+        tree.insert(1).unwrap();
+        assert_eq!(tree.ancestors_of_focused().count(), 0);
+
+        tree.insert(2).unwrap();
+        tree.insert_with(3, SplitDirection::Horizontal, Side::Second)
+            .unwrap();
+        let ancestors: Vec<_> = tree.ancestors_of_focused().collect();
+        assert_eq!(ancestors.len(), 2);
+
+        let (parent, view) = &ancestors[0];
+        assert_eq!(view.rect, Rectangle::new(32, 0, 32, 64));
+        assert_eq!(view.split, SplitDirection::Horizontal);
+        assert_eq!(view.depth, 1);
+        assert!(!view.leaf);
+        assert_eq!(tree[*parent].get_rect(), view.rect);
+
+        let (root, view) = &ancestors[1];
+        assert_eq!(Some(*root), tree.get_root());
+        assert_eq!(view.rect, Rectangle::new(0, 0, 64, 64));
+        assert_eq!(view.split, SplitDirection::Vertical);
+        assert_eq!(view.depth, 0);
+    }
+
+    #[test]
+    fn bs_node_accessors() {
+        let mut tree = BSPTree::new(Rectangle::new(0, 0, 64, 64));
+        tree.insert(1).unwrap();
+        tree.insert(2).unwrap();
+        tree.insert_with(3, SplitDirection::Horizontal, Side::Second)
+            .unwrap();
+
+        let root = &tree[tree.get_root().unwrap()];
+        assert!(!root.is_leaf());
+        assert_eq!(root.parent(), None);
+        assert_eq!(root.split(), SplitDirection::Vertical);
+
+        let left = &tree[root.left().unwrap()];
+        assert!(left.is_leaf());
+        assert_eq!(left.get_data(), Some(&1));
+        assert_eq!((left.left(), left.right()), (None, None));
+        assert_eq!(left.parent(), tree.get_root());
+
+        let right = root.right().unwrap();
+        assert_eq!(tree[right].split(), SplitDirection::Horizontal);
+        let bottom = tree[right].right().unwrap();
+        assert_eq!(tree[bottom].get_data(), Some(&3));
+        assert_eq!(tree[bottom].parent(), Some(right));
+        assert_eq!(Some(bottom), tree.get_focused());
+    }
+
+    #[test]
+    fn bs_from_leaves() {
+        let size = Rectangle::new(0, 0, 120, 60);
+        let tree: BSPTree = BSPTree::from_leaves(size, &[], SplitPolicy::Manual);
+        assert!(tree.is_empty());
+
+        // equal columns
+        let tree = BSPTree::from_leaves(size, &[1, 2, 3, 4], SplitPolicy::Manual);
+        assert_eq!(
+            tree.leaves().collect::<Vec<_>>(),
+            vec![
+                (1, Rectangle::new(0, 0, 30, 60)),
+                (2, Rectangle::new(30, 0, 30, 60)),
+                (3, Rectangle::new(60, 0, 30, 60)),
+                (4, Rectangle::new(90, 0, 30, 60)),
+            ]
+        );
+        assert_eq!(tree[tree.get_focused().unwrap()].get_data(), Some(&1));
+        assert_eq!(tree.validate(), Ok(()));
+
+        // a grid, split along the longer sides
+        let size = Rectangle::new(0, 0, 120, 80);
+        let tree = BSPTree::from_leaves(size, &[1, 2, 3, 4], SplitPolicy::AutoSplit);
+        assert_eq!(tree.depth(), 2);
+        assert_eq!(tree.find(&2), Some(Rectangle::new(0, 40, 60, 40)));
+        assert_eq!(tree.find(&3), Some(Rectangle::new(60, 0, 60, 40)));
+        assert_eq!(tree.get_split_policy(), SplitPolicy::AutoSplit);
+        assert_eq!(tree.validate(), Ok(()));
+
+        let items: Vec<u32> = (0..1024).collect();
+        let size = Rectangle::new(0, 0, 1024, 1024);
+        let tree = BSPTree::from_leaves(size, &items, SplitPolicy::AutoSplit);
+        assert_eq!(tree.leaf_count(), 1024);
+        assert_eq!(tree.find(&1023), Some(Rectangle::new(992, 992, 32, 32)));
+        assert_eq!(tree.depth(), 10);
+        assert_eq!(tree.validate(), Ok(()));
+    }
+
+    #[test]
+    fn bs_odd_sizes() {
+        // children cover their parent exactly, whatever the size and rounding
+        for rounding in [Rounding::Floor, Rounding::Nearest, Rounding::Ceil] {
+            let items: Vec<u32> = (0..7).collect();
+            let mut tree = BSPTree::from_leaves(
+                Rectangle::new(0, 0, 101, 37),
+                &items,
+                SplitPolicy::AutoSplit,
+            );
+            tree.set_rounding(rounding);
+            assert_eq!(tree.get_rounding(), rounding);
+            assert_eq!(tree.validate(), Ok(()));
+
+            tree.insert(7).unwrap();
+            tree.resize_focused(MoveDirection::Right, 3).unwrap();
+            for size in [(0, 0, 1, 1), (3, 5, 99, 13), (0, 0, 1921, 1079)] {
+                tree.resize(Rectangle::new(size.0, size.1, size.2, size.3));
+                assert_eq!(tree.validate(), Ok(()));
+                let area: u64 = tree.leaves().map(|(_, rect)| rect.area()).sum();
+                assert_eq!(area, tree.get_size().area());
+            }
+        }
+
+        let split = SplitDirection::Vertical;
+        let rect = Rectangle::new(0, 0, 5, 1);
+        assert_eq!(
+            split.split(rect, 0.5),
+            (Rectangle::new(0, 0, 2, 1), Rectangle::new(2, 0, 3, 1))
+        );
+        assert_eq!(
+            split.split_rounded(rect, 0.5, Rounding::Nearest),
+            (Rectangle::new(0, 0, 3, 1), Rectangle::new(3, 0, 2, 1))
+        );
+        assert_eq!(
+            split.split_rounded(rect, 0.3, Rounding::Ceil),
+            (Rectangle::new(0, 0, 2, 1), Rectangle::new(2, 0, 3, 1))
+        );
+    }
+
+    #[test]
+    fn bs_border() {
+        let mut tree = BSPTree::new(Rectangle::new(0, 0, 64, 64));
+        tree.insert(1).unwrap();
+        tree.insert(2).unwrap();
+        tree.set_gaps(Gaps::new(4, 2));
+        tree.set_border_width(3);
+        let right = tree.get_focused().unwrap();
+        let left = tree.leaf_at(0, 0).unwrap();
+        assert_eq!(tree.frame(tree.get_root().unwrap()), None);
+
+        assert_eq!(
+            tree.frame(left),
+            Some(Frame {
+                outer: Rectangle::new(2, 2, 28, 60),
+                inner: Rectangle::new(5, 5, 22, 54),
+            })
+        );
+        // the node rectangles are left alone
+        assert_eq!(tree[left].get_rect(), Rectangle::new(0, 0, 32, 64));
+
+        tree.set_node_border(right, Some(1)).unwrap();
+        assert_eq!(tree[right].get_border(), Some(1));
+        assert_eq!(
+            tree.frame(right).unwrap().inner,
+            Rectangle::new(35, 3, 26, 58)
+        );
+
+        // the border follows the data
+        tree.swap_in_direction(MoveDirection::Left).unwrap();
+        assert_eq!(tree[left].get_border(), Some(1));
+        assert_eq!(tree[right].get_border(), None);
+        tree.set_node_border(left, None).unwrap();
+        assert_eq!(
+            tree.frame(left).unwrap().inner,
+            Rectangle::new(5, 5, 22, 54)
+        );
+
+        // a border wider than the leaf shrinks it as far as possible
+        tree.set_border_width(100);
+        assert_eq!(
+            tree.frame(left).unwrap().inner,
+            Rectangle::new(16, 32, 0, 0)
+        );
+        assert_eq!(
+            Rectangle::new(0, 0, 5, 4).inset(10),
+            Rectangle::new(2, 2, 1, 0)
+        );
+
+        // floating leaves keep their own rectangle, hidden ones have no frame
+        tree.set_border_width(3);
+        tree.focus_coords(0, 0);
+        tree.float_focused().unwrap();
+        assert_eq!(
+            tree.frame(left),
+            Some(Frame {
+                outer: Rectangle::new(0, 0, 32, 64),
+                inner: Rectangle::new(3, 3, 26, 58),
+            })
+        );
+        tree.hide_focused().unwrap();
+        assert!(tree.hidden_leaves().any(|id| id == right));
+        assert_eq!(tree.frame(right), None);
+    }
+
+    #[test]
+    fn bs_floating() {
+        let mut tree = BSPTree::new(Rectangle::new(0, 0, 64, 64));
+        assert_eq!(tree.float_focused(), Err(TreeError::Empty));
+        tree.insert(1).unwrap();
+        tree.insert(2).unwrap();
+        tree.insert(3).unwrap();
+        let third = tree.get_focused().unwrap();
+
+        // the sibling takes the space, the leaf keeps it's rectangle
+        tree.float_focused().unwrap();
+        assert!(tree.is_floating(third));
+        assert_eq!(tree[third].get_rect(), Rectangle::new(48, 0, 16, 64));
+        assert_eq!(tree.find(&2), Some(Rectangle::new(32, 0, 32, 64)));
+        assert_eq!(tree.leaf_count(), 2);
+        assert_ne!(tree.get_focused(), Some(third));
+        assert_eq!(tree.validate(), Ok(()));
+
+        tree.focus_data(&1);
+        let first = tree.get_focused().unwrap();
+        tree.float_focused().unwrap();
+        assert_eq!(
+            tree.floating_leaves().collect::<Vec<_>>(),
+            vec![third, first]
+        );
+        assert_eq!(
+            tree.layout().iter().map(|l| l.data).collect::<Vec<_>>(),
+            vec![2, 3, 1]
+        );
+
+        // stacking order
+        tree.raise_floating(third).unwrap();
+        assert_eq!(
+            tree.floating_leaves().collect::<Vec<_>>(),
+            vec![first, third]
+        );
+        tree.lower_floating(third).unwrap();
+        assert_eq!(
+            tree.floating_leaves().collect::<Vec<_>>(),
+            vec![third, first]
+        );
+
+        tree.set_floating_rect(third, Rectangle::new(-10, 5, 20, 20))
+            .unwrap();
+        tree.move_floating(third, 4, -5).unwrap();
+        assert_eq!(tree[third].get_rect(), Rectangle::new(-6, 0, 20, 20));
+        let root = tree.get_root().unwrap();
+        assert_eq!(
+            tree.move_floating(root, 1, 1),
+            Err(TreeError::NotFloating { id: root })
+        );
+        assert_eq!(tree.tile(root), Err(TreeError::NotFloating { id: root }));
+        assert_eq!(tree.validate(), Ok(()));
+
+        // tiling puts the leaf back like an insertion
+        tree.tile(third).unwrap();
+        assert!(!tree.is_floating(third));
+        assert_eq!(tree.get_focused(), Some(third));
+        assert_eq!(tree[third].get_rect(), Rectangle::new(32, 0, 32, 64));
+        assert_eq!(tree.floating_leaves().collect::<Vec<_>>(), vec![first]);
+        assert_eq!(tree.validate(), Ok(()));
+
+        tree.undo();
+        assert!(tree.is_floating(third));
+        assert_eq!(tree.validate(), Ok(()));
+    }
+
+    #[test]
+    fn bs_focus_memory() {
+        let mut tree = BSPTree::new(Rectangle::new(0, 0, 64, 64));
+        tree.insert(1).unwrap();
+        tree.insert(2).unwrap();
+        tree.insert_with(3, SplitDirection::Horizontal, Side::Second)
+            .unwrap();
+        tree.insert_with(4, SplitDirection::Horizontal, Side::Second)
+            .unwrap();
+        let data = |tree: &BSPTree| *tree[tree.get_focused().unwrap()].get_data().unwrap();
+
+        // 1 is on the left, 2, 3 and 4 are stacked on the right
+        tree.move_focus(MoveDirection::Left).unwrap();
+        assert_eq!(data(&tree), 1);
+        tree.move_focus(MoveDirection::Right).unwrap();
+        assert_eq!(data(&tree), 4);
+
+        tree.move_focus(MoveDirection::Up).unwrap();
+        assert_eq!(data(&tree), 3);
+        tree.move_focus(MoveDirection::Left).unwrap();
+        tree.move_focus(MoveDirection::Right).unwrap();
+        assert_eq!(data(&tree), 3);
+
+        // without a memory the leaf at the edge is chosen
+        let mut tree = BSPTree::new(Rectangle::new(0, 0, 64, 64));
+        tree.insert(1).unwrap();
+        tree.focus_nth(0);
+        tree.insert_with(2, SplitDirection::Horizontal, Side::First)
+            .unwrap();
+        tree.insert_with(3, SplitDirection::Vertical, Side::Second)
+            .unwrap();
+        tree.move_focus(MoveDirection::Left).unwrap();
+        assert_eq!(data(&tree), 2);
+        tree.move_focus(MoveDirection::Down).unwrap();
+        assert_eq!(data(&tree), 1);
+    }
+
+    #[test]
+    fn bs_insert_unfocused() {
+        let mut tree = BSPTree::new(Rectangle::new(0, 0, 64, 64));
+        let first = tree.insert_unfocused(1).unwrap();
+        assert_eq!(tree.get_focused(), Some(first));
+
+        tree.insert(2).unwrap();
+        let focused = tree.get_focused().unwrap();
+        let new = tree.insert_unfocused(3).unwrap();
+        assert_eq!(tree.get_focused(), Some(focused));
+        assert_eq!(tree[new].get_data(), Some(&3));
+        assert_eq!(tree[focused].get_rect(), Rectangle::new(32, 0, 16, 64));
+        assert_eq!(tree[new].get_rect(), Rectangle::new(48, 0, 16, 64));
+        assert_eq!(tree.node_count(), 5);
+        assert_eq!(tree.validate(), Ok(()));
+
+        tree.set_min_size(32, 32);
+        assert_eq!(tree.insert_unfocused(4), Err(TreeError::TooSmall));
+        assert_eq!(tree.get_focused(), Some(focused));
+    }
+
+    #[test]
+    fn bs_gridify() {
+        let mut tree = BSPTree::new(Rectangle::new(0, 0, 96, 64));
+        for i in 1..=6 {
+            tree.insert(i).unwrap();
+        }
+        assert_eq!(tree.gridify_focused_parent(3), Ok(()));
+        assert_eq!(tree.leaf_count(), 6);
+
+        tree.focus_data(&1);
+        let before = tree.clone();
+        tree.gridify_focused_parent(3).unwrap();
+        let rects: Vec<_> = tree.leaves().collect();
+        assert_eq!(
+            rects,
+            vec![
+                (1, Rectangle::new(0, 0, 32, 32)),
+                (2, Rectangle::new(32, 0, 32, 32)),
+                (3, Rectangle::new(64, 0, 32, 32)),
+                (4, Rectangle::new(0, 32, 32, 32)),
+                (5, Rectangle::new(32, 32, 32, 32)),
+                (6, Rectangle::new(64, 32, 32, 32)),
+            ]
+        );
+        assert_eq!(tree[tree.get_focused().unwrap()].get_data(), Some(&1));
+        assert_eq!(tree.node_count(), 11);
+        assert_eq!(tree.validate(), Ok(()));
+
+        // the first row is the container of the focused leaf now
+        tree.gridify_focused_parent(0).unwrap();
+        assert_eq!(
+            tree.leaves().nth(2),
+            Some((3, Rectangle::new(0, 21, 96, 11)))
+        );
+        assert_eq!(
+            tree.leaves().nth(3),
+            Some((4, Rectangle::new(0, 32, 32, 32)))
+        );
+        tree.set_min_size(0, 12);
+        assert_eq!(tree.gridify_focused_parent(1), Err(TreeError::TooSmall));
+
+        tree.undo();
+        tree.undo();
+        assert!(tree.structural_eq(&before));
+
+        let mut tree = BSPTree::new(Rectangle::new(0, 0, 96, 64));
+        tree.insert(1).unwrap();
+        assert_eq!(tree.gridify_focused_parent(2), Err(TreeError::NoParent));
+    }
+
+    #[test]
+    fn bs_point_queries() {
+        let mut tree = BSPTree::new(Rectangle::new(0, 0, 64, 64));
+        tree.insert(1).unwrap();
+        assert_eq!(tree.deepest_container_at(0, 0), None);
+        tree.insert(2).unwrap();
+        tree.insert_with(3, SplitDirection::Horizontal, Side::Second)
+            .unwrap();
+        let data = |id: Option<NodeId>| id.and_then(|id| tree[id].get_data().copied());
+
+        // the shared edge belongs to the right or bottom leaf only
+        assert_eq!(data(tree.leaf_at(31, 0)), Some(1));
+        assert_eq!(data(tree.leaf_at(32, 0)), Some(2));
+        assert_eq!(data(tree.leaf_at(32, 31)), Some(2));
+        assert_eq!(data(tree.leaf_at(32, 32)), Some(3));
+        assert_eq!(tree.leaf_at(64, 0), None);
+        assert_eq!(tree.leaf_at(0, -1), None);
+        assert!(!Rectangle::new(0, 0, 32, 64).is_inside(32, 0));
+
+        let root = tree.get_root();
+        let right = tree.leaf_at(32, 0).and_then(|id| tree[id].parent());
+        assert_eq!(tree.deepest_container_at(31, 63), root);
+        assert_eq!(tree.deepest_container_at(32, 63), right);
+        assert_eq!(tree.deepest_container_at(0, 64), None);
+
+        let mut tree = tree.clone();
+        tree.toggle_fullscreen().unwrap();
+        assert_eq!(tree.leaf_at(0, 0), tree.get_focused());
+        assert_eq!(tree.deepest_container_at(0, 0), root);
+    }
+
+    #[test]
+    fn bs_apply() {
+        let mut tree = BSPTree::new(Rectangle::new(0, 0, 64, 64));
+        assert_eq!(tree.apply(TreeCommand::Delete), Err(TreeError::Empty));
+        assert_eq!(tree.apply(TreeCommand::ToggleSplit), Err(TreeError::Empty));
+
+        let events = tree.apply(TreeCommand::Insert(1)).unwrap();
+        let first = tree.get_focused().unwrap();
+        let rect = Rectangle::new(0, 0, 64, 64);
+        assert_eq!(
+            events,
+            vec![
+                TreeEvent::Inserted { id: first, rect },
+                TreeEvent::FocusChanged { id: first }
+            ]
+        );
+
+        tree.apply(TreeCommand::Insert(2)).unwrap();
+        let second = tree.get_focused().unwrap();
+        let events = tree.apply(TreeCommand::Focus(MoveDirection::Left)).unwrap();
+        assert_eq!(events, vec![TreeEvent::FocusChanged { id: first }]);
+        assert_eq!(
+            tree.apply(TreeCommand::Focus(MoveDirection::Left)),
+            Err(TreeError::NoNeighbor)
+        );
+
+        let events = tree.apply(TreeCommand::Move(MoveDirection::Right)).unwrap();
+        assert_eq!(
+            events[0],
+            TreeEvent::Swapped {
+                a: first,
+                b: second
+            }
+        );
+        assert_eq!(tree[second].get_data(), Some(&1));
+
+        let events = tree
+            .apply(TreeCommand::Resize {
+                direction: MoveDirection::Left,
+                amount: 8,
+            })
+            .unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(tree[second].get_rect(), Rectangle::new(24, 0, 40, 64));
+        assert_eq!(
+            tree.apply(TreeCommand::Resize {
+                direction: MoveDirection::Right,
+                amount: 8,
+            }),
+            Err(TreeError::NoParent)
+        );
+
+        assert_eq!(tree.apply(TreeCommand::ToggleSplit), Ok(vec![]));
+        assert_eq!(tree[second].split(), SplitDirection::Horizontal);
+
+        // other callbacks keep getting the changes
+        let seen = Arc::new(Mutex::new(0));
+        let counter = seen.clone();
+        tree.on_change(move |_| *counter.lock().unwrap() += 1);
+        tree.apply(TreeCommand::Delete).unwrap();
+        let count = *seen.lock().unwrap();
+        tree.insert(3).unwrap();
+        assert!(*seen.lock().unwrap() > count);
+        assert_eq!(tree.leaf_count(), 2);
+    }
+
+    #[test]
+    fn bs_record_replay() {
+        let mut tree = BSPTree::new(Rectangle::new(0, 0, 640, 480));
+        tree.set_min_size(100, 100);
+        tree.insert(1).unwrap();
+        tree.apply(TreeCommand::Insert(0)).unwrap();
+        assert!(tree.recording().is_none());
+
+        tree.record();
+        let start = tree.clone();
+        // failed commands don't change the tree, so they are left out
+        assert_eq!(
+            tree.apply(TreeCommand::Focus(MoveDirection::Up)),
+            Err(TreeError::NoNeighbor)
+        );
+        let commands = [
+            TreeCommand::Insert(2),
+            TreeCommand::ToggleSplit,
+            TreeCommand::Insert(3),
+            TreeCommand::Focus(MoveDirection::Left),
+            TreeCommand::Resize {
+                direction: MoveDirection::Right,
+                amount: 60,
+            },
+            TreeCommand::Move(MoveDirection::Right),
+            TreeCommand::Rotate,
+            TreeCommand::Delete,
+        ];
+        for command in commands.iter() {
+            tree.apply(command.clone()).unwrap();
+        }
+        assert_eq!(tree.recording().unwrap().commands(), commands);
+
+        let log = tree.stop_recording().unwrap();
+        assert!(tree.recording().is_none());
+        assert!(log.start().structural_eq(&start));
+        assert_eq!(BSPTree::replay(&log), Ok(tree.clone()));
+
+        // a log which doesn't fit the tree it starts with
+        let empty: BSPTree = BSPTree::new(start.get_size());
+        let log = CommandLog::new(empty, vec![TreeCommand::Delete]);
+        assert_eq!(BSPTree::replay(&log), Err(TreeError::Empty));
+    }
+
+    #[test]
+    fn bs_structural_eq() {
+        let mut a = BSPTree::new(Rectangle::new(0, 0, 64, 64));
+        let mut b = BSPTree::new(Rectangle::new(0, 0, 64, 64));
+        assert!(a.structural_eq(&b));
+
+        a.insert(1).unwrap();
+        a.insert(2).unwrap();
+        assert!(!a.structural_eq(&b));
+
+        // the same layout reached in another way, with other ids and focus
+        b.insert(1).unwrap();
+        b.insert(3).unwrap();
+        b.delete_focused().unwrap();
+        b.insert(2).unwrap();
+        b.focus_data(&1);
+        assert_ne!(a, b);
+        assert!(a.structural_eq(&b));
+        assert!(b.structural_eq(&a));
+
+        b.resize_focused(MoveDirection::Right, 8).unwrap();
+        assert!(!a.structural_eq(&b));
+    }
+
+    #[test]
+    fn bs_auto_split() {
+        let mut tree = BSPTree::new(Rectangle::new(0, 0, 128, 64));
+        assert_eq!(tree.get_split_policy(), SplitPolicy::Manual);
+        tree.set_split_policy(SplitPolicy::AutoSplit);
+
+        for i in 1..=4 {
+            tree.insert(i).unwrap();
+        }
+        assert_eq!(tree.find(&1), Some(Rectangle::new(0, 0, 64, 64)));
+        // square leaves are split vertically
+        assert_eq!(tree.find(&2), Some(Rectangle::new(64, 0, 32, 64)));
+        assert_eq!(tree.find(&3), Some(Rectangle::new(96, 0, 32, 32)));
+        assert_eq!(tree.find(&4), Some(Rectangle::new(96, 32, 32, 32)));
+    }
+
+    #[test]
+    fn bs_layout_policy() {
+        let mut tree = BSPTree::new(Rectangle::new(0, 0, 128, 64));
+        tree.set_policy(Box::new(MasterStack { ratio: 0.75 }));
+
+        for i in 1..=3 {
+            tree.insert(i).unwrap();
+        }
+        tree.focus_data(&1);
+        tree.insert(4).unwrap();
+        assert_eq!(tree.find(&1), Some(Rectangle::new(0, 0, 96, 64)));
+        assert_eq!(tree.find(&2), Some(Rectangle::new(96, 0, 32, 32)));
+        assert_eq!(tree.find(&3), Some(Rectangle::new(96, 32, 32, 16)));
+        assert_eq!(tree.find(&4), Some(Rectangle::new(96, 48, 32, 16)));
+
+        let mut tree = BSPTree::new(Rectangle::new(0, 0, 64, 64));
+        tree.set_policy(Box::new(Grid));
+        for i in 1..=4 {
+            tree.insert(i).unwrap();
+        }
+        let rects: Vec<_> = tree.leaves().map(|(_, rect)| rect).collect();
+        assert!(rects.iter().all(|r| r.w == 32 && r.h == 32));
+
+        // a custom policy which always puts new leaves first
+        #[derive(Debug)]
+        struct Prepend;
+
+        impl LayoutPolicy for Prepend {
+            fn place(&self, context: &InsertContext<'_>) -> Placement {
+                Placement {
+                    target: context.leaves[0].0,
+                    split: SplitDirection::Vertical,
+                    ratio: DEFAULT_RATIO,
+                    side: Side::First,
+                }
+            }
+        }
+
+        let mut tree = BSPTree::new(Rectangle::new(0, 0, 64, 64));
+        tree.set_policy(Box::new(Prepend));
+        for i in 1..=3 {
+            tree.insert(i).unwrap();
+        }
+        let data: Vec<_> = tree.leaves().map(|(data, _)| data).collect();
+        assert_eq!(data, vec![3, 2, 1]);
+
+        tree.clear_policy();
+        tree.insert(4).unwrap();
+        assert_eq!(tree.find(&4), Some(Rectangle::new(8, 0, 8, 64)));
+    }
+
+    #[test]
+    fn bs_errors() {
+        let mut tree: BSPTree = BSPTree::new(Rectangle::new(0, 0, 64, 64));
+
+        assert_eq!(tree.delete_focused(), Err(TreeError::Empty));
+        assert_eq!(tree.move_focus(MoveDirection::Up), Err(TreeError::Empty));
+        assert_eq!(
+            tree.swap_in_direction(MoveDirection::Up),
+            Err(TreeError::Empty)
+        );
+        assert_eq!(tree.promote_focused(), Err(TreeError::Empty));
+        assert!(!tree.undo());
+
+        tree.insert(1).unwrap();
+        assert_eq!(
+            tree.move_focus(MoveDirection::Up),
+            Err(TreeError::NoNeighbor)
+        );
+        assert_eq!(tree.delete_focused(), Ok(()));
+        assert!(tree.is_empty());
+    }
+
+    #[test]
+    fn bs_generic_data() {
+        let mut tree = BSPTree::new(Rectangle::new(0, 0, 64, 64));
+
+        tree.insert(String::from("editor")).unwrap();
+        tree.insert(String::from("terminal")).unwrap();
+        tree.swap_in_direction(MoveDirection::Left).unwrap();
+
+        let left = tree.leaf_at(0, 0).unwrap();
+        assert_eq!(tree[left].get_data().map(String::as_str), Some("terminal"));
+
+        tree.delete_focused().unwrap();
+        let nodes = tree.walk();
+        assert_eq!(nodes.len(), 1);
+        assert_eq!(tree[nodes[0]].get_data().unwrap(), "editor");
+    }
+
+    #[test]
+    fn bs_resize_focused() {
+        let mut tree = BSPTree::new(Rectangle::new(0, 0, 64, 64));
+        assert_eq!(
+            tree.resize_focused(MoveDirection::Left, 16),
+            Err(TreeError::Empty)
+        );
+        assert_eq!(tree.toggle_split(), Err(TreeError::Empty));
+        assert_eq!(
+            tree.set_split(SplitDirection::Vertical),
+            Err(TreeError::Empty)
+        );
+        assert_eq!(tree.toggle_fullscreen(), Err(TreeError::Empty));
+
+        tree.insert(1).unwrap();
+        tree.insert(2).unwrap();
+
+        tree.resize_focused(MoveDirection::Left, 16).unwrap();
+        let left = tree.leaf_at(0, 0).unwrap();
+        let right = tree.leaf_at(63, 0).unwrap();
+        assert_eq!(tree[left].get_rect(), Rectangle::new(0, 0, 16, 64));
+        assert_eq!(tree[right].get_rect(), Rectangle::new(16, 0, 48, 64));
+
+        // the focused node touches the right and top edges of the tree
+        assert_eq!(
+            tree.resize_focused(MoveDirection::Right, 16),
+            Err(TreeError::NoParent)
+        );
+        assert_eq!(
+            tree.resize_focused(MoveDirection::Up, 16),
+            Err(TreeError::NoParent)
+        );
+        assert_eq!(tree[right].get_rect(), Rectangle::new(16, 0, 48, 64));
+
+        // ratios are clamped
+        tree.resize_focused(MoveDirection::Left, 64).unwrap();
+        assert_eq!(tree[right].get_rect(), Rectangle::new(3, 0, 61, 64));
+
+        // the ratio is kept when the tree is split further
+        tree.focus_coords(0, 0);
+        tree.toggle_split().unwrap();
+        tree.insert(3).unwrap();
+        assert_eq!(
+            tree[tree.leaf_at(0, 63).unwrap()].get_rect(),
+            Rectangle::new(0, 32, 3, 32)
+        );
+    }
+
+    #[test]
+    fn bs_resize() {
+        let mut tree = BSPTree::new(Rectangle::new(0, 0, 64, 64));
+        tree.resize(Rectangle::new(0, 0, 32, 32));
+        assert_eq!(tree.get_size(), Rectangle::new(0, 0, 32, 32));
+
+        tree.insert(1).unwrap();
+        tree.insert(2).unwrap();
+        tree.toggle_split().unwrap();
+        tree.insert(3).unwrap();
+
+        tree.resize(Rectangle::new(10, 10, 128, 64));
+        assert_eq!(
+            tree.leaves().collect::<Vec<_>>(),
+            vec![
+                (1, Rectangle::new(10, 10, 64, 64)),
+                (2, Rectangle::new(74, 10, 64, 32)),
+                (3, Rectangle::new(74, 42, 64, 32)),
+            ]
+        );
+
+        // states saved before the resize are laid out with the new size
+        assert!(tree.undo());
+        assert_eq!(
+            tree.leaves().collect::<Vec<_>>(),
+            vec![
+                (1, Rectangle::new(10, 10, 64, 64)),
+                (2, Rectangle::new(74, 10, 64, 64)),
+            ]
+        );
+
+        tree.toggle_fullscreen().unwrap();
+        tree.resize(Rectangle::new(0, 0, 64, 64));
+        assert_eq!(tree.find(&2), Some(Rectangle::new(0, 0, 64, 64)));
+        tree.toggle_fullscreen().unwrap();
+        assert_eq!(tree.find(&2), Some(Rectangle::new(32, 0, 32, 64)));
+    }
+
+    #[test]
+    fn bs_min_size() {
+        let mut tree = BSPTree::new(Rectangle::new(0, 0, 64, 64));
+        tree.set_min_size(16, 16);
+        assert_eq!(tree.get_min_size(), (16, 16));
+
+        tree.insert(1).unwrap();
+        tree.insert(2).unwrap();
+        tree.insert(3).unwrap();
+        assert_eq!(tree.insert(4), Err(TreeError::TooSmall));
+        assert_eq!(tree.leaves().count(), 3);
+        assert_eq!(tree.find(&3), Some(Rectangle::new(48, 0, 16, 64)));
+
+        // the failed insert is not recorded
+        assert!(tree.undo());
+        assert_eq!(tree.leaves().count(), 2);
+        assert!(tree.redo());
+
+        // the leaves right of `1` can't get any smaller
+        tree.focus_data(&1);
+        tree.resize_focused(MoveDirection::Right, 16).unwrap();
+        assert_eq!(tree.find(&1), Some(Rectangle::new(0, 0, 32, 64)));
+
+        tree.resize_focused(MoveDirection::Right, -30).unwrap();
+        assert_eq!(tree.find(&1), Some(Rectangle::new(0, 0, 16, 64)));
+    }
+
+    #[test]
+    fn bs_rotate_focused() {
+        let mut tree = BSPTree::new(Rectangle::new(0, 0, 64, 64));
+
+        assert_eq!(tree.rotate_focused(), Err(TreeError::Empty));
+        tree.insert(1).unwrap();
+        assert_eq!(tree.rotate_focused(), Err(TreeError::NoParent));
+        assert_eq!(tree.walk().len(), 1);
+
+        tree.insert(2).unwrap();
+        tree.resize_focused(MoveDirection::Left, 16).unwrap();
+        tree.rotate_focused().unwrap();
+
+        let left = tree.leaf_at(0, 0).unwrap();
+        let right = tree.leaf_at(63, 0).unwrap();
+        assert_eq!(tree[left].get_data(), Some(&2));
+        assert_eq!(tree[left].get_rect(), Rectangle::new(0, 0, 48, 64));
+        assert!(tree[left].is_focused());
+        assert_eq!(tree[right].get_data(), Some(&1));
+        assert_eq!(tree[right].get_rect(), Rectangle::new(48, 0, 16, 64));
+
+        // the focused node is now a left child, so it grows to the right
+        tree.resize_focused(MoveDirection::Right, 8).unwrap();
+        assert_eq!(tree[left].get_rect(), Rectangle::new(0, 0, 56, 64));
+    }
+
+    #[test]
+    fn bs_mirror() {
+        let mut tree = BSPTree::new(Rectangle::new(0, 0, 64, 64));
+        tree.mirror(SplitDirection::Vertical);
+
+        tree.insert(1).unwrap();
+        tree.insert(2).unwrap();
+        tree.resize_focused(MoveDirection::Left, 16).unwrap();
+        tree.toggle_split().unwrap();
+        tree.insert(3).unwrap();
+
+        tree.mirror(SplitDirection::Vertical);
+        assert_eq!(
+            tree.leaves().collect::<Vec<_>>(),
+            vec![
+                (2, Rectangle::new(0, 0, 48, 32)),
+                (3, Rectangle::new(0, 32, 48, 32)),
+                (1, Rectangle::new(48, 0, 16, 64)),
+            ]
+        );
+
+        tree.mirror(SplitDirection::Horizontal);
+        assert_eq!(tree.find(&3), Some(Rectangle::new(0, 0, 48, 32)));
+        assert_eq!(tree.find(&1), Some(Rectangle::new(48, 0, 16, 64)));
+
+        // the focused node moved with it's data
+        assert_eq!(tree[tree.leaf_at(0, 0).unwrap()].get_data(), Some(&3));
+        assert!(tree[tree.leaf_at(0, 0).unwrap()].is_focused());
+
+        assert!(tree.undo());
+        assert!(tree.undo());
+        assert_eq!(tree.find(&1), Some(Rectangle::new(0, 0, 16, 64)));
+
+        // odd lengths are swapped exactly, whichever way they are rounded
+        for rounding in [Rounding::Floor, Rounding::Nearest, Rounding::Ceil] {
+            let mut tree = BSPTree::new(Rectangle::new(0, 0, 65, 33));
+            tree.set_rounding(rounding);
+            tree.insert(1).unwrap();
+            tree.insert(2).unwrap();
+            tree.toggle_split().unwrap();
+            tree.insert(3).unwrap();
+            let before: Vec<_> = tree.leaves().map(|(_, rect)| (rect.w, rect.h)).collect();
+
+            tree.mirror(SplitDirection::Vertical);
+            tree.mirror(SplitDirection::Horizontal);
+            let after: Vec<_> = tree.leaves().map(|(_, rect)| (rect.w, rect.h)).collect();
+            assert_eq!(after.into_iter().rev().collect::<Vec<_>>(), before);
+
+            tree.mirror(SplitDirection::Vertical);
+            tree.mirror(SplitDirection::Horizontal);
+            assert_eq!(
+                tree.leaves()
+                    .map(|(_, rect)| (rect.w, rect.h))
+                    .collect::<Vec<_>>(),
+                before
+            );
+        }
+    }
+
+    #[test]
+    fn bs_on_change() {
+        let mut tree = BSPTree::new(Rectangle::new(0, 0, 64, 64));
+        let events = Arc::new(Mutex::new(vec![]));
+        let log = events.clone();
+        tree.on_change(move |e| log.lock().unwrap().push(*e));
+        let take = || std::mem::take(&mut *events.lock().unwrap());
+
+        tree.insert(1).unwrap();
+        let first = tree.get_focused().unwrap();
+        assert_eq!(
+            take(),
+            vec![
+                TreeEvent::Inserted {
+                    id: first,
+                    rect: Rectangle::new(0, 0, 64, 64)
+                },
+                TreeEvent::FocusChanged { id: first },
+            ]
+        );
+
+        tree.insert(2).unwrap();
+        let second = tree.get_focused().unwrap();
+        assert_eq!(
+            take(),
+            vec![
+                TreeEvent::Resized {
+                    id: first,
+                    rect: Rectangle::new(0, 0, 32, 64)
+                },
+                TreeEvent::Inserted {
+                    id: second,
+                    rect: Rectangle::new(32, 0, 32, 64)
+                },
+                TreeEvent::FocusChanged { id: second },
+            ]
+        );
+
+        tree.swap_in_direction(MoveDirection::Left).unwrap();
+        assert_eq!(
+            take(),
+            vec![
+                TreeEvent::Swapped {
+                    a: second,
+                    b: first
+                },
+                TreeEvent::FocusChanged { id: first },
+            ]
+        );
+
+        tree.delete_focused().unwrap();
+        assert_eq!(
+            take(),
+            vec![
+                TreeEvent::Deleted {
+                    id: first,
+                    rect: Rectangle::new(0, 0, 32, 64)
+                },
+                TreeEvent::Resized {
+                    id: second,
+                    rect: Rectangle::new(0, 0, 64, 64)
+                },
+                TreeEvent::FocusChanged { id: second },
+            ]
+        );
+
+        // undoing reports the differences
+        assert!(tree.undo());
+        assert_eq!(
+            take(),
+            vec![
+                TreeEvent::Inserted {
+                    id: first,
+                    rect: Rectangle::new(0, 0, 32, 64)
+                },
+                TreeEvent::Resized {
+                    id: second,
+                    rect: Rectangle::new(32, 0, 32, 64)
+                },
+                TreeEvent::FocusChanged { id: first },
+            ]
+        );
+
+        // floating leaves leave the layout and come back with `tile`
+        tree.float_focused().unwrap();
+        assert_eq!(
+            take(),
+            vec![
+                TreeEvent::Deleted {
+                    id: first,
+                    rect: Rectangle::new(0, 0, 32, 64)
+                },
+                TreeEvent::Resized {
+                    id: second,
+                    rect: Rectangle::new(0, 0, 64, 64)
+                },
+                TreeEvent::FocusChanged { id: second },
+            ]
+        );
+
+        tree.tile(first).unwrap();
+        assert_eq!(
+            take(),
+            vec![
+                TreeEvent::Resized {
+                    id: second,
+                    rect: Rectangle::new(0, 0, 32, 64)
+                },
+                TreeEvent::Inserted {
+                    id: first,
+                    rect: Rectangle::new(32, 0, 32, 64)
+                },
+                TreeEvent::FocusChanged { id: first },
+            ]
+        );
+
+        // removing a detached leaf doesn't report it a second time, the swap above left `first`
+        // holding 2
+        tree.float_focused().unwrap();
+        take();
+        assert_eq!(tree.remove_data(&2), Some(2));
+        assert!(take().is_empty());
+
+        tree.hide_focused().unwrap();
+        assert_eq!(
+            take(),
+            vec![TreeEvent::Deleted {
+                id: second,
+                rect: Rectangle::new(0, 0, 64, 64)
+            }]
+        );
+        assert_eq!(tree.remove_data(&1), Some(1));
+        assert!(take().is_empty());
+
+        // clones don't share the callbacks
+        let mut copy = tree.clone();
+        copy.insert(3).unwrap();
+        assert!(take().is_empty());
+    }
+
+    #[test]
+    fn bs_to_dot() {
+        let mut tree = BSPTree::new(Rectangle::new(0, 0, 64, 64));
+        tree.insert(1).unwrap();
+        tree.insert(2).unwrap();
+
+        let mut out = vec![];
+        tree.to_dot(&mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "digraph bsptree {
+    node [shape=box];
+    n1 [label=\"Vertical 0.5\\n(0, 0); (64, 64)\"];
+    n1 -> n0;
+    n1 -> n2;
+    n0 [label=\"1\\n(0, 0); (32, 64)\"];
+    n2 [label=\"2\\n(32, 0); (32, 64)\", style=bold];
+}
+"
+        );
+
+        // quotes in the data are escaped
+        let mut tree = BSPTree::new(Rectangle::new(0, 0, 64, 64));
+        tree.insert(String::from("term")).unwrap();
+
+        let mut out = vec![];
+        tree.to_dot(&mut out).unwrap();
+        assert!(String::from_utf8(out)
+            .unwrap()
+            .contains(r#"n0 [label="\"term\"\n(0, 0); (64, 64)", style=bold];"#));
+    }
+
+    #[test]
+    fn bs_layout_round_trip() {
+        let mut tree = BSPTree::new(Rectangle::new(0, 0, 64, 64));
+        assert!(tree.to_layout().is_none());
+
+        tree.insert(1).unwrap();
+        tree.insert(2).unwrap();
+        tree.toggle_split().unwrap();
+        tree.insert(3).unwrap();
+        tree.resize_focused(MoveDirection::Up, 16).unwrap();
+        tree.focus_coords(40, 0);
+
+        let layout = tree.to_layout().unwrap();
+        let restored = BSPTree::from_layout(layout.clone(), Rectangle::new(0, 0, 128, 128));
+
+        assert_eq!(restored.to_layout().unwrap(), layout);
+
+        let top_right = restored.leaf_at(127, 0).unwrap();
+        assert_eq!(restored[top_right].get_data(), Some(&2));
+        assert_eq!(
+            restored[top_right].get_rect(),
+            Rectangle::new(64, 0, 64, 32)
+        );
+        assert!(restored[top_right].is_focused());
+
+        let bottom_right = restored.leaf_at(127, 127).unwrap();
+        assert_eq!(
+            restored[bottom_right].get_rect(),
+            Rectangle::new(64, 32, 64, 96)
+        );
+    }
+
+    #[test]
+    fn bs_leaves() {
+        let mut tree = BSPTree::new(Rectangle::new(0, 0, 64, 64));
+        assert_eq!(tree.leaves().count(), 0);
+
+        tree.insert(1).unwrap();
+        tree.insert(2).unwrap();
+        tree.toggle_split().unwrap();
+        tree.insert(3).unwrap();
+
+        let leaves: Vec<_> = tree.leaves().collect();
+        assert_eq!(
+            leaves,
+            vec![
+                (1, Rectangle::new(0, 0, 32, 64)),
+                (2, Rectangle::new(32, 0, 32, 32)),
+                (3, Rectangle::new(32, 32, 32, 32)),
+            ]
+        );
+
+        let nodes: Vec<_> = tree.iter().collect();
+        assert_eq!(nodes.len(), 5);
+        assert_eq!(
+            nodes.iter().map(|n| n.depth).collect::<Vec<_>>(),
+            vec![0, 1, 1, 2, 2]
+        );
+        assert!(!nodes[0].leaf);
+        assert_eq!(nodes[0].data, None);
+        assert!(nodes[4].focused);
+    }
+
+    #[test]
+    fn bs_iter_bfs() {
+        let mut tree = BSPTree::new(Rectangle::new(0, 0, 64, 64));
+        assert_eq!(tree.iter_bfs().count(), 0);
+
+        tree.insert(1).unwrap();
+        tree.insert(2).unwrap();
+        tree.focus_coords(0, 0);
+        tree.insert(3).unwrap();
+        tree.insert(4).unwrap();
+
+        let nodes: Vec<_> = tree.iter_bfs().map(|n| (n.depth, n.data)).collect();
+        assert_eq!(
+            nodes,
+            vec![
+                (0, None),
+                (1, None),
+                (1, Some(2)),
+                (2, Some(1)),
+                (2, None),
+                (3, Some(3)),
+                (3, Some(4)),
+            ]
+        );
+    }
+
+    #[test]
+    fn rect_geometry() {
+        let a = Rectangle::new(0, 0, 32, 32);
+        let b = Rectangle::new(16, 8, 32, 32);
+        let c = Rectangle::new(32, 0, 16, 16);
+
+        assert_eq!(a.area(), 1024);
+        assert_eq!(Rectangle::new(0, 0, 0, 10).area(), 0);
+        assert_eq!(b.center(), (32, 24));
+
+        assert!(a.intersects(&b));
+        assert_eq!(a.intersection(&b), Some(Rectangle::new(16, 8, 16, 24)));
+        // touching edges don't count
+        assert!(!a.intersects(&c));
+        assert_eq!(a.intersection(&c), None);
+
+        assert_eq!(a.union(&c), Rectangle::new(0, 0, 48, 32));
+        assert_eq!(a.union(&b), Rectangle::new(0, 0, 48, 40));
+
+        assert!(a.contains_rect(&a));
+        assert!(a.contains_rect(&Rectangle::new(8, 8, 24, 24)));
+        assert!(!a.contains_rect(&b));
+    }
+
+    #[test]
+    fn bs_negative_coordinates() {
+        // a screen to the left of the primary one
+        let mut tree = BSPTree::new(Rectangle::new(-64, -16, 64, 64));
+
+        tree.insert(1).unwrap();
+        tree.insert(2).unwrap();
+        assert_eq!(
+            tree.leaves().collect::<Vec<_>>(),
+            vec![
+                (1, Rectangle::new(-64, -16, 32, 64)),
+                (2, Rectangle::new(-32, -16, 32, 64)),
+            ]
+        );
+        assert_eq!(tree[tree.leaf_at(-10, -10).unwrap()].get_data(), Some(&2));
+
+        tree.move_focus(MoveDirection::Left).unwrap();
+        assert_eq!(tree[tree.get_focused().unwrap()].get_data(), Some(&1));
+
+        tree.set_gaps(Gaps::new(4, 2));
+        assert_eq!(
+            tree.leaf_rects_with_gaps()[0],
+            (1, Rectangle::new(-62, -14, 28, 60))
+        );
+    }
+
+    #[test]
+    fn bs_gaps() {
+        let mut tree = BSPTree::new(Rectangle::new(0, 0, 64, 64));
+
+        tree.insert(1).unwrap();
+        tree.insert(2).unwrap();
+        tree.toggle_split().unwrap();
+        tree.insert(3).unwrap();
+        assert_eq!(tree.gaps(), Gaps::default());
+
+        tree.set_gaps(Gaps::new(4, 2));
+        assert_eq!(
+            tree.leaf_rects_with_gaps(),
+            vec![
+                (1, Rectangle::new(2, 2, 28, 60)),
+                (2, Rectangle::new(34, 2, 28, 28)),
+                (3, Rectangle::new(34, 34, 28, 28)),
+            ]
+        );
+
+        // the node rectangles themselves are left untouched
+        assert_eq!(
+            tree.leaves().next(),
+            Some((1, Rectangle::new(0, 0, 32, 64)))
+        );
+    }
+
+    #[test]
+    fn bs_gaps_larger_than_leaf() {
+        let gaps = Gaps::new(0, 40);
+        let bounds = Rectangle::new(0, 0, 64, 64);
+
+        assert_eq!(
+            gaps.apply(Rectangle::new(0, 0, 32, 64), bounds),
+            Rectangle::new(32, 40, 0, 0)
+        );
+    }
+
+    #[test]
+    fn bs_node_ids() {
+        let mut tree = BSPTree::new(Rectangle::new(0, 0, 64, 64));
+        assert!(tree.get_root().is_none());
+
+        tree.insert(1).unwrap();
+        let first = tree.get_focused().unwrap();
+        tree.insert(2).unwrap();
+        let second = tree.get_focused().unwrap();
+
+        // the split leaf keeps it's id
+        assert_eq!(tree.leaf_at(0, 0), Some(first));
+        assert_eq!(tree[first].get_rect(), Rectangle::new(0, 0, 32, 64));
+        assert_eq!(tree.get_root(), tree.walk().first().copied());
+
+        tree.delete_focused().unwrap();
+        assert!(tree.node(second).is_none());
+        assert_eq!(tree.get_root(), Some(first));
+        assert_eq!(tree.get_focused(), Some(first));
+        assert_eq!(tree[first].get_rect(), Rectangle::new(0, 0, 64, 64));
+
+        // freed slots are reused
+        tree.insert(3).unwrap();
+        assert!(tree.node(second).is_some());
+    }
+
+    #[test]
+    fn bs_send() {
+        fn assert_send<T: Send>(_: &T) {}
 
-        tree.print(0);
+        let tree: BSPTree = BSPTree::new(Rectangle::new(0, 0, 64, 64));
+        assert_send(&tree);
     }
 }
@@ -0,0 +1,174 @@
+#[cfg(test)]
+mod test {
+    use crate::data_structures::bsptree::*;
+
+    use proptest::prelude::*;
+
+    #[derive(Clone, Debug)]
+    enum Op {
+        Insert,
+        InsertWith(SplitDirection, Side),
+        Delete,
+        Move(MoveDirection),
+        ToggleSplit,
+        Resize(MoveDirection, i32),
+        Rotate,
+        Promote,
+        Fullscreen,
+        Hide,
+        Show,
+        Float,
+        Tile,
+        Undo,
+        Redo,
+    }
+
+    fn direction() -> impl Strategy<Value = MoveDirection> {
+        prop_oneof![
+            Just(MoveDirection::Left),
+            Just(MoveDirection::Right),
+            Just(MoveDirection::Up),
+            Just(MoveDirection::Down),
+        ]
+    }
+
+    fn op() -> impl Strategy<Value = Op> {
+        let split = prop_oneof![
+            Just(SplitDirection::Horizontal),
+            Just(SplitDirection::Vertical)
+        ];
+        let side = prop_oneof![Just(Side::First), Just(Side::Second)];
+
+        prop_oneof![
+            4 => Just(Op::Insert),
+            2 => (split, side).prop_map(|(split, side)| Op::InsertWith(split, side)),
+            3 => Just(Op::Delete),
+            3 => direction().prop_map(Op::Move),
+            1 => Just(Op::ToggleSplit),
+            2 => (direction(), -200..200).prop_map(|(d, amount)| Op::Resize(d, amount)),
+            1 => Just(Op::Rotate),
+            1 => Just(Op::Promote),
+            1 => Just(Op::Fullscreen),
+            1 => Just(Op::Hide),
+            1 => Just(Op::Show),
+            1 => Just(Op::Float),
+            1 => Just(Op::Tile),
+            1 => Just(Op::Undo),
+            1 => Just(Op::Redo),
+        ]
+    }
+
+    /// Apply `op`, ignoring whether it succeeded, as the tree has to stay valid either way.
+    fn apply(tree: &mut BSPTree, op: &Op, next: &mut u32) {
+        match *op {
+            Op::Insert => {
+                *next += 1;
+                let _ = tree.insert(*next);
+            }
+            Op::InsertWith(split, side) => {
+                *next += 1;
+                let _ = tree.insert_with(*next, split, side);
+            }
+            Op::Delete => {
+                let _ = tree.delete_focused();
+            }
+            Op::Move(direction) => {
+                let _ = tree.move_focus(direction);
+            }
+            Op::ToggleSplit => {
+                let _ = tree.toggle_split();
+            }
+            Op::Resize(direction, amount) => {
+                let _ = tree.resize_focused(direction, amount);
+            }
+            Op::Rotate => {
+                let _ = tree.rotate_focused();
+            }
+            Op::Promote => {
+                let _ = tree.promote_focused();
+            }
+            Op::Fullscreen => {
+                let _ = tree.toggle_fullscreen();
+            }
+            Op::Hide => {
+                let _ = tree.hide_focused();
+            }
+            Op::Show => {
+                let hidden = tree.hidden_leaves().next();
+                if let Some(data) = hidden.and_then(|id| tree[id].get_data().copied()) {
+                    let _ = tree.show(&data);
+                }
+            }
+            Op::Float => {
+                let _ = tree.float_focused();
+            }
+            Op::Tile => {
+                let floating = tree.floating_leaves().next();
+                if let Some(id) = floating {
+                    let _ = tree.tile(id);
+                }
+            }
+            Op::Undo => {
+                tree.undo();
+            }
+            Op::Redo => {
+                tree.redo();
+            }
+        }
+    }
+
+    /// Check the invariants of `tree` which `validate` doesn't cover: the leaves don't overlap
+    /// and cover the whole area together.
+    fn check(tree: &BSPTree) -> Result<(), TestCaseError> {
+        prop_assert_eq!(tree.validate(), Ok(()));
+        if tree.get_fullscreen().is_some() || tree.is_empty() {
+            return Ok(());
+        }
+
+        let rects: Vec<_> = tree.leaves().map(|(_, rect)| rect).collect();
+        for (i, a) in rects.iter().enumerate() {
+            prop_assert!(
+                tree.get_area().contains_rect(a),
+                "{} is outside of the tree",
+                a
+            );
+            for b in &rects[i + 1..] {
+                prop_assert!(!a.intersects(b), "{} overlaps {}", a, b);
+            }
+        }
+        let area: u64 = rects.iter().map(Rectangle::area).sum();
+        prop_assert_eq!(area, tree.get_area().area());
+        Ok(())
+    }
+
+    proptest! {
+        #[test]
+        fn bs_prop_invariants(ops in prop::collection::vec(op(), 1..100)) {
+            let mut tree = BSPTree::new(Rectangle::new(0, 0, 640, 480));
+            tree.set_min_size(8, 8);
+            let mut next = 0;
+
+            for op in &ops {
+                apply(&mut tree, op, &mut next);
+                check(&tree)?;
+            }
+        }
+
+        #[test]
+        fn bs_prop_delete_all(count in 1..64u32, moves in prop::collection::vec(direction(), 0..32)) {
+            let mut tree = BSPTree::new(Rectangle::new(0, 0, 1920, 1080));
+            for i in 0..count {
+                let _ = tree.insert(i);
+            }
+            for direction in moves {
+                let _ = tree.move_focus(direction);
+            }
+
+            while !tree.is_empty() {
+                prop_assert_eq!(tree.delete_focused(), Ok(()));
+                check(&tree)?;
+            }
+            prop_assert_eq!(tree.delete_focused(), Err(TreeError::Empty));
+        }
+    }
+}
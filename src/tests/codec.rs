@@ -0,0 +1,104 @@
+#[cfg(test)]
+mod test {
+    use crate::data_structures::bsptree::*;
+
+    use std::io;
+
+    #[test]
+    fn codec_roundtrip() {
+        let mut tree = BSPTree::new(Rectangle::new(-1920, 0, 1920, 1080));
+        tree.insert(1).unwrap();
+        tree.insert(300).unwrap();
+        tree.insert_with(u32::MAX, SplitDirection::Horizontal, Side::First)
+            .unwrap();
+        tree.resize_focused(MoveDirection::Down, 100).unwrap();
+        tree.focus_data(&1);
+
+        let mut bytes = vec![];
+        tree.encode(&mut bytes).unwrap();
+        let decoded = BSPTree::decode(&mut bytes.as_slice()).unwrap();
+        assert!(decoded.structural_eq(&tree));
+        assert_eq!(decoded.get_size(), tree.get_size());
+        assert_eq!(decoded[decoded.get_focused().unwrap()].get_data(), Some(&1));
+        assert_eq!(decoded.validate(), Ok(()));
+
+        let empty: BSPTree = BSPTree::new(Rectangle::new(0, 0, 64, 64));
+        let mut bytes = vec![];
+        empty.encode(&mut bytes).unwrap();
+        assert_eq!(bytes.len(), 10);
+        assert_eq!(BSPTree::decode(&mut bytes.as_slice()).unwrap(), empty);
+    }
+
+    #[test]
+    fn codec_invalid() {
+        let mut tree = BSPTree::new(Rectangle::new(0, 0, 64, 64));
+        tree.insert(1).unwrap();
+        tree.insert(2).unwrap();
+        let mut bytes = vec![];
+        tree.encode(&mut bytes).unwrap();
+
+        let error = BSPTree::decode(&mut &bytes[..bytes.len() - 1]).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+
+        let mut wrong = bytes.clone();
+        wrong[0] = b'X';
+        let error = BSPTree::decode(&mut wrong.as_slice()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        // a leaf where the container should be
+        let mut wrong = bytes.clone();
+        wrong[10] |= 0b001;
+        let error = BSPTree::decode(&mut wrong.as_slice()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        // a ratio outside of (0, 1) is rejected, a tiny one is clamped
+        let mut wrong = bytes.clone();
+        wrong[11..15].copy_from_slice(&1.5f32.to_le_bytes());
+        let error = BSPTree::decode(&mut wrong.as_slice()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        let mut tiny = bytes.clone();
+        tiny[11..15].copy_from_slice(&0.001f32.to_le_bytes());
+        let decoded = BSPTree::decode(&mut tiny.as_slice()).unwrap();
+        assert_eq!(decoded.validate(), Ok(()));
+        assert_eq!(decoded.find(&1), Some(Rectangle::new(0, 0, 3, 64)));
+        assert_eq!(decoded.find(&2), Some(Rectangle::new(3, 0, 61, 64)));
+    }
+
+    #[test]
+    fn codec_command_log() {
+        let mut tree = BSPTree::new(Rectangle::new(0, 0, 640, 480));
+        tree.insert(1).unwrap();
+        tree.record();
+        for command in [
+            TreeCommand::Insert(70000),
+            TreeCommand::Focus(MoveDirection::Left),
+            TreeCommand::Resize {
+                direction: MoveDirection::Right,
+                amount: -40,
+            },
+            TreeCommand::Move(MoveDirection::Right),
+            TreeCommand::ToggleSplit,
+            TreeCommand::ToggleFullscreen,
+            TreeCommand::Promote,
+        ] {
+            let _ = tree.apply(command);
+        }
+        let log = tree.stop_recording().unwrap();
+
+        let mut bytes = vec![];
+        log.encode(&mut bytes).unwrap();
+        let decoded = CommandLog::decode(&mut bytes.as_slice()).unwrap();
+        assert_eq!(decoded.commands(), log.commands());
+        assert!(BSPTree::replay(&decoded)
+            .unwrap()
+            .structural_eq(&BSPTree::replay(&log).unwrap()));
+
+        let error = CommandLog::decode(&mut &bytes[..bytes.len() - 1]).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+        let mut wrong = bytes.clone();
+        *wrong.last_mut().unwrap() = 9;
+        let error = CommandLog::decode(&mut wrong.as_slice()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -1,6 +1,9 @@
 #[cfg(test)]
 mod tests {
-    use crate::data_structures::gap_buffer::GapBuffer;
+    use crate::data_structures::gap_buffer::{
+        GapBuffer, Growth, RegisterId, GROW_BY, KILL_RING_LEN,
+    };
+    use std::borrow::Cow;
 
     #[test]
     fn gap_buffer_show() {
@@ -30,6 +33,29 @@ mod tests {
         assert_eq!(gb.to_string(), "hello world");
     }
 
+    #[test]
+    fn gb_insert_char() {
+        let mut gb = GapBuffer::default();
+
+        gb.insert_char('é');
+        gb.insert_char('漢');
+        gb.insert_char('🦀');
+        gb.insert_char('!');
+
+        assert_eq!(gb.gap().0, 10);
+        assert_eq!(&gb.buffer()[0..10], "é漢🦀!".as_bytes());
+        assert_eq!(gb.to_string(), "é漢🦀!");
+
+        // the characters keep all of their bytes when the buffer grows
+        gb.left_by(1);
+        for _ in 0..10 {
+            gb.insert_char('🦀');
+        }
+
+        assert_eq!(gb.capacity, 64);
+        assert_eq!(gb.to_string(), format!("é漢🦀{}!", "🦀".repeat(10)));
+    }
+
     #[test]
     fn gb_insert_many() {
         let mut gb = GapBuffer::default();
@@ -193,4 +219,709 @@ mod tests {
         assert_eq!(gb.to_string(), "hahahahahahah");
         assert_eq!(gb.capacity, 64);
     }
+
+    #[test]
+    fn gb_delete_returns() {
+        let mut gb = GapBuffer::default();
+
+        gb.insert_str("hello wörld");
+        gb.left_by(6);
+
+        assert_eq!(gb.delete_left(2), b"o ");
+        assert_eq!(gb.delete_right(3), "wö".as_bytes());
+        assert_eq!(gb.to_string(), "hellrld");
+
+        assert_eq!(gb.delete_left(10), b"hell");
+        assert_eq!(gb.delete_right(10), b"rld");
+        assert_eq!(gb.delete_right(1), b"");
+        assert_eq!(gb.to_string(), "");
+    }
+
+    #[test]
+    fn gb_ranges() {
+        let mut gb = GapBuffer::default();
+
+        gb.insert_str("hello wonderful world");
+        gb.left_by(3);
+
+        assert_eq!(gb.delete_range(5..15), b" wonderful");
+        assert_eq!(gb.to_string(), "hello world");
+        assert_eq!(gb.gap().0, 5);
+
+        assert_eq!(gb.replace_range(0..5, "goodbye, cruel"), b"hello");
+        assert_eq!(gb.to_string(), "goodbye, cruel world");
+        assert_eq!(gb.gap().0, 14);
+
+        // ranges past the end are cut off
+        assert_eq!(gb.replace_range(15..40, "day"), b"world");
+        assert_eq!(gb.delete_range(30..40), b"");
+        assert_eq!(gb.to_string(), "goodbye, cruel day");
+    }
+
+    #[test]
+    fn gb_insert_at() {
+        let mut gb = GapBuffer::default();
+
+        gb.insert_str("hello world");
+        gb.insert_at(5, b",");
+        assert_eq!(gb.to_string(), "hello, world");
+        assert_eq!(gb.gap().0, 6);
+
+        gb.insert_at(0, b">> ");
+        gb.insert_at(100, b"!");
+        gb.insert_at(9, b" big wide");
+        assert_eq!(gb.to_string(), ">> hello, big wide world!");
+        assert_eq!(gb.gap().0, 18);
+    }
+
+    #[test]
+    fn gb_seek() {
+        let mut gb = GapBuffer::default();
+
+        gb.insert_str("hello world");
+        assert_eq!(gb.cursor(), 11);
+
+        gb.seek(6);
+        assert_eq!(gb.cursor(), 6);
+        gb.insert_str("big ");
+        assert_eq!(gb.to_string(), "hello big world");
+
+        gb.seek(0);
+        assert_eq!(gb.cursor(), 0);
+        assert_eq!(gb.as_slices(), (&b""[..], &b"hello big world"[..]));
+
+        gb.seek(100);
+        assert_eq!(gb.cursor(), 15);
+        assert_eq!(gb.to_string(), "hello big world");
+    }
+
+    #[test]
+    fn gb_len() {
+        let mut gb = GapBuffer::default();
+
+        assert!(gb.is_empty());
+        assert_eq!(gb.len(), 0);
+        assert_eq!(gb.gap_len(), 32);
+
+        gb.insert_str("hello wörld");
+        gb.left_by(4);
+        assert!(!gb.is_empty());
+        assert_eq!(gb.len(), 12);
+        assert_eq!(gb.gap_len(), 20);
+
+        gb.insert_str(" welcome to another day");
+        assert_eq!(gb.len(), 35);
+        assert_eq!(gb.len() + gb.gap_len(), gb.capacity);
+
+        gb.delete_left(100);
+        gb.delete_right(100);
+        assert!(gb.is_empty());
+    }
+
+    #[test]
+    fn gb_str_slices() {
+        let mut gb = GapBuffer::default();
+
+        gb.insert_str("hello wörld");
+        gb.seek(6);
+        assert_eq!(gb.as_slices(), (&b"hello "[..], "wörld".as_bytes()));
+        assert_eq!(gb.as_str_slices(), Ok(("hello ", "wörld")));
+
+        // the gap splits the 'ö'
+        gb.seek(8);
+        assert!(gb.as_str_slices().is_err());
+    }
+
+    #[test]
+    fn gb_display() {
+        let mut gb = GapBuffer::default();
+
+        gb.insert_str("hello wörld");
+        gb.seek(8);
+        assert_eq!(format!("[{gb}]"), "[hello wörld]");
+        assert_eq!(gb.to_string_lossy(), "hello wörld");
+        assert_eq!(gb.bytes().collect::<Vec<_>>(), "hello wörld".as_bytes());
+
+        gb.insert(&[0xff]);
+        assert_eq!(gb.to_string(), "hello w\u{fffd}\u{fffd}\u{fffd}rld");
+        assert_eq!(gb.to_string_lossy(), gb.to_string());
+        assert_eq!(gb.bytes().count(), 13);
+    }
+
+    #[test]
+    fn gb_index_slice() {
+        let mut gb = GapBuffer::default();
+
+        gb.insert_str("hello world");
+        gb.seek(5);
+        assert_eq!(gb[0], b'h');
+        assert_eq!(gb[4], b'o');
+        assert_eq!(gb[5], b' ');
+        assert_eq!(gb[10], b'd');
+
+        assert!(matches!(gb.slice(0..5), Cow::Borrowed(b"hello")));
+        assert!(matches!(gb.slice(6..11), Cow::Borrowed(b"world")));
+        assert!(matches!(gb.slice(3..8), Cow::Owned(_)));
+        assert_eq!(gb.slice(3..8), &b"lo wo"[..]);
+        assert_eq!(gb.slice(8..100), &b"rld"[..]);
+        assert_eq!(gb.slice(20..30), &b""[..]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn gb_index_out_of_bounds() {
+        let mut gb = GapBuffer::default();
+
+        gb.insert_str("hello");
+        let _ = gb[5];
+    }
+
+    #[test]
+    fn gb_chars_lines() {
+        let mut gb = GapBuffer::default();
+
+        gb.insert_str("hö\r\nwörld\n\nend\n");
+        gb.seek(2);
+        assert_eq!(gb.chars().collect::<String>(), "hö\r\nwörld\n\nend\n");
+        assert_eq!(gb.lines().collect::<Vec<_>>(), ["hö", "wörld", "", "end"]);
+
+        // the gap splits the 'ö' of "wörld"
+        gb.seek(7);
+        assert_eq!(gb.chars().nth(5), Some('ö'));
+        assert!(matches!(gb.lines().nth(1), Some(Cow::Owned(_))));
+        assert!(matches!(gb.lines().nth(3), Some(Cow::Borrowed("end"))));
+        assert_eq!(gb.lines().nth(1).unwrap(), "wörld");
+
+        gb.insert(&[0xff]);
+        assert_eq!(gb.chars().filter(|&c| c == '\u{fffd}').count(), 3);
+        assert_eq!(gb.chars().collect::<String>(), gb.to_string_lossy());
+        assert_eq!(GapBuffer::default().lines().next(), None);
+    }
+
+    #[test]
+    fn gb_line_col() {
+        let mut gb = GapBuffer::default();
+
+        assert_eq!(gb.line_count(), 1);
+        assert_eq!(gb.position_of(0), (0, 0));
+        gb.insert_str("first\nsecond\n\nlast\n");
+        gb.seek(8);
+        assert_eq!(gb.line_count(), 5);
+
+        assert_eq!(gb.offset_of(0, 0), Some(0));
+        assert_eq!(gb.offset_of(1, 2), Some(8));
+        assert_eq!(gb.offset_of(1, 6), Some(12));
+        assert_eq!(gb.offset_of(1, 7), None);
+        assert_eq!(gb.offset_of(2, 0), Some(13));
+        assert_eq!(gb.offset_of(4, 0), Some(19));
+        assert_eq!(gb.offset_of(5, 0), None);
+
+        assert_eq!(gb.position_of(5), (0, 5));
+        assert_eq!(gb.position_of(6), (1, 0));
+        assert_eq!(gb.position_of(8), (1, 2));
+        assert_eq!(gb.position_of(100), (4, 0));
+
+        assert!(gb.goto_line(3));
+        assert_eq!(gb.cursor(), 14);
+        assert!(!gb.goto_line(5));
+        assert_eq!(gb.cursor(), 14);
+        assert!(gb.goto_line(0));
+        assert_eq!(gb.cursor(), 0);
+    }
+
+    #[test]
+    fn gb_find() {
+        let mut gb = GapBuffer::default();
+
+        gb.insert_str("abcabcab abc");
+        // the gap splits the second "abc"
+        gb.seek(4);
+        assert_eq!(gb.find("abc", 0), Some(0));
+        assert_eq!(gb.find("abc", 1), Some(3));
+        assert_eq!(gb.find("cab", 3), Some(5));
+        assert_eq!(gb.find("abc", 10), None);
+        assert_eq!(gb.find("xyz", 0), None);
+        assert_eq!(gb.find("", 12), Some(12));
+        assert_eq!(gb.find("", 13), None);
+
+        assert_eq!(gb.rfind("abc", 100), Some(9));
+        assert_eq!(gb.rfind("abc", 11), Some(3));
+        assert_eq!(gb.rfind("abc", 5), Some(0));
+        assert_eq!(gb.rfind("abc", 2), None);
+
+        assert_eq!(gb.find_all("abc").collect::<Vec<_>>(), [0, 3, 9]);
+        assert_eq!(gb.find_all("ab").collect::<Vec<_>>(), [0, 3, 6, 9]);
+        assert_eq!(gb.find_all("").count(), 13);
+    }
+
+    #[test]
+    #[cfg(feature = "regex")]
+    fn gb_regex() {
+        use regex::bytes::Regex;
+
+        let mut gb = GapBuffer::default();
+        gb.insert_str("let x = 10; let yy = 200;");
+        // the gap splits the first number
+        gb.seek(9);
+
+        let number = Regex::new(r"\d+").unwrap();
+        assert_eq!(gb.find_regex(&number, 0), Some(8..10));
+        assert_eq!(gb.find_regex(&number, 10), Some(21..24));
+        assert_eq!(gb.find_regex(&number, 24), None);
+        assert_eq!(gb.find_regex(&number, 100), None);
+
+        let binding = Regex::new(r"let (?<name>\w+) = (\d+)").unwrap();
+        assert_eq!(gb.replace_regex(&binding, "const $name: i32 = $2"), 2);
+        assert_eq!(gb.to_string(), "const x: i32 = 10; const yy: i32 = 200;");
+        assert_eq!(gb.cursor(), 17);
+        assert_eq!(gb.replace_regex(&Regex::new("let").unwrap(), ""), 0);
+        assert_eq!(gb.cursor(), 17);
+    }
+
+    #[test]
+    fn gb_undo_redo() {
+        let mut gb = GapBuffer::default();
+
+        assert!(!gb.undo());
+        gb.insert_str("hello");
+        gb.insert_char(' ');
+        gb.insert_str("world");
+        gb.seek(0);
+        gb.delete_right(6);
+        assert_eq!(gb.to_string(), "world");
+
+        assert!(gb.undo());
+        assert_eq!(gb.to_string(), "hello world");
+        assert_eq!(gb.cursor(), 6);
+        assert!(gb.undo());
+        assert_eq!(gb.to_string(), "hello ");
+        assert!(gb.redo());
+        assert!(gb.redo());
+        assert_eq!(gb.to_string(), "world");
+        assert!(!gb.redo());
+
+        gb.undo();
+        gb.undo();
+        // a new edit discards what could be redone
+        gb.insert_byte(b'!');
+        assert_eq!(gb.to_string(), "hello !");
+        assert!(!gb.redo());
+        assert!(gb.undo());
+        assert!(gb.undo());
+        assert!(gb.undo());
+        assert_eq!(gb.to_string(), "");
+        assert!(!gb.undo());
+    }
+
+    #[test]
+    fn gb_transaction() {
+        let mut gb = GapBuffer::default();
+
+        gb.insert_str("one two three");
+        gb.begin_transaction();
+        gb.seek(0);
+        gb.delete_right(4);
+        gb.begin_transaction();
+        gb.seek(usize::MAX);
+        gb.insert_str(" four");
+        gb.commit();
+        gb.replace_range(0..3, "TWO");
+        gb.commit();
+        assert_eq!(gb.to_string(), "TWO three four");
+
+        assert!(gb.undo());
+        assert_eq!(gb.to_string(), "one two three");
+        assert!(gb.redo());
+        assert_eq!(gb.to_string(), "TWO three four");
+
+        gb.replace_range(4..9, "3");
+        assert_eq!(gb.to_string(), "TWO 3 four");
+        gb.undo();
+        assert_eq!(gb.to_string(), "TWO three four");
+
+        // undo commits the open transaction
+        gb.begin_transaction();
+        gb.delete_left(5);
+        gb.delete_left(6);
+        assert!(gb.undo());
+        assert_eq!(gb.to_string(), "TWO three four");
+        gb.commit();
+        assert!(gb.undo());
+        assert_eq!(gb.to_string(), "one two three");
+    }
+
+    #[test]
+    fn gb_shrink() {
+        let mut gb = GapBuffer::default();
+
+        gb.insert_str("hello world, hello world, hello world");
+        gb.seek(5);
+        gb.delete_right(20);
+        assert_eq!(gb.gap_len(), 47);
+
+        gb.compact(100);
+        assert_eq!(gb.capacity, 64);
+        gb.compact(8);
+        assert_eq!(gb.gap_len(), 8);
+        assert_eq!(gb.capacity, 25);
+        assert_eq!(gb.to_string(), "hello hello world");
+        assert_eq!(gb.cursor(), 5);
+
+        gb.shrink_to_fit();
+        assert_eq!(gb.gap_len(), 1);
+        assert_eq!(gb.capacity, 18);
+        assert_eq!(gb.buffer().len(), 18);
+        assert_eq!(gb.to_string(), "hello hello world");
+
+        // it still grows as needed
+        gb.insert_str(" world");
+        assert_eq!(gb.to_string(), "hello world hello world");
+        gb.seek(usize::MAX);
+        gb.compact(0);
+        assert_eq!(gb.gap_len(), 1);
+        gb.insert_char('!');
+        assert_eq!(gb.to_string(), "hello world hello world!");
+        gb.seek(5);
+        gb.compact(0);
+        gb.insert_byte(b',');
+        gb.insert_byte(b'!');
+        assert_eq!(gb.to_string(), "hello,! world hello world!");
+    }
+
+    #[test]
+    fn gb_capacity() {
+        let mut gb = GapBuffer::new(0);
+        assert_eq!(gb.buffer().len(), 1);
+        gb.insert_str("hello");
+        assert_eq!(gb.to_string(), "hello");
+
+        let mut gb = GapBuffer::new(8);
+        assert_eq!(gb.buffer(), &[0; 8]);
+        gb.insert_str("hello");
+        assert_eq!(gb.capacity, 8);
+
+        let mut gb = GapBuffer::with_capacity(10);
+        gb.insert_str("0123456789");
+        assert_eq!(gb.capacity, 11);
+
+        // a large insertion reallocates once, to the needed size
+        let mut gb = GapBuffer::default();
+        gb.insert(&[b'a'; 1000]);
+        assert_eq!(gb.capacity, 1001);
+        gb.reserve(10);
+        assert_eq!(gb.capacity, 1001 + GROW_BY);
+        gb.reserve(10);
+        assert_eq!(gb.capacity, 1001 + GROW_BY);
+
+        gb.set_growth(Growth::Double);
+        gb.seek(500);
+        gb.insert(&[b'b'; 40]);
+        assert_eq!(gb.capacity, 2 * (1001 + GROW_BY));
+        assert_eq!(gb.len(), 1040);
+        assert_eq!(gb.slice(499..541), [&b"a"[..], &[b'b'; 40], b"a"].concat());
+    }
+
+    #[test]
+    fn gb_read_write() {
+        use std::io::{BufRead, Read, Write};
+
+        let mut gb = GapBuffer::default();
+        let name = "world";
+        write!(gb, "hello {name}").unwrap();
+        gb.seek(5);
+        gb.write_all(b",").unwrap();
+        gb.flush().unwrap();
+        assert_eq!(gb.to_string(), "hello, world");
+        assert_eq!(gb.cursor(), 6);
+
+        let mut content = String::new();
+        gb.reader().read_to_string(&mut content).unwrap();
+        assert_eq!(content, "hello, world");
+
+        let mut reader = gb.reader();
+        let mut buf = [0; 4];
+        assert_eq!(reader.read(&mut buf).unwrap(), 4);
+        assert_eq!(&buf, b"hell");
+        assert_eq!(reader.read(&mut buf).unwrap(), 4);
+        assert_eq!(&buf, b"o, w");
+        assert_eq!(reader.fill_buf().unwrap(), b"orld");
+
+        gb.seek(2);
+        let lines: Vec<_> = gb.reader().split(b',').map(Result::unwrap).collect();
+        assert_eq!(lines, [&b"hello"[..], b" world"]);
+    }
+
+    #[test]
+    fn gb_files() {
+        let path = std::env::temp_dir().join(format!("dss-gap-buffer-{}", std::process::id()));
+
+        let mut gb = GapBuffer::default();
+        gb.insert_str("first line\nsecond line\n");
+        gb.seek(4);
+        gb.write_to_file(&path).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), b"first line\nsecond line\n");
+
+        let mut loaded = GapBuffer::from_file(&path).unwrap();
+        assert_eq!(loaded.to_string(), "first line\nsecond line\n");
+        assert_eq!(loaded.cursor(), 0);
+        assert_eq!(loaded.capacity, 23 + GROW_BY + 1);
+        assert!(!loaded.undo());
+
+        std::fs::remove_file(&path).unwrap();
+        assert!(GapBuffer::from_file(&path).is_err());
+    }
+
+    #[test]
+    fn gb_conversions() {
+        let gb = GapBuffer::from("hello");
+        assert_eq!(gb.to_string(), "hello");
+        assert_eq!(gb.cursor(), 5);
+        assert_eq!(gb.gap_len(), GROW_BY);
+
+        let mut gb = GapBuffer::from(String::from("hello"));
+        gb.insert_str(" world");
+        assert_eq!(gb.to_string(), "hello world");
+        assert_eq!(GapBuffer::from(b"bytes".to_vec()).to_string(), "bytes");
+        assert!(GapBuffer::from("").is_empty());
+
+        let mut gb: GapBuffer = "wörld".chars().rev().collect();
+        assert_eq!(gb.to_string(), "dlröw");
+        gb.seek(0);
+        gb.extend(*b"hello ");
+        assert_eq!(gb.to_string(), "hello dlröw");
+        assert!(gb.undo());
+        assert_eq!(gb.to_string(), "dlröw");
+        assert!(!gb.undo());
+    }
+
+    #[test]
+    fn gb_eq() {
+        let mut gb = GapBuffer::from("hello world");
+        gb.seek(3);
+        assert_eq!(gb, "hello world");
+        assert_eq!(gb, *"hello world");
+        assert_eq!("hello world", gb);
+        assert_ne!(gb, "hello");
+        assert_ne!(gb, "hello worle");
+        assert_ne!(gb, "hello world!");
+
+        let mut other = GapBuffer::with_capacity(100);
+        other.insert_str("hello world");
+        other.seek(8);
+        assert_eq!(gb, other);
+        other.delete_left(1);
+        assert_ne!(gb, other);
+        other.undo();
+        assert_eq!(other, gb);
+        assert_eq!(GapBuffer::default(), GapBuffer::new(1));
+    }
+
+    #[test]
+    fn gb_cursors() {
+        let mut gb = GapBuffer::from("one\ntwo\nthree");
+        gb.seek(0);
+        gb.add_cursor(4);
+        gb.add_cursor(8);
+        gb.add_cursor(4);
+        gb.add_cursor(100);
+        assert_eq!(gb.cursors(), [4, 8, 13]);
+        assert!(gb.remove_cursor(13));
+        assert!(!gb.remove_cursor(13));
+
+        gb.insert_at_all_cursors("- ");
+        assert_eq!(gb, "- one\n- two\n- three");
+        assert_eq!(gb.cursor(), 2);
+        assert_eq!(gb.cursors(), [8, 14]);
+
+        // the cursors keep their place through other edits
+        gb.seek(usize::MAX);
+        gb.insert_str("!");
+        gb.seek(0);
+        gb.delete_right(2);
+        assert_eq!(gb.cursors(), [6, 12]);
+        gb.insert_at_all_cursors("* ");
+        assert_eq!(gb, "* one\n- * two\n- * three!");
+
+        assert_eq!(gb.cursors(), [10, 18]);
+
+        // deleting a range merges the cursors in it
+        gb.add_cursor(12);
+        gb.delete_range(4..16);
+        assert_eq!(gb, "* on* three!");
+        assert_eq!(gb.cursors(), [4, 6]);
+        assert!(gb.undo());
+        assert!(gb.undo());
+        assert_eq!(gb, "one\n- two\n- three!");
+        gb.clear_cursors();
+        assert!(gb.cursors().is_empty());
+    }
+
+    #[test]
+    fn gb_words() {
+        let mut gb = GapBuffer::from("  fn wörd_one(x: i32) ->  bool");
+        gb.seek(0);
+
+        assert_eq!(gb.next_word_boundary(), 4);
+        gb.move_word_right(1);
+        assert_eq!(gb.cursor(), 4);
+        gb.move_word_right(1);
+        assert_eq!(gb.cursor(), 14);
+        gb.move_word_right(3);
+        assert_eq!(gb.cursor(), 17);
+        gb.move_word_right(100);
+        assert_eq!(gb.cursor(), gb.len());
+
+        assert_eq!(gb.prev_word_boundary(), 27);
+        gb.move_word_left(1);
+        assert_eq!(gb.cursor(), 27);
+        gb.move_word_left(1);
+        #[cfg(not(feature = "unicode-segmentation"))]
+        assert_eq!(gb.cursor(), 23);
+        gb.seek(14);
+        gb.move_word_left(1);
+        assert_eq!(gb.cursor(), 5);
+        gb.move_word_left(100);
+        assert_eq!(gb.cursor(), 0);
+    }
+
+    #[test]
+    #[cfg(not(feature = "unicode-segmentation"))]
+    fn gb_words_invalid() {
+        let mut gb = GapBuffer::default();
+        gb.insert(b"ab\xff\xffcd ef");
+        gb.seek(0);
+
+        gb.move_word_right(1);
+        assert_eq!(gb.cursor(), 2);
+        gb.move_word_right(1);
+        assert_eq!(gb.cursor(), 4);
+        gb.move_word_right(1);
+        assert_eq!(gb.cursor(), 6);
+        gb.move_word_left(2);
+        assert_eq!(gb.cursor(), 2);
+    }
+
+    #[test]
+    #[cfg(feature = "unicode-segmentation")]
+    fn gb_words_unicode() {
+        let mut gb = GapBuffer::from("can't stop");
+        gb.seek(0);
+
+        gb.move_word_right(1);
+        assert_eq!(gb.cursor(), 5);
+        gb.seek(usize::MAX);
+        gb.move_word_left(2);
+        assert_eq!(gb.cursor(), 0);
+    }
+
+    #[test]
+    fn gb_registers() {
+        let mut gb = GapBuffer::from("one two three");
+
+        let two = gb.cut_range(4..8);
+        assert_eq!(gb, "one three");
+        assert_eq!(gb.cursor(), 4);
+        let one = gb.copy_range(0..3);
+        assert_eq!(gb.register(&two), Some(&b"two "[..]));
+        assert_eq!(
+            gb.kill_ring().collect::<Vec<_>>(),
+            [one.clone(), two.clone()]
+        );
+
+        let three = gb.cut_range_to(4..9, "a");
+        assert_eq!(three, RegisterId::Named("a".to_string()));
+        assert_eq!(gb, "one ");
+        assert!(gb.paste(&two));
+        assert!(gb.paste(&one));
+        assert!(gb.paste(&three));
+        assert_eq!(gb, "one two onethree");
+
+        gb.set_register("a", "!");
+        gb.seek(usize::MAX);
+        assert!(gb.paste(&three));
+        assert!(!gb.paste(&RegisterId::Named("b".to_string())));
+        assert_eq!(gb, "one two onethree!");
+        assert!(gb.undo());
+        assert_eq!(gb, "one two onethree");
+
+        // the oldest entries are dropped from the kill ring
+        for _ in 0..KILL_RING_LEN {
+            gb.copy_range(0..1);
+        }
+        assert_eq!(gb.kill_ring().count(), KILL_RING_LEN);
+        assert_eq!(gb.register(&two), None);
+        assert!(!gb.paste(&one));
+    }
+
+    #[test]
+    fn gb_byte_char_at() {
+        let mut gb = GapBuffer::from("aöb€");
+        // the gap splits the '€'
+        gb.seek(5);
+
+        assert_eq!(gb.byte_at(0), Some(b'a'));
+        assert_eq!(gb.byte_at(3), Some(b'b'));
+        assert_eq!(gb.byte_at(5), Some(0x82));
+        assert_eq!(gb.byte_at(7), None);
+
+        assert_eq!(gb.char_at(0), Some('a'));
+        assert_eq!(gb.char_at(1), Some('ö'));
+        assert_eq!(gb.char_at(2), Some('\u{fffd}'));
+        assert_eq!(gb.char_at(4), Some('€'));
+        assert_eq!(gb.char_at(7), None);
+    }
+
+    #[test]
+    fn gb_truncate_split() {
+        let mut gb = GapBuffer::from("hello world");
+        gb.seek(3);
+
+        let capacity = gb.capacity;
+        let mut tail = gb.split_off(5);
+        assert_eq!(gb, "hello");
+        assert_eq!(gb.cursor(), 3);
+        assert_eq!(tail, " world");
+        assert_eq!(tail.cursor(), 6);
+        assert_eq!(gb.split_off(100), "");
+
+        tail.truncate(3);
+        assert_eq!(tail, " wo");
+        assert_eq!(tail.cursor(), 3);
+        tail.truncate(10);
+        assert_eq!(tail, " wo");
+
+        gb.clear();
+        assert!(gb.is_empty());
+        assert_eq!(gb.cursor(), 0);
+        assert_eq!(gb.capacity, capacity);
+        assert!(gb.undo());
+        assert_eq!(gb, "hello");
+        assert!(gb.undo());
+        assert_eq!(gb, "hello world");
+    }
+
+    #[test]
+    fn gb_insert_byte_full_gap() {
+        let mut gb = GapBuffer::from("abc");
+        gb.shrink_to_fit();
+        gb.seek(0);
+        gb.insert_byte(b'x');
+        gb.insert_byte(b'y');
+        assert_eq!(gb, "xyabc");
+
+        let mut gb = GapBuffer::with_capacity(3);
+        gb.insert_str("abc");
+        gb.insert_byte(b'd');
+        gb.insert_byte(b'e');
+        assert_eq!(gb, "abcde");
+
+        let mut gb = GapBuffer::default();
+        gb.insert(&[b'a'; 100]);
+        assert_eq!(gb.gap_len(), 1);
+        gb.seek(50);
+        gb.insert_byte(b'b');
+        gb.insert_byte(b'c');
+        assert_eq!(gb.len(), 102);
+        assert_eq!(gb.slice(49..53), &b"abca"[..]);
+    }
 }
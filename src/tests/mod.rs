@@ -1,3 +1,8 @@
 mod bsptree;
+mod bsptree_props;
+mod codec;
 mod gap_buffer;
 mod linked_list;
+mod render;
+mod screens;
+mod workspaces;
@@ -0,0 +1,192 @@
+#[cfg(test)]
+mod test {
+    use crate::data_structures::bsptree::*;
+    use crate::render::*;
+
+    #[test]
+    fn render_empty() {
+        let tree: BSPTree = BSPTree::new(Rectangle::new(0, 0, 64, 64));
+
+        assert_eq!(render_ascii(&tree, 4, 2), "    \n    \n");
+    }
+
+    #[test]
+    fn render_split() {
+        let mut tree = BSPTree::new(Rectangle::new(0, 0, 64, 32));
+
+        tree.insert(1).unwrap();
+        tree.insert(2).unwrap();
+
+        assert_eq!(render_ascii(&tree, 8, 2), "....####\n....####\n");
+
+        tree.toggle_split().unwrap();
+        tree.insert(3).unwrap();
+
+        assert_eq!(
+            render_ascii(&tree, 8, 4),
+            "........\n........\n....####\n....####\n"
+        );
+    }
+
+    #[test]
+    fn render_fullscreen() {
+        let mut tree = BSPTree::new(Rectangle::new(0, 0, 64, 32));
+
+        tree.insert(1).unwrap();
+        tree.insert(2).unwrap();
+        tree.focus_coords(0, 0);
+        tree.toggle_fullscreen().unwrap();
+
+        assert_eq!(render_ascii(&tree, 8, 2), "########\n########\n");
+
+        tree.toggle_fullscreen().unwrap();
+        assert_eq!(render_ascii(&tree, 8, 2), "####....\n####....\n");
+    }
+
+    #[test]
+    fn render_floating() {
+        let mut tree = BSPTree::new(Rectangle::new(0, 0, 64, 32));
+        tree.insert(1).unwrap();
+        tree.insert(2).unwrap();
+        tree.float_focused().unwrap();
+        let floating = tree.floating_leaves().next().unwrap();
+        tree.set_floating_rect(floating, Rectangle::new(16, 0, 32, 16))
+            .unwrap();
+
+        let list = draw_list(&tree);
+        assert_eq!(
+            list.last(),
+            Some(&(Rectangle::new(16, 0, 32, 16), Fill::Leaf))
+        );
+        assert_eq!(render_ascii(&tree, 4, 2), "#..#\n####\n");
+
+        // leaves left of or above the tree are clipped
+        tree.set_floating_rect(floating, Rectangle::new(-10, 5, 20, 20))
+            .unwrap();
+        assert_eq!(
+            render_ascii(&tree, 8, 4),
+            ".#######\n.#######\n.#######\n########\n"
+        );
+        tree.set_floating_rect(floating, Rectangle::new(-40, -40, 20, 20))
+            .unwrap();
+        assert_eq!(render_ascii(&tree, 4, 2), "####\n####\n");
+    }
+
+    #[test]
+    fn render_framebuffer() {
+        let mut tree = BSPTree::new(Rectangle::new(0, 0, 640, 480));
+        tree.insert(1).unwrap();
+        tree.insert(2).unwrap();
+
+        let mut fb = Framebuffer::new(640, 480);
+        fb.draw(&tree);
+
+        assert_eq!(fb.get(0, 0), Some(Fill::Leaf));
+        assert_eq!(fb.get(319, 479), Some(Fill::Leaf));
+        assert_eq!(fb.get(320, 0), Some(Fill::Focused));
+        assert_eq!(fb.get(639, 479), Some(Fill::Focused));
+        assert_eq!(fb.get(640, 0), None);
+
+        // the sizes don't overflow
+        let fb = Framebuffer::new(u32::MAX, 0);
+        assert_eq!(fb.get(0, 0), None);
+        assert_eq!(fb.to_ascii(), "");
+    }
+
+    #[test]
+    fn render_svg() {
+        let mut tree = BSPTree::new(Rectangle::new(0, 0, 64, 32));
+        tree.insert(String::from("a&b")).unwrap();
+        tree.insert(String::from("<c>")).unwrap();
+
+        let mut out = vec![];
+        tree.to_svg(&mut out).unwrap();
+        let svg = String::from_utf8(out).unwrap();
+        let lines: Vec<_> = svg.lines().collect();
+
+        assert_eq!(
+            lines,
+            vec![
+                r#"<svg xmlns="http://www.w3.org/2000/svg" width="64" height="32" viewBox="0 0 64 32">"#,
+                r#"  <rect x="0" y="0" width="64" height="32" fill="white"/>"#,
+                r#"  <rect x="0" y="0" width="64" height="32" fill="blue"/>"#,
+                r#"  <rect x="0" y="0" width="32" height="32" fill="red"/>"#,
+                r#"  <rect x="32" y="0" width="32" height="32" fill="green"/>"#,
+                r#"  <text x="16" y="16" text-anchor="middle" dominant-baseline="middle">a&amp;b</text>"#,
+                r#"  <text x="48" y="16" text-anchor="middle" dominant-baseline="middle">&lt;c&gt;</text>"#,
+                "</svg>",
+            ]
+        );
+    }
+
+    #[test]
+    fn render_boxes() {
+        let mut tree = BSPTree::new(Rectangle::new(0, 0, 64, 32));
+        assert_eq!(tree.render_boxes(3, 2), "   \n   \n");
+
+        tree.insert(1).unwrap();
+        tree.insert(2).unwrap();
+        assert_eq!(
+            tree.render_boxes(9, 4),
+            "+---+---+\n\
+             |   |###|\n\
+             |   |###|\n\
+             +---+---+\n"
+        );
+
+        tree.toggle_split().unwrap();
+        tree.insert(3).unwrap();
+        assert_eq!(
+            tree.render_boxes(9, 5),
+            "+---+---+\n\
+             |   |   |\n\
+             |   +---+\n\
+             |   |###|\n\
+             +---+---+\n"
+        );
+
+        // the gaps are applied like in `draw_list`
+        let mut tree = BSPTree::new(Rectangle::new(0, 0, 64, 32));
+        tree.insert(1).unwrap();
+        tree.insert(2).unwrap();
+        tree.set_gaps(Gaps::new(16, 0));
+        assert_eq!(
+            tree.render_boxes(9, 4),
+            "+--+ +--+\n\
+             |  | |##|\n\
+             |  | |##|\n\
+             +--+ +--+\n"
+        );
+
+        // floating leaves are drawn on top, clipped to the tree
+        tree.set_gaps(Gaps::default());
+        tree.float_focused().unwrap();
+        let floating = tree.floating_leaves().next().unwrap();
+        tree.set_floating_rect(floating, Rectangle::new(-16, 8, 48, 16))
+            .unwrap();
+        assert_eq!(
+            tree.render_boxes(9, 5),
+            "+-------+\n\
+             +---+###|\n\
+             |   |###|\n\
+             +---+###|\n\
+             +-------+\n"
+        );
+        tree.set_floating_rect(floating, Rectangle::new(-64, 0, 32, 32))
+            .unwrap();
+        assert_eq!(
+            tree.render_boxes(9, 5),
+            "+-------+\n\
+             |#######|\n\
+             |#######|\n\
+             |#######|\n\
+             +-------+\n"
+        );
+
+        // without any cells nothing is drawn, the same as with `render_ascii`
+        assert_eq!(tree.render_boxes(0, 2), "");
+        assert_eq!(tree.render_boxes(0, 2), render_ascii(&tree, 0, 2));
+        assert_eq!(tree.render_boxes(9, 0), "");
+        assert_eq!(tree.render_boxes(0, 0), "");
+    }
+}
@@ -0,0 +1,127 @@
+#[cfg(test)]
+mod test {
+    use crate::data_structures::bsptree::*;
+    use crate::screens::*;
+
+    /// Two screens next to each other and a third one below the left one.
+    fn screens() -> Screens {
+        Screens::new(&[
+            Rectangle::new(0, 0, 100, 100),
+            Rectangle::new(100, 0, 200, 100),
+            Rectangle::new(0, 100, 100, 50),
+        ])
+    }
+
+    #[test]
+    fn sc_move_focus() {
+        let mut screens = screens();
+        screens.active_mut().insert(1).unwrap();
+        screens
+            .active_mut()
+            .insert_with(4, SplitDirection::Horizontal, Side::Second)
+            .unwrap();
+        screens.active_mut().focus_data(&1);
+        screens.get_mut(1).unwrap().insert(2).unwrap();
+        screens
+            .get_mut(1)
+            .unwrap()
+            .insert_with(3, SplitDirection::Horizontal, Side::Second)
+            .unwrap();
+        screens.get_mut(1).unwrap().focus_data(&2);
+
+        let focused = |screens: &Screens| {
+            let tree = screens.active();
+            *tree[tree.get_focused().unwrap()].get_data().unwrap()
+        };
+
+        assert_eq!(screens.move_focus(MoveDirection::Right), Ok(()));
+        assert_eq!(screens.active_index(), 1);
+        assert_eq!(focused(&screens), 2);
+        assert_eq!(screens.move_focus(MoveDirection::Down), Ok(()));
+        assert_eq!(focused(&screens), 3);
+        assert_eq!(
+            screens.move_focus(MoveDirection::Right),
+            Err(TreeError::NoNeighbor)
+        );
+        assert_eq!(
+            screens.move_focus(MoveDirection::Down),
+            Err(TreeError::NoNeighbor)
+        );
+        assert_eq!(screens.active_index(), 1);
+
+        // the leaf at the height of the focused one is entered
+        screens.move_focus(MoveDirection::Left).unwrap();
+        assert_eq!(screens.active_index(), 0);
+        assert_eq!(focused(&screens), 4);
+
+        // an empty screen becomes active on it's own
+        screens.move_focus(MoveDirection::Down).unwrap();
+        assert_eq!(screens.active_index(), 2);
+        assert!(screens.active().is_empty());
+        screens.move_focus(MoveDirection::Up).unwrap();
+        assert_eq!(screens.active_index(), 0);
+        assert_eq!(focused(&screens), 4);
+        assert_eq!(screens.screen_at(150, 50), Some(1));
+        assert_eq!(screens.screen_at(150, 120), None);
+    }
+
+    #[test]
+    fn sc_swap() {
+        let mut screens = screens();
+        screens.active_mut().insert(1).unwrap();
+        let id = screens.active().get_focused().unwrap();
+        screens.active_mut().set_meta(id, "title", "one").unwrap();
+        screens.active_mut().set_node_border(id, Some(4)).unwrap();
+        screens.get_mut(1).unwrap().insert(2).unwrap();
+
+        assert_eq!(screens.swap(MoveDirection::Right), Ok(()));
+        assert_eq!(screens.active_index(), 1);
+        let tree = screens.active();
+        let focused = tree.get_focused().unwrap();
+        assert_eq!(tree[focused].get_data(), Some(&1));
+        assert_eq!(tree.get_meta(focused, "title"), Some("one"));
+        assert_eq!(tree[focused].get_border(), Some(4));
+        let left = screens.get(0).unwrap();
+        let other = left.get_focused().unwrap();
+        assert_eq!(left[other].get_data(), Some(&2));
+        assert_eq!(left.get_meta(other, "title"), None);
+        assert_eq!(left[other].get_border(), None);
+
+        // into an empty screen the leaf is moved
+        screens.swap(MoveDirection::Left).unwrap();
+        screens.swap(MoveDirection::Down).unwrap();
+        assert_eq!(screens.active_index(), 2);
+        assert_eq!(screens.get(0).unwrap().leaf_count(), 0);
+        let tree = screens.active();
+        assert_eq!(
+            tree.get_meta(tree.get_focused().unwrap(), "title"),
+            Some("one")
+        );
+        assert_eq!(tree[tree.get_focused().unwrap()].get_border(), Some(4));
+
+        assert_eq!(
+            screens.swap(MoveDirection::Down),
+            Err(TreeError::NoNeighbor)
+        );
+        screens.focus_screen(0);
+        assert_eq!(screens.swap(MoveDirection::Down), Err(TreeError::Empty));
+    }
+
+    #[test]
+    fn sc_skip_full_screens() {
+        let mut screens = screens();
+        screens.active_mut().insert(1).unwrap();
+        screens.get_mut(1).unwrap().reserve(MoveDirection::Up, 100);
+
+        assert_eq!(
+            screens.move_focus(MoveDirection::Right),
+            Err(TreeError::NoNeighbor)
+        );
+        assert_eq!(
+            screens.swap(MoveDirection::Right),
+            Err(TreeError::NoNeighbor)
+        );
+        assert_eq!(screens.active_index(), 0);
+        assert_eq!(screens.get(1).unwrap().leaf_count(), 0);
+    }
+}
@@ -0,0 +1,66 @@
+#[cfg(test)]
+mod test {
+    use crate::data_structures::bsptree::*;
+    use crate::workspaces::*;
+
+    #[test]
+    fn ws_switch() {
+        let mut ws = Workspaces::new(3, Rectangle::new(0, 0, 64, 64));
+
+        assert_eq!(ws.len(), 3);
+        ws.active_mut().insert(1).unwrap();
+        assert!(ws.switch_to(2));
+        assert_eq!(ws.active_index(), 2);
+        assert!(ws.active().is_empty());
+        assert!(!ws.switch_to(3));
+        assert_eq!(ws.active_index(), 2);
+
+        ws.switch_to(0);
+        assert_eq!(ws.active().leaf_count(), 1);
+    }
+
+    #[test]
+    fn ws_move_focused() {
+        let mut ws = Workspaces::new(2, Rectangle::new(0, 0, 64, 64));
+
+        ws.active_mut().insert(1).unwrap();
+        ws.active_mut().insert(2).unwrap();
+        let id = ws.active().get_focused().unwrap();
+        ws.active_mut().set_meta(id, "urgent", "yes").unwrap();
+
+        assert_eq!(ws.move_focused_to(0), Ok(false));
+        assert_eq!(ws.move_focused_to(5), Ok(false));
+        assert_eq!(ws.move_focused_to(1), Ok(true));
+        assert_eq!(ws.active_index(), 0);
+        assert_eq!(ws.active().leaf_count(), 1);
+
+        let target = ws.get(1).unwrap();
+        let id = target.get_focused().unwrap();
+        assert_eq!(target[id].get_data(), Some(&2));
+        assert_eq!(target.get_meta(id, "urgent"), Some("yes"));
+        assert_eq!(target[id].get_rect(), Rectangle::new(0, 0, 64, 64));
+    }
+
+    #[test]
+    fn ws_move_too_small() {
+        let mut tree = BSPTree::new(Rectangle::new(0, 0, 64, 64));
+        tree.set_min_size(40, 40);
+        let mut ws = Workspaces::from_template(2, tree);
+
+        ws.active_mut().insert(1).unwrap();
+        ws.switch_to(1);
+        ws.active_mut().insert(2).unwrap();
+        ws.switch_to(0);
+
+        assert_eq!(ws.move_focused_to(1), Err(TreeError::TooSmall));
+        assert_eq!(ws.active().leaf_count(), 1);
+        assert_eq!(ws.get(1).unwrap().leaf_count(), 1);
+
+        ws.resize(Rectangle::new(0, 0, 128, 64));
+        assert!(ws
+            .iter()
+            .all(|t| t.get_size() == Rectangle::new(0, 0, 128, 64)));
+        assert_eq!(ws.move_focused_to(1), Ok(true));
+        assert!(ws.active().is_empty());
+    }
+}
@@ -0,0 +1,130 @@
+use crate::data_structures::bsptree::{BSPTree, NodeData, Rectangle, TreeError};
+
+/// A fixed number of independent `BSPTree`s which share one screen rectangle, only one of which
+/// is active, i.e. shown, at a time. This is how a tiling window manager typically uses the tree.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Workspaces<T = NodeData> {
+    trees: Vec<BSPTree<T>>,
+    active: usize,
+}
+
+impl<T: Clone> Workspaces<T> {
+    /// Create `count` empty workspaces covering `size`, the first one is active.
+    ///
+    /// Panics if `count` is zero.
+    pub fn new(count: usize, size: Rectangle) -> Self {
+        Self::from_template(count, BSPTree::new(size))
+    }
+
+    /// Create `count` workspaces, each of which starts out as a copy of `tree`. This way all the
+    /// workspaces can share settings like the gaps or the minimum leaf size.
+    ///
+    /// Panics if `count` is zero.
+    pub fn from_template(count: usize, tree: BSPTree<T>) -> Self {
+        assert!(count > 0, "there has to be at least one workspace");
+        Self {
+            trees: vec![tree; count],
+            active: 0,
+        }
+    }
+
+    /// Get the number of workspaces.
+    pub fn len(&self) -> usize {
+        self.trees.len()
+    }
+
+    /// There is always at least one workspace, this exists for consistency with `len`.
+    pub fn is_empty(&self) -> bool {
+        self.trees.is_empty()
+    }
+
+    /// Get the index of the active workspace.
+    pub fn active_index(&self) -> usize {
+        self.active
+    }
+
+    /// Get the tree of the active workspace.
+    pub fn active(&self) -> &BSPTree<T> {
+        &self.trees[self.active]
+    }
+
+    /// Get the tree of the active workspace for modification.
+    pub fn active_mut(&mut self) -> &mut BSPTree<T> {
+        &mut self.trees[self.active]
+    }
+
+    /// Get the tree of workspace `n`, `None` if there is no such workspace.
+    pub fn get(&self, n: usize) -> Option<&BSPTree<T>> {
+        self.trees.get(n)
+    }
+
+    /// Get the tree of workspace `n` for modification, `None` if there is no such workspace.
+    pub fn get_mut(&mut self, n: usize) -> Option<&mut BSPTree<T>> {
+        self.trees.get_mut(n)
+    }
+
+    /// Iterate over the trees of all the workspaces, in order.
+    pub fn iter(&self) -> impl Iterator<Item = &BSPTree<T>> {
+        self.trees.iter()
+    }
+
+    /// Get the screen rectangle shared by all the workspaces.
+    pub fn get_size(&self) -> Rectangle {
+        self.active().get_size()
+    }
+
+    /// Resize all the workspaces to `size`.
+    pub fn resize(&mut self, size: Rectangle) {
+        for tree in self.trees.iter_mut() {
+            tree.resize(size);
+        }
+    }
+
+    /// Make workspace `n` the active one. Returns false if there is no such workspace.
+    pub fn switch_to(&mut self, n: usize) -> bool {
+        if n >= self.trees.len() {
+            return false;
+        }
+
+        self.active = n;
+        true
+    }
+
+    /// Remove the focused leaf of the active workspace and insert it, together with it's
+    /// metadata, into workspace `n`, where it becomes focused. The active workspace does not
+    /// change.
+    ///
+    /// Returns `Ok(false)` if nothing was moved, because `n` is the active workspace, there is no
+    /// such workspace or the active workspace is empty. If the leaf does not fit into workspace
+    /// `n` the error is returned and both workspaces are left untouched.
+    pub fn move_focused_to(&mut self, n: usize) -> Result<bool, TreeError> {
+        if n == self.active || n >= self.trees.len() {
+            return Ok(false);
+        }
+
+        let source = &self.trees[self.active];
+        let (data, meta) = match source.get_focused().map(|id| &source[id]) {
+            Some(node) => match node.get_data() {
+                Some(data) => (
+                    data.clone(),
+                    node.meta()
+                        .map(|(k, v)| (k.to_string(), v.to_string()))
+                        .collect::<Vec<_>>(),
+                ),
+                None => return Ok(false),
+            },
+            None => return Ok(false),
+        };
+
+        let target = &mut self.trees[n];
+        target.insert(data)?;
+        if let Some(id) = target.get_focused() {
+            for (key, value) in meta {
+                target.set_meta(id, key, value)?;
+            }
+        }
+
+        self.trees[self.active].delete_focused()?;
+        Ok(true)
+    }
+}